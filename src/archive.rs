@@ -0,0 +1,275 @@
+//! Listing the contents of zip and tar archives as virtual subtrees (`archives` feature).
+
+use crate::node::Node;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// A file stored inside an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path relative to the archive root
+    pub path: PathBuf,
+    /// Uncompressed size in bytes
+    pub size: u64,
+}
+
+/// Archive formats that can be listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Detect a supported archive from its file name
+fn format_of(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Whether a file looks like a supported archive (`.zip`, `.tar`, `.tar.gz`, `.tgz`)
+pub fn is_archive(path: &Path) -> bool {
+    format_of(path).is_some()
+}
+
+/// List the files in an archive with their uncompressed sizes.
+///
+/// Zip archives only need their central directory; tar archives are read header by header,
+/// which for `.tar.gz` means decompressing the whole stream. Entries whose paths would
+/// escape the archive (absolute, or containing `..`) are skipped.
+pub fn list_entries(path: &Path) -> Result<Vec<ArchiveEntry>> {
+    let format = format_of(path)
+        .with_context(|| format!("'{}' is not a supported archive", path.display()))?;
+    let file = File::open(path).with_context(|| format!("Failed to open '{}'", path.display()))?;
+    let reader = BufReader::new(file);
+    match format {
+        Format::Zip => list_zip(reader),
+        Format::Tar => list_tar(reader),
+        Format::TarGz => list_tar(flate2::read::GzDecoder::new(reader)),
+    }
+    .with_context(|| format!("Failed to read archive '{}'", path.display()))
+}
+
+/// Read the entries of a zip archive from its central directory
+fn list_zip(reader: BufReader<File>) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        if let Some(path) = file.enclosed_name().and_then(|p| normalize(&p)) {
+            entries.push(ArchiveEntry {
+                path,
+                size: file.size(),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Read the entries of a tar stream header by header
+fn list_tar(reader: impl Read) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        if let Some(path) = normalize(&entry.path()?) {
+            entries.push(ArchiveEntry {
+                path,
+                size: entry.header().size()?,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Keep only the plain components of an entry path (dropping `./`), or None if it would
+/// escape the archive
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!normalized.as_os_str().is_empty()).then_some(normalized)
+}
+
+/// Nodes for the contents of an archive at `depth`, placed under the archive's own path.
+///
+/// Directories inside the archive get their uncompressed totals; files are only added when
+/// `include_files` is set.
+pub(crate) fn virtual_nodes(
+    archive: &Path,
+    depth: usize,
+    entries: &[ArchiveEntry],
+    include_files: bool,
+) -> Vec<Node> {
+    // Recursive size, file count and size of the files directly inside
+    let mut dirs: HashMap<&Path, (u64, u64, u64)> = HashMap::new();
+    let mut nodes = Vec::new();
+    for entry in entries {
+        // Every ancestor inside the archive, e.g. `a` and `a/b` for `a/b/c.txt`
+        for dir in entry.path.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            let totals = dirs.entry(dir).or_default();
+            totals.0 += entry.size;
+            totals.1 += 1;
+            if Some(dir) == entry.path.parent() {
+                totals.2 += entry.size;
+            }
+        }
+        if include_files {
+            let entry_depth = depth + entry.path.components().count();
+            nodes.push(Node::new(
+                archive.join(&entry.path),
+                entry.size,
+                false,
+                entry_depth,
+            ));
+        }
+    }
+    nodes.extend(
+        dirs.into_iter()
+            .map(|(dir, (size, file_count, self_size))| {
+                Node::new(
+                    archive.join(dir),
+                    size,
+                    true,
+                    depth + dir.components().count(),
+                )
+                .with_self_size(self_size)
+                .with_file_count(file_count)
+            }),
+    );
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_format_of() {
+        assert_eq!(format_of(Path::new("a/backup.ZIP")), Some(Format::Zip));
+        assert_eq!(format_of(Path::new("site.tar")), Some(Format::Tar));
+        assert_eq!(format_of(Path::new("site.tar.gz")), Some(Format::TarGz));
+        assert_eq!(format_of(Path::new("site.tgz")), Some(Format::TarGz));
+        assert_eq!(format_of(Path::new("notes.txt")), None);
+        assert_eq!(format_of(Path::new("zip")), None);
+    }
+
+    #[test]
+    fn test_list_tar_gz() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("backup.tar.gz");
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (name, size) in [("./www/index.html", 300), ("www/img/logo.png", 5000)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(size);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, &vec![0u8; size as usize][..])
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let entries = list_entries(&path).unwrap();
+        assert_eq!(
+            entries,
+            [
+                ArchiveEntry {
+                    path: PathBuf::from("www/index.html"),
+                    size: 300
+                },
+                ArchiveEntry {
+                    path: PathBuf::from("www/img/logo.png"),
+                    size: 5000
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_corrupt_archive() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("broken.zip");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"not a zip")
+            .unwrap();
+        let err = list_entries(&path).unwrap_err();
+        assert!(format!("{:#}", err).contains("broken.zip"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("./a/b")), Some(PathBuf::from("a/b")));
+        assert_eq!(normalize(Path::new("../escape")), None);
+        assert_eq!(normalize(Path::new("/etc/passwd")), None);
+        assert_eq!(normalize(Path::new(".")), None);
+    }
+
+    #[test]
+    fn test_virtual_nodes() {
+        let entries = [
+            ArchiveEntry {
+                path: PathBuf::from("www/index.html"),
+                size: 300,
+            },
+            ArchiveEntry {
+                path: PathBuf::from("www/img/logo.png"),
+                size: 5000,
+            },
+            ArchiveEntry {
+                path: PathBuf::from("README"),
+                size: 10,
+            },
+        ];
+        let archive = Path::new("/data/site.zip");
+
+        let dirs = virtual_nodes(archive, 2, &entries, false);
+        let find = |nodes: &[Node], path: &str| {
+            nodes
+                .iter()
+                .find(|n| n.path == Path::new(path))
+                .map(|n| (n.size, n.file_count, n.depth, n.is_dir))
+        };
+        assert_eq!(dirs.len(), 2);
+        assert_eq!(find(&dirs, "/data/site.zip/www"), Some((5300, 2, 3, true)));
+        assert_eq!(
+            find(&dirs, "/data/site.zip/www/img"),
+            Some((5000, 1, 4, true))
+        );
+
+        let all = virtual_nodes(archive, 2, &entries, true);
+        assert_eq!(all.len(), 5);
+        assert_eq!(find(&all, "/data/site.zip/README"), Some((10, 1, 3, false)));
+    }
+}
@@ -0,0 +1,186 @@
+//! On-disk cache of directory listings used for incremental rescans.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Version of the cache format; caches with a different version are ignored
+pub const CACHE_VERSION: u32 = 2;
+
+/// File sizes recorded per directory, keyed by the directory's modification time
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanCache {
+    /// Cache format version
+    pub version: u32,
+    /// Scanner options that affect which files were recorded
+    pub options: String,
+    /// Recorded directories
+    pub dirs: Vec<CachedDir>,
+}
+
+/// A directory and the files directly inside it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedDir {
+    /// Absolute path to the directory
+    pub path: PathBuf,
+    /// Modification time when the directory was read
+    pub modified: SystemTime,
+    /// Files directly inside the directory
+    pub files: Vec<CachedFile>,
+}
+
+/// Sizes recorded for a single file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedFile {
+    /// File name within its directory
+    pub name: String,
+    /// Size on disk in bytes
+    pub disk_size: u64,
+    /// Apparent (logical) size in bytes
+    pub apparent_size: u64,
+    /// Modification time, if available
+    pub modified: Option<SystemTime>,
+}
+
+/// Lookup tables over a loaded cache
+#[derive(Default)]
+pub(crate) struct CacheIndex<'a> {
+    dirs: HashMap<&'a Path, (SystemTime, HashMap<&'a str, &'a CachedFile>)>,
+}
+
+impl Default for ScanCache {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl ScanCache {
+    /// Create an empty cache for the given scanner options
+    pub fn new(options: String) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            options,
+            dirs: Vec::new(),
+        }
+    }
+
+    /// Load a cache file, failing if it is unreadable, corrupt, or from another version
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open cache '{}'", path.display()))?;
+        let cache: Self = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to parse cache '{}'", path.display()))?;
+        if cache.version != CACHE_VERSION {
+            anyhow::bail!(
+                "Cache '{}' has version {} (expected {})",
+                path.display(),
+                cache.version,
+                CACHE_VERSION
+            );
+        }
+        Ok(cache)
+    }
+
+    /// Write the cache to a file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create cache '{}'", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)
+            .with_context(|| format!("Failed to write cache '{}'", path.display()))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Add the directories recorded by another scan
+    pub fn merge(&mut self, other: ScanCache) {
+        self.options = other.options;
+        self.dirs.extend(other.dirs);
+    }
+
+    /// Build lookup tables for the recorded directories
+    pub(crate) fn index(&self) -> CacheIndex<'_> {
+        let dirs = self
+            .dirs
+            .iter()
+            .map(|dir| {
+                let files = dir.files.iter().map(|f| (f.name.as_str(), f)).collect();
+                (dir.path.as_path(), (dir.modified, files))
+            })
+            .collect();
+        CacheIndex { dirs }
+    }
+}
+
+impl CacheIndex<'_> {
+    /// Whether a directory was recorded with the given modification time
+    pub(crate) fn is_unchanged(&self, dir: &Path, modified: SystemTime) -> bool {
+        self.dirs
+            .get(dir)
+            .is_some_and(|(cached, _)| *cached == modified)
+    }
+
+    /// Recorded sizes of a file inside a directory
+    pub(crate) fn file(&self, dir: &Path, name: &str) -> Option<&CachedFile> {
+        self.dirs
+            .get(dir)
+            .and_then(|(_, files)| files.get(name).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+
+        let mut cache = ScanCache::new("opts".into());
+        cache.dirs.push(CachedDir {
+            path: PathBuf::from("/data"),
+            modified: SystemTime::UNIX_EPOCH,
+            files: vec![CachedFile {
+                name: "a.txt".into(),
+                disk_size: 4096,
+                apparent_size: 10,
+                modified: None,
+            }],
+        });
+        cache.save(&path).unwrap();
+
+        let loaded = ScanCache::load(&path).unwrap();
+        let index = loaded.index();
+        assert_eq!(loaded.options, "opts");
+        assert!(index.is_unchanged(Path::new("/data"), SystemTime::UNIX_EPOCH));
+        assert!(!index.is_unchanged(Path::new("/data"), SystemTime::now()));
+        assert_eq!(
+            index
+                .file(Path::new("/data"), "a.txt")
+                .unwrap()
+                .apparent_size,
+            10
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_corrupt_and_old_versions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(ScanCache::load(&path).is_err());
+
+        std::fs::write(&path, r#"{"version":0,"options":"","dirs":[]}"#).unwrap();
+        let err = ScanCache::load(&path).unwrap_err();
+        assert!(err.to_string().contains("version 0"));
+
+        assert!(ScanCache::load(&dir.path().join("missing.json")).is_err());
+    }
+}
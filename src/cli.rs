@@ -1,7 +1,26 @@
 //! Command-line argument parsing using clap derive macros.
 
-use clap::Parser;
-use std::path::PathBuf;
+use crate::config;
+use crate::units::parse_human_size;
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, Parser, ValueEnum};
+use glob::Pattern;
+use regex::Regex;
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use terminal_size::Width;
+
+/// Report width when stdout is not a terminal
+const DEFAULT_WIDTH: usize = 70;
+/// Narrowest report fitted to a terminal
+const MIN_WIDTH: usize = 40;
+/// Widest report fitted to a terminal
+const MAX_WIDTH: usize = 160;
+/// Most decimal places accepted by --precision
+const MAX_PRECISION: usize = 6;
 
 /// A fast, cross-platform CLI tool for analyzing disk usage.
 ///
@@ -11,32 +30,742 @@ use std::path::PathBuf;
 #[command(name = "disk-scanner")]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Target directory to scan (defaults to current directory)
+    /// Target directories to scan (defaults to current directory)
     #[arg(value_name = "PATH", default_value = ".")]
-    pub path: PathBuf,
+    pub paths: Vec<PathBuf>,
+
+    /// Read newline-separated directories to scan from standard input
+    #[arg(long, conflicts_with = "paths")]
+    pub stdin: bool,
+
+    /// Only check that each path can be scanned, print its device and permissions, and exit
+    #[arg(
+        long,
+        conflicts_with_all = ["merge", "interactive", "tree", "format", "output", "compare", "cache", "summary_only", "empty_dirs", "stream", "fail_on_error", "by_type", "duplicates", "top_files", "all"]
+    )]
+    pub check: bool,
 
-    /// Number of top items to display
+    /// Combine all scanned paths into a single report
+    #[arg(long)]
+    pub merge: bool,
+
+    /// Number of top items to display (0 for all)
     #[arg(short = 'n', long = "count", default_value = "10")]
     pub count: usize,
 
+    /// Keep only the N largest entries in memory while building results (0 for no cap)
+    #[arg(
+        long = "max-results",
+        value_name = "N",
+        conflicts_with_all = ["depth", "min_depth", "older_than", "newer_than", "sort", "top_files", "tree", "interactive", "empty_dirs", "compare"]
+    )]
+    pub max_results: Option<usize>,
+
     /// Maximum depth to display (unlimited if not specified)
     #[arg(short = 'd', long = "depth")]
     pub depth: Option<usize>,
 
+    /// Stop descending below this depth while scanning (sizes then leave out deeper files)
+    #[arg(long = "max-scan-depth", value_name = "N")]
+    pub max_scan_depth: Option<usize>,
+
+    /// Minimum depth to display (hides shallower entries)
+    #[arg(long = "min-depth", value_name = "N")]
+    pub min_depth: Option<usize>,
+
+    /// Only show entries at least this large (e.g. 500, 10K, 100MB, 1.5GiB)
+    #[arg(long = "min-size", value_name = "SIZE", value_parser = parse_human_size)]
+    pub min_size: Option<u64>,
+
+    /// Only show directories containing at least N files (counted recursively)
+    #[arg(long = "min-files", value_name = "N", conflicts_with = "top_files")]
+    pub min_files: Option<u64>,
+
+    /// Hide zero-byte files and directories (including ones emptied by filters)
+    #[arg(long = "exclude-empty", conflicts_with = "empty_dirs")]
+    pub exclude_empty: bool,
+
+    /// Only show entries last modified longer ago than this (e.g. 30d, 6mo, 1y)
+    #[arg(long = "older-than", value_name = "AGE", value_parser = parse_age)]
+    pub older_than: Option<Duration>,
+
+    /// Only show entries modified within this period (e.g. 12h, 7d, 2w)
+    #[arg(long = "newer-than", value_name = "AGE", value_parser = parse_age)]
+    pub newer_than: Option<Duration>,
+
+    /// Sort key for results
+    #[arg(short = 's', long = "sort", value_enum, default_value_t = SortKey::Size)]
+    pub sort: SortKey,
+
+    /// Reverse the sort order (smallest first for size and count, Z-A for name, deepest
+    /// first for depth)
+    #[arg(short = 'r', long = "reverse", visible_alias = "ascending")]
+    pub reverse: bool,
+
     /// Show files in addition to directories
     #[arg(short, long)]
     pub all: bool,
 
-    /// Number of threads to use (defaults to number of CPU cores)
-    #[arg(short = 't', long = "threads")]
+    /// Also list the scanned directory itself (as ".") with its total size
+    #[arg(long = "include-root", conflicts_with = "top_files")]
+    pub include_root: bool,
+
+    /// List the contents of zip and tar archives (.zip, .tar, .tar.gz, .tgz) under their paths,
+    /// with uncompressed sizes that don't count towards the totals
+    #[cfg(feature = "archives")]
+    #[arg(long = "into-archives")]
+    pub into_archives: bool,
+
+    /// Show only the largest individual files anywhere in the tree
+    #[arg(long, conflicts_with = "all")]
+    pub top_files: bool,
+
+    /// Show files matching a glob pattern alongside the directories, without every other file
+    /// as with --all (repeatable, e.g. --show-files-matching '*.iso')
+    #[arg(
+        long,
+        value_name = "GLOB",
+        value_parser = parse_glob,
+        conflicts_with_all = ["all", "top_files"]
+    )]
+    pub show_files_matching: Vec<Pattern>,
+
+    /// Report apparent sizes (file length) instead of size on disk
+    #[arg(long)]
+    pub apparent_size: bool,
+
+    /// Round each file's apparent size up to a multiple of this block size (e.g. 4096, 4K),
+    /// to estimate usage on another filesystem (0 for size on disk)
+    #[arg(
+        long = "block-size",
+        value_name = "BYTES",
+        value_parser = parse_human_size,
+        conflicts_with = "apparent_size"
+    )]
+    pub block_size: Option<u64>,
+
+    /// Follow symbolic links (cycles are skipped; sizes may be double-counted
+    /// when several links point at the same directory)
+    #[arg(short = 'L', long)]
+    pub follow_symlinks: bool,
+
+    /// Don't descend into directories on other filesystems, like `du -x` (Unix only)
+    #[arg(short = 'x', long = "one-file-system")]
+    pub one_file_system: bool,
+
+    /// Note where the scan crossed into another filesystem, such as a mounted drive (Unix only)
+    #[arg(long, conflicts_with = "one_file_system")]
+    pub same_device_warning: bool,
+
+    /// Count files with several hard links only once, like `du` (Unix only)
+    #[arg(long)]
+    pub count_links_once: bool,
+
+    /// Descend into pseudo-filesystems such as /proc, /sys and /dev, which are
+    /// skipped by default (Linux only)
+    #[arg(long)]
+    pub scan_special: bool,
+
+    /// Cache file for incremental rescans: file sizes are reused for directories whose
+    /// modification time is unchanged (created on first use)
+    #[arg(long, value_name = "FILE")]
+    pub cache: Option<PathBuf>,
+
+    /// Number of threads to use (defaults to number of CPU cores); 1 walks the tree serially
+    /// in name order, for reproducible debugging and benchmarks
+    #[arg(short = 't', long = "threads", value_name = "N", value_parser = parse_threads)]
     pub threads: Option<usize>,
+
+    /// Skip entries matching a glob pattern (repeatable, e.g. --exclude node_modules)
+    #[arg(short = 'e', long = "exclude", value_name = "GLOB", value_parser = parse_glob)]
+    pub exclude: Vec<Pattern>,
+
+    /// Only count files whose full path matches a regex (repeatable, e.g. '\.rs$')
+    #[arg(long = "include-regex", value_name = "REGEX", value_parser = parse_regex)]
+    pub include_regex: Vec<Regex>,
+
+    /// Skip entries whose full path matches a regex (repeatable; wins over --include-regex)
+    #[arg(long = "exclude-regex", value_name = "REGEX", value_parser = parse_regex)]
+    pub exclude_regex: Vec<Regex>,
+
+    /// Only count files with this extension (repeatable, case-insensitive, e.g. --ext jpg)
+    #[arg(long = "ext", value_name = "EXTENSION")]
+    pub extensions: Vec<String>,
+
+    /// Skip hidden files and directories (dotfiles; hidden attribute on Windows)
+    #[arg(long)]
+    pub no_hidden: bool,
+
+    /// Hide files starting with `.` from the results, still scanning hidden directories and
+    /// counting every file in sizes
+    #[arg(long = "exclude-hidden-files")]
+    pub exclude_hidden_files: bool,
+
+    /// Skip files and directories ignored by .gitignore files
+    #[arg(long)]
+    pub respect_gitignore: bool,
+
+    /// Summarize total size and file count per file extension instead of listing paths
+    #[arg(long)]
+    pub by_type: bool,
+
+    /// Show how many files fall in each size range (and their total size) as a bar chart
+    /// instead of listing paths
+    #[arg(long, conflicts_with_all = ["by_type", "duplicates", "compare", "tree", "empty_dirs", "summary_only", "interactive"])]
+    pub histogram: bool,
+
+    /// Show the number and total size of directories at each depth instead of listing paths
+    #[arg(long = "by-depth", conflicts_with_all = ["by_type", "histogram", "duplicates", "compare", "tree", "empty_dirs", "summary_only", "interactive", "max_results", "top_files"])]
+    pub by_depth: bool,
+
+    /// Size boundaries between --histogram buckets, in increasing order
+    #[arg(
+        long,
+        value_name = "SIZES",
+        value_parser = parse_bounds,
+        default_value = "1K,10K,100K,1M,10M,100M,1G"
+    )]
+    pub histogram_buckets: SizeBounds,
+
+    /// Find files with identical content and report the space they waste
+    #[arg(long)]
+    pub duplicates: bool,
+
+    /// Show size changes since a report saved with --format json (save it with --count 0
+    /// so every directory is included)
+    #[arg(long, value_name = "BASELINE_JSON", conflicts_with_all = ["by_type", "duplicates", "tree"])]
+    pub compare: Option<PathBuf>,
+
+    /// Add a column with each directory's average file size (table output)
+    #[arg(long = "show-avg")]
+    pub show_avg: bool,
+
+    /// Add a column with the size of the files directly in each directory, leaving out
+    /// subdirectories (table output)
+    #[arg(long = "self-size")]
+    pub self_size: bool,
+
+    /// Add a column with how long ago each entry was modified, e.g. "3 days ago"
+    /// (directories use their newest file; table output)
+    #[arg(long = "show-mtime")]
+    pub show_mtime: bool,
+
+    /// Color sizes by magnitude: green below the first --heat-thresholds size, yellow up to
+    /// the second, red above it
+    #[arg(long)]
+    pub heat: bool,
+
+    /// Sizes where --heat switches to yellow and to red
+    #[arg(
+        long = "heat-thresholds",
+        value_name = "WARM,HOT",
+        value_parser = parse_thresholds,
+        default_value = "100MB,1GB"
+    )]
+    pub heat_thresholds: (u64, u64),
+
+    /// Display results as a directory tree (--count limits entries per level)
+    #[arg(long)]
+    pub tree: bool,
+
+    /// In the tree, fold directories smaller than SIZE into one summary row per parent
+    #[arg(long, value_name = "SIZE", value_parser = parse_human_size, requires = "tree")]
+    pub collapse_under: Option<u64>,
+
+    /// List every directory with no files beneath it, sorted by path
+    #[arg(
+        long = "empty-dirs",
+        conflicts_with_all = ["interactive", "tree", "by_type", "duplicates", "compare", "top_files", "all", "summary_only"]
+    )]
+    pub empty_dirs: bool,
+
+    /// Only print the totals (size, file and directory counts), not the entry list
+    #[arg(
+        long,
+        conflicts_with_all = ["interactive", "tree", "by_type", "duplicates", "compare", "top_files"]
+    )]
+    pub summary_only: bool,
+
+    /// Browse the results in an interactive terminal UI (multiple paths are merged)
+    #[arg(short = 'i', long, conflicts_with_all = ["format", "tree", "output", "compare"])]
+    pub interactive: bool,
+
+    /// After the report, pick listed entries by number to delete (dry run unless confirmed)
+    #[arg(
+        long,
+        conflicts_with_all = ["interactive", "stdin", "format", "output", "tree", "summary_only", "empty_dirs", "by_type", "duplicates", "compare", "stream"]
+    )]
+    pub interactive_delete: bool,
+
+    /// Delete the entries selected with --interactive-delete without asking for confirmation
+    #[arg(long, requires = "interactive_delete")]
+    pub yes: bool,
+
+    /// Disable colored output (also honors the NO_COLOR environment variable)
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Show file icons and colors by type (e.g. 🎬 video, 📦 archives); off without colors
+    #[arg(long)]
+    pub icons: bool,
+
+    /// Print files of at least --min-size to stdout as they are found, before the report
+    #[arg(long, conflicts_with = "interactive")]
+    pub stream: bool,
+
+    /// Count entries first, then show a progress bar with percent and ETA (about twice as slow)
+    #[arg(long = "progress-bar", conflicts_with = "quiet")]
+    pub progress_bar: bool,
+
+    /// Minimum milliseconds between progress updates [default: 50, 0 = as fast as possible]
+    #[arg(long, value_name = "MS")]
+    pub progress_interval: Option<u64>,
+
+    /// Don't show the progress spinner on stderr
+    #[arg(short = 'q', long)]
+    pub quiet: bool,
+
+    /// Show the progress spinner even when stderr is not a terminal or CI is set
+    #[arg(long, conflicts_with = "quiet")]
+    pub force_progress: bool,
+
+    /// List paths that could not be read (first 1000) after the report
+    #[arg(long = "show-errors")]
+    pub show_errors: bool,
+
+    /// Don't look up the capacity of the scanned volume
+    #[arg(long = "no-disk-info")]
+    pub no_disk_info: bool,
+
+    /// Print per-thread entry counts, lock waits and phase timings to stderr after the scan
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Print the time spent walking, aggregating, sorting and displaying to stderr at the end
+    #[arg(long, conflicts_with_all = ["interactive", "interactive_delete"])]
+    pub profile: bool,
+
+    /// Exit with status 2 if any entry could not be read
+    #[arg(long = "fail-on-error")]
+    pub fail_on_error: bool,
+
+    /// Output format
+    #[arg(short = 'f', long = "format", value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Instead of a report, print the full path of each listed entry followed by a NUL byte,
+    /// for `xargs -0`
+    #[arg(
+        short = '0',
+        long,
+        conflicts_with_all = ["format", "interactive", "interactive_delete", "tree", "summary_only", "by_type", "histogram", "by_depth", "duplicates", "compare", "stream"]
+    )]
+    pub print0: bool,
+
+    /// Size units: binary (KiB, MiB = powers of 1024), si (KB, MB = powers of 1000), or bytes
+    #[arg(long = "units", value_enum, default_value_t = Units::Binary)]
+    pub units: Units,
+
+    /// Which part of long paths to replace with "..."
+    #[arg(long = "truncate", value_name = "MODE", value_enum, default_value_t = Truncate::Start)]
+    pub truncate: Truncate,
+
+    /// Show paths relative to this directory instead of the scanned one (paths outside it
+    /// are shown in full)
+    #[arg(long, value_name = "BASE")]
+    pub relative_to: Option<PathBuf>,
+
+    /// Show full paths instead of paths relative to the scanned directory (JSON output
+    /// always has full paths)
+    #[arg(long, conflicts_with = "relative_to")]
+    pub absolute_paths: bool,
+
+    /// Report width in columns [default: terminal width, or 70 when not a terminal]
+    #[arg(long, value_name = "COLS", value_parser = clap::value_parser!(u16).range(40..))]
+    pub width: Option<u16>,
+
+    /// Print sizes as plain byte counts (same as --units bytes)
+    #[arg(long, conflicts_with = "units")]
+    pub bytes: bool,
+
+    /// Number separators: comma (1,234.5), period (1.234,5), space (1 234,5), none (1234.5), or
+    /// a locale name such as de_DE [default: from LC_ALL, LC_NUMERIC or LANG, else comma]
+    #[arg(long, value_name = "LOCALE", value_parser = parse_locale)]
+    pub locale: Option<NumberFormat>,
+
+    /// Decimal places in sizes, from 0 to 6 [default: up to 2, without trailing zeros]
+    #[arg(long, value_name = "N", value_parser = parse_precision)]
+    pub precision: Option<usize>,
+
+    /// Write results to a file instead of stdout (colors are disabled)
+    #[arg(short = 'o', long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Send the JSON report to unix:PATH or tcp:HOST:PORT instead of stdout
+    #[cfg(feature = "emit")]
+    #[arg(
+        long,
+        value_name = "ENDPOINT",
+        value_parser = crate::emit::parse_endpoint,
+        conflicts_with_all = ["format", "output", "interactive", "interactive_delete", "stream"]
+    )]
+    pub emit: Option<crate::emit::Endpoint>,
+
+    /// Seconds to wait when connecting to and writing to the --emit endpoint
+    #[cfg(feature = "emit")]
+    #[arg(long, value_name = "SECS", default_value_t = 10, requires = "emit")]
+    pub emit_timeout: u64,
+
+    /// Read default options from this TOML file instead of ~/.config/disk-scanner/config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Ignore the default config file
+    #[arg(long, conflicts_with = "config")]
+    pub no_config: bool,
+}
+
+/// Key used to order results
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Total size in bytes
+    #[default]
+    Size,
+    /// Number of files contained
+    Count,
+    /// Size of the files directly inside, leaving out subdirectories
+    SelfSize,
+    /// Path, alphabetically
+    Name,
+    /// Nesting depth, shallowest first
+    Depth,
+}
+
+/// Output format for scan results
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored human-readable table
+    #[default]
+    Table,
+    /// Single JSON object on stdout
+    Json,
+    /// One JSON object per line (JSON Lines), ending with a summary line
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+    /// GitHub-flavored Markdown table, for pasting into docs and issues
+    Markdown,
+    /// Standalone HTML page with sortable tables, for sharing reports
+    Html,
+    /// Length-delimited protobuf records (see proto/disk_scanner.proto)
+    #[cfg(feature = "protobuf")]
+    Bin,
+    /// ncdu's JSON export format (version 1.2), for browsing with `ncdu -f`
+    Ncdu,
+}
+
+/// Units used for human-readable sizes
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    /// Powers of 1024 (KiB, MiB, GiB)
+    #[default]
+    Binary,
+    /// Powers of 1000 (KB, MB, GB)
+    Si,
+    /// Plain integer byte counts without a unit
+    Bytes,
+}
+
+/// Increasing size boundaries between histogram buckets
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeBounds(pub Vec<u64>);
+
+/// Separators for grouping thousands and for decimals, and decimal places, in printed numbers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Between groups of three digits (None = no grouping)
+    pub separator: Option<char>,
+    /// Before the fractional part of sizes
+    pub decimal: char,
+    /// Decimal places in sizes (None = up to two, without trailing zeros)
+    pub precision: Option<usize>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::COMMA
+    }
+}
+
+impl NumberFormat {
+    /// `1,234.5`
+    pub const COMMA: Self = Self {
+        separator: Some(','),
+        decimal: '.',
+        precision: None,
+    };
+    /// `1.234,5`
+    pub const PERIOD: Self = Self {
+        separator: Some('.'),
+        decimal: ',',
+        precision: None,
+    };
+    /// `1 234,5`
+    pub const SPACE: Self = Self {
+        separator: Some(' '),
+        decimal: ',',
+        precision: None,
+    };
+    /// `1234.5`
+    pub const NONE: Self = Self {
+        separator: None,
+        decimal: '.',
+        precision: None,
+    };
+
+    /// Conventions of a POSIX locale name such as `de_DE.UTF-8`, by language.
+    ///
+    /// Only the common cases are known; other languages, `C` and `POSIX` use commas.
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "de" | "es" | "it" | "nl" | "pt" | "da" | "el" | "id" | "tr" | "ro" | "hr" | "sl"
+            | "sr" | "vi" => Self::PERIOD,
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "nn" | "no" | "uk" | "hu"
+            | "bg" | "lt" | "lv" | "et" => Self::SPACE,
+            _ => Self::COMMA,
+        }
+    }
+
+    /// Use this decimal separator in an already formatted size such as `1.50 KiB`
+    pub fn localize(&self, formatted: String) -> String {
+        if self.decimal == '.' {
+            formatted
+        } else {
+            formatted.replace('.', self.decimal.encode_utf8(&mut [0; 4]))
+        }
+    }
+}
+
+/// Part of a long path that is elided to fit the display width
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Truncate {
+    /// Drop the beginning, keeping the file name (`...deep/file.txt`)
+    #[default]
+    Start,
+    /// Drop the middle, keeping the first and last components (`home/.../file.txt`)
+    Middle,
+    /// Drop the end, keeping the leading directories (`home/user/...`)
+    End,
 }
 
 impl Args {
-    /// Parse command-line arguments
-    pub fn parse_args() -> Self {
-        Self::parse()
+    /// Parse command-line arguments, taking options that aren't given from the config file,
+    /// and exit with status 1 on usage errors.
+    ///
+    /// clap would exit with 2, which is reserved for scans with inaccessible entries.
+    pub fn parse_args() -> Result<Self> {
+        let argv: Vec<OsString> = std::env::args_os().collect();
+        let matches = Self::command()
+            .try_get_matches_from(&argv)
+            .unwrap_or_else(|err| exit_usage(err, None));
+
+        let path = match matches.get_one::<PathBuf>("config") {
+            Some(path) => Some(path.clone()),
+            None if matches.get_flag("no_config") => None,
+            None => config::default_path().filter(|path| path.is_file()),
+        };
+        let Some(path) = path else {
+            return Ok(Self::try_parse_from(argv).unwrap_or_else(|err| exit_usage(err, None)));
+        };
+
+        // Config options go first so that the command line can't be misread after them
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let defaults = config::to_args(&config::load(&path)?, &Self::command(), given)
+            .with_context(|| format!("Invalid config file '{}'", path.display()))?;
+        let mut argv = argv.into_iter();
+        let full: Vec<OsString> = argv
+            .next()
+            .into_iter()
+            .chain(defaults)
+            .chain(argv)
+            .collect();
+        Ok(Self::try_parse_from(full).unwrap_or_else(|err| exit_usage(err, Some(&path))))
     }
+
+    /// Units for sizes, with --bytes taking precedence
+    pub fn size_units(&self) -> Units {
+        if self.bytes { Units::Bytes } else { self.units }
+    }
+
+    /// Report width: --width, else the terminal's width clamped to 40-160 columns, or 70 when
+    /// stdout is not a terminal
+    pub fn report_width(&self) -> usize {
+        if let Some(width) = self.width {
+            return width as usize;
+        }
+        if self.output.is_some() || !std::io::stdout().is_terminal() {
+            return DEFAULT_WIDTH;
+        }
+        terminal_size::terminal_size().map_or(DEFAULT_WIDTH, |(Width(width), _)| {
+            (width as usize).clamp(MIN_WIDTH, MAX_WIDTH)
+        })
+    }
+
+    /// Number separators: --locale, else the first of LC_ALL, LC_NUMERIC and LANG that is set,
+    /// else commas; with the decimal places from --precision
+    pub fn number_format(&self) -> NumberFormat {
+        let separators = self.locale.unwrap_or_else(|| {
+            ["LC_ALL", "LC_NUMERIC", "LANG"]
+                .iter()
+                .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+                .map_or_else(NumberFormat::default, |locale| {
+                    NumberFormat::from_locale(&locale)
+                })
+        });
+        NumberFormat {
+            precision: self.precision,
+            ..separators
+        }
+    }
+
+    /// Whether to draw the progress spinner: always with --force-progress, else only when stderr
+    /// is a terminal outside CI and --quiet is not given
+    pub fn show_progress(&self) -> bool {
+        self.force_progress || (!self.quiet && std::io::stderr().is_terminal() && !running_in_ci())
+    }
+
+    /// Whether to color output: off with --no-color, a non-empty NO_COLOR, --output, or when
+    /// stdout is not a terminal
+    pub fn use_color(&self) -> bool {
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        !self.no_color && !no_color_env && self.output.is_none() && std::io::stdout().is_terminal()
+    }
+}
+
+/// Whether the `CI` environment variable is set, as CI services do (`false` and `0` don't count)
+fn running_in_ci() -> bool {
+    std::env::var("CI").is_ok_and(|value| !matches!(value.as_str(), "" | "false" | "0"))
+}
+
+/// Print a usage error (noting the config file that supplied defaults) and exit
+fn exit_usage(err: clap::Error, config: Option<&Path>) -> ! {
+    let _ = err.print();
+    if !err.use_stderr() {
+        std::process::exit(0);
+    }
+    if let Some(path) = config {
+        eprintln!("note: defaults were read from '{}'", path.display());
+    }
+    std::process::exit(1);
+}
+
+/// Parse a pair of increasing sizes such as `100MB,1GB` for `--heat-thresholds`
+fn parse_thresholds(s: &str) -> Result<(u64, u64), String> {
+    let (warm, hot) = s
+        .split_once(',')
+        .ok_or_else(|| format!("invalid thresholds '{}': expected WARM,HOT", s))?;
+    let (warm, hot) = (parse_human_size(warm)?, parse_human_size(hot)?);
+    if warm >= hot {
+        return Err(format!(
+            "invalid thresholds '{}': the first size must be smaller",
+            s
+        ));
+    }
+    Ok((warm, hot))
+}
+
+/// Parse a separator style (`comma`, `period`, `space`, `none`) or a locale name for `--locale`
+fn parse_locale(s: &str) -> Result<NumberFormat, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "comma" => Ok(NumberFormat::COMMA),
+        "period" => Ok(NumberFormat::PERIOD),
+        "space" => Ok(NumberFormat::SPACE),
+        "none" => Ok(NumberFormat::NONE),
+        "" => Err("invalid locale '': expected comma, period, space, none or a locale name".into()),
+        locale => Ok(NumberFormat::from_locale(locale)),
+    }
+}
+
+/// Parse a thread count for `--threads`, which must be at least 1
+fn parse_threads(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(0) => Err("invalid thread count '0': use 1 for a single-threaded scan".to_string()),
+        Ok(threads) => Ok(threads),
+        Err(_) => Err(format!("invalid thread count '{}': expected a number", s)),
+    }
+}
+
+/// Parse a number of decimal places for `--precision`, clamped to 0-6
+fn parse_precision(s: &str) -> Result<usize, String> {
+    s.trim()
+        .parse::<usize>()
+        .map(|places| places.min(MAX_PRECISION))
+        .map_err(|_| format!("invalid precision '{}': expected a number from 0 to 6", s))
+}
+
+/// Parse increasing sizes such as `1K,1M,1G` for `--histogram-buckets`
+fn parse_bounds(s: &str) -> Result<SizeBounds, String> {
+    let bounds = s
+        .split(',')
+        .map(parse_human_size)
+        .collect::<Result<Vec<_>, _>>()?;
+    if bounds.first() == Some(&0) || bounds.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(format!(
+            "invalid buckets '{}': sizes must be positive and increasing",
+            s
+        ));
+    }
+    Ok(SizeBounds(bounds))
+}
+
+/// Parse a regular expression for `--include-regex` and `--exclude-regex`
+fn parse_regex(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| format!("invalid regex '{}': {}", s, e))
+}
+
+/// Parse a glob pattern for `--exclude`
+fn parse_glob(s: &str) -> Result<Pattern, String> {
+    Pattern::new(s).map_err(|e| format!("invalid glob pattern '{}': {}", s, e))
+}
+
+/// Parse an age such as `90s`, `30m`, `12h`, `7d`, `2w`, `6mo`, or `1y`.
+///
+/// Months count as 30 days and years as 365 days.
+pub fn parse_age(s: &str) -> Result<Duration, String> {
+    let input = s.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, suffix) = input.split_at(split);
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid age '{}': expected a number with a unit", s))?;
+
+    let seconds: u64 = match suffix.trim().to_ascii_lowercase().as_str() {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "mo" => 30 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        "" => {
+            return Err(format!(
+                "invalid age '{}': missing unit (s, m, h, d, w, mo, y)",
+                s
+            ));
+        }
+        other => return Err(format!("invalid age '{}': unknown unit '{}'", s, other)),
+    };
+
+    Duration::try_from_secs_f64(value * seconds as f64)
+        .map_err(|_| format!("invalid age '{}': too large", s))
 }
 
 #[cfg(test)]
@@ -46,11 +775,96 @@ mod tests {
     #[test]
     fn test_default_args() {
         let args = Args::parse_from(["disk-scanner"]);
-        assert_eq!(args.path, PathBuf::from("."));
+        assert_eq!(args.paths, vec![PathBuf::from(".")]);
+        assert!(!args.merge);
+        assert!(!args.stdin);
+        assert!(!args.include_root);
+        #[cfg(feature = "archives")]
+        assert!(!args.into_archives);
+        #[cfg(feature = "emit")]
+        assert_eq!((&args.emit, args.emit_timeout), (&None, 10));
+        assert!(!args.check);
         assert_eq!(args.count, 10);
         assert_eq!(args.depth, None);
+        assert_eq!(args.min_depth, None);
+        assert_eq!(args.min_size, None);
+        assert_eq!(args.min_files, None);
+        assert!(!args.exclude_empty);
+        assert_eq!(args.config, None);
+        assert!(!args.no_config);
+        assert!(!args.icons);
+        assert_eq!(args.collapse_under, None);
+        assert_eq!(args.max_results, None);
+        assert_eq!(args.max_scan_depth, None);
+        assert_eq!(args.older_than, None);
+        assert_eq!(args.newer_than, None);
         assert_eq!(args.threads, None);
         assert!(!args.all);
+        assert!(!args.top_files);
+        assert_eq!(args.sort, SortKey::Size);
+        assert_eq!(args.units, Units::Binary);
+        assert!(!args.bytes);
+        assert_eq!(args.size_units(), Units::Binary);
+        assert_eq!(args.truncate, Truncate::Start);
+        assert!(!args.reverse);
+        assert!(!args.apparent_size);
+        assert_eq!(args.block_size, None);
+        assert!(!args.follow_symlinks);
+        assert!(args.exclude.is_empty());
+        assert!(args.include_regex.is_empty());
+        assert!(args.exclude_regex.is_empty());
+        assert!(args.extensions.is_empty());
+        assert!(!args.no_hidden);
+        assert!(!args.exclude_hidden_files);
+        assert!(!args.respect_gitignore);
+        assert!(!args.no_color);
+        assert!(!args.quiet);
+        assert!(!args.force_progress);
+        assert!(!args.progress_bar);
+        assert_eq!(args.progress_interval, None);
+        assert!(!args.stream);
+        assert!(!args.show_errors);
+        assert!(!args.fail_on_error);
+        assert!(!args.summary_only);
+        assert!(!args.empty_dirs);
+        assert!(!args.show_avg);
+        assert!(!args.show_mtime);
+        assert!(!args.self_size);
+        assert!(!args.heat);
+        assert_eq!(args.heat_thresholds, (100_000_000, 1_000_000_000));
+        assert!(!args.no_disk_info);
+        assert!(!args.one_file_system);
+        assert!(!args.scan_special);
+        assert!(!args.count_links_once);
+        assert!(!args.same_device_warning);
+        assert!(!args.interactive);
+        assert!(!args.interactive_delete);
+        assert!(!args.stats);
+        assert!(!args.profile);
+        assert_eq!(args.width, None);
+        assert_eq!(args.locale, None);
+        assert_eq!(args.precision, None);
+        assert!(!args.yes);
+        assert!(args.output.is_none());
+        assert!(args.cache.is_none());
+        assert!(args.compare.is_none());
+        assert!(!args.by_type);
+        assert!(!args.histogram);
+        assert!(!args.by_depth);
+        assert_eq!(
+            args.histogram_buckets.0,
+            [
+                1 << 10,
+                10 << 10,
+                100 << 10,
+                1 << 20,
+                10 << 20,
+                100 << 20,
+                1 << 30
+            ]
+        );
+        assert!(!args.duplicates);
+        assert_eq!(args.format, OutputFormat::Table);
     }
 
     #[test]
@@ -65,11 +879,108 @@ mod tests {
             "--all",
             "-t",
             "4",
+            "--format",
+            "json",
+            "--exclude",
+            "node_modules",
+            "-e",
+            "*.log",
         ]);
-        assert_eq!(args.path, PathBuf::from("/some/path"));
+        assert_eq!(args.paths, vec![PathBuf::from("/some/path")]);
         assert_eq!(args.count, 20);
         assert_eq!(args.depth, Some(3));
         assert!(args.all);
         assert_eq!(args.threads, Some(4));
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(args.exclude.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_thresholds() {
+        assert_eq!(
+            parse_thresholds("100MB,1GB"),
+            Ok((100_000_000, 1_000_000_000))
+        );
+        assert_eq!(parse_thresholds("1K, 2K"), Ok((1024, 2048)));
+        assert!(parse_thresholds("1GB").is_err());
+        assert!(parse_thresholds("1GB,1GB").is_err());
+        assert!(parse_thresholds("2GB,1GB").is_err());
+        assert!(parse_thresholds("x,1GB").is_err());
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        assert_eq!(
+            parse_bounds("1K,1MB"),
+            Ok(SizeBounds(vec![1024, 1_000_000]))
+        );
+        assert_eq!(parse_bounds("500"), Ok(SizeBounds(vec![500])));
+        assert!(parse_bounds("1M,1K").is_err());
+        assert!(parse_bounds("1K,1K").is_err());
+        assert!(parse_bounds("0,1K").is_err());
+        assert!(parse_bounds("1K,").is_err());
+    }
+
+    #[test]
+    fn test_parse_precision() {
+        assert_eq!(parse_precision("0"), Ok(0));
+        assert_eq!(parse_precision("4"), Ok(4));
+        assert_eq!(parse_precision("12"), Ok(6));
+        assert!(parse_precision("-1").is_err());
+        assert!(parse_precision("two").is_err());
+    }
+
+    #[test]
+    fn test_parse_threads() {
+        assert_eq!(parse_threads("1"), Ok(1));
+        assert_eq!(parse_threads("8"), Ok(8));
+        assert!(parse_threads("0").unwrap_err().contains("use 1"));
+        assert!(parse_threads("many").is_err());
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(parse_locale("comma"), Ok(NumberFormat::COMMA));
+        assert_eq!(parse_locale("Period"), Ok(NumberFormat::PERIOD));
+        assert_eq!(parse_locale("space"), Ok(NumberFormat::SPACE));
+        assert_eq!(parse_locale("none"), Ok(NumberFormat::NONE));
+        assert_eq!(parse_locale("de_DE.UTF-8"), Ok(NumberFormat::PERIOD));
+        assert_eq!(parse_locale("fr-CA"), Ok(NumberFormat::SPACE));
+        assert_eq!(parse_locale("en_US.UTF-8"), Ok(NumberFormat::COMMA));
+        assert_eq!(parse_locale("C"), Ok(NumberFormat::COMMA));
+        assert!(parse_locale("").is_err());
+
+        assert_eq!(
+            NumberFormat::PERIOD.localize("976.56 KiB".to_string()),
+            "976,56 KiB"
+        );
+        assert_eq!(NumberFormat::NONE.localize("1.5 MB".to_string()), "1.5 MB");
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_age("6MO"), Ok(Duration::from_secs(180 * 86400)));
+        assert_eq!(parse_age("1y"), Ok(Duration::from_secs(365 * 86400)));
+        assert_eq!(parse_age("1.5d"), Ok(Duration::from_secs(36 * 3600)));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("abc").is_err());
+        assert!(parse_age("3x").is_err());
+
+        // Ages beyond what a Duration holds are rejected instead of panicking
+        for input in ["99999999999999y", &format!("{}s", "9".repeat(400))] {
+            let err = parse_age(input).unwrap_err();
+            assert!(err.contains("too large"), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn test_multiple_paths() {
+        let args = Args::parse_from(["disk-scanner", "/a", "/b", "--merge"]);
+        assert_eq!(args.paths, vec![PathBuf::from("/a"), PathBuf::from("/b")]);
+        assert!(args.merge);
     }
 }
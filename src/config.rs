@@ -0,0 +1,130 @@
+//! Default options from a TOML config file (`~/.config/disk-scanner/config.toml`, `--config`).
+
+use anyhow::{Context, Result, bail};
+use clap::{ArgAction, Command};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// The config file used without `--config`: `$XDG_CONFIG_HOME/disk-scanner/config.toml`,
+/// else `~/.config/disk-scanner/config.toml`
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::home_dir()
+                .filter(|home| !home.as_os_str().is_empty())
+                .map(|home| home.join(".config"))
+        })?;
+    Some(config_dir.join("disk-scanner").join("config.toml"))
+}
+
+/// Read a config file into a table of options
+pub fn load(path: &Path) -> Result<toml::Table> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file '{}'", path.display()))?;
+    text.parse()
+        .with_context(|| format!("Failed to parse config file '{}'", path.display()))
+}
+
+/// Turn the options in `config` into command-line arguments for `command`.
+///
+/// Keys are `Args` field names (e.g. `count`, `min_size`); options for which `given` returns
+/// true were set on the command line and are left out so that they take precedence.
+pub fn to_args(
+    config: &toml::Table,
+    command: &Command,
+    given: impl Fn(&str) -> bool,
+) -> Result<Vec<OsString>> {
+    let mut args = Vec::new();
+    for (key, value) in config {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key.as_str() && !arg.is_hide_set())
+            .filter(|_| key != "config" && key != "no_config")
+            .with_context(|| format!("Unknown option '{}' in config file", key))?;
+        if given(key) {
+            continue;
+        }
+
+        let values = match value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let text = match (value, arg.get_action()) {
+                (toml::Value::Boolean(enabled), ArgAction::SetTrue) => {
+                    if *enabled {
+                        args.push(format!("--{}", arg.get_long().unwrap_or(key)).into());
+                    }
+                    continue;
+                }
+                (_, ArgAction::SetTrue) => {
+                    bail!("Option '{}' in config file must be true or false", key)
+                }
+                (toml::Value::String(text), _) => text.clone(),
+                (toml::Value::Integer(number), _) => number.to_string(),
+                (toml::Value::Float(number), _) => number.to_string(),
+                _ => bail!(
+                    "Option '{}' in config file must be a string, a number or a list of them",
+                    key
+                ),
+            };
+            match arg.get_long() {
+                Some(long) => args.push(format!("--{}={}", long, text).into()),
+                None => args.push(text.into()),
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_to_args() {
+        let config: toml::Table = r#"
+            count = 30
+            units = "si"
+            exclude = ["node_modules", "target"]
+            all = true
+            merge = false
+        "#
+        .parse()
+        .unwrap();
+        let args = to_args(&config, &Args::command(), |_| false).unwrap();
+        assert_eq!(
+            args,
+            [
+                "--all",
+                "--count=30",
+                "--exclude=node_modules",
+                "--exclude=target",
+                "--units=si",
+            ]
+        );
+
+        // Options given on the command line are skipped
+        let args = to_args(&config, &Args::command(), |key| key == "exclude").unwrap();
+        assert_eq!(args, ["--all", "--count=30", "--units=si"]);
+    }
+
+    #[test]
+    fn test_to_args_errors() {
+        let check = |text: &str| {
+            let config: toml::Table = text.parse().unwrap();
+            format!(
+                "{:#}",
+                to_args(&config, &Args::command(), |_| false).unwrap_err()
+            )
+        };
+        assert!(check("colour = true").contains("Unknown option 'colour'"));
+        assert!(check("config = 'other.toml'").contains("Unknown option 'config'"));
+        assert!(check("all = 'yes'").contains("must be true or false"));
+        assert!(check("count = { n = 3 }").contains("must be a string"));
+    }
+}
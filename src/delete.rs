@@ -0,0 +1,299 @@
+//! Prompting for entries of a report to delete (`--interactive-delete`).
+
+use crate::cli::{NumberFormat, Units};
+use crate::display::format_bytes;
+use anyhow::{Context, Result};
+use disk_scanner::node::Node;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// Lets the user pick reported entries by number and deletes them once confirmed.
+///
+/// Nothing is removed unless `yes` is typed at the confirmation prompt or `assume_yes`
+/// is set; otherwise the selection is only reported (a dry run).
+pub struct Deleter<'a> {
+    /// Root of the scan, which is never offered for deletion
+    root: &'a Path,
+    /// Units for human-readable sizes
+    units: Units,
+    /// Separators for sizes
+    numbers: NumberFormat,
+    /// Skip the confirmation prompt
+    assume_yes: bool,
+}
+
+impl<'a> Deleter<'a> {
+    /// Create a deleter for entries under `root`
+    pub fn new(root: &'a Path) -> Self {
+        Self {
+            root,
+            units: Units::default(),
+            numbers: NumberFormat::default(),
+            assume_yes: false,
+        }
+    }
+
+    /// Set the units for human-readable sizes
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Set the separators for sizes
+    pub fn with_number_format(mut self, numbers: NumberFormat) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Delete the selection without asking for confirmation
+    pub fn assume_yes(mut self, assume_yes: bool) -> Self {
+        self.assume_yes = assume_yes;
+        self
+    }
+
+    /// List `nodes` with numbers, ask which to delete and delete them, returning the bytes freed
+    pub fn run(&self, nodes: &[Node], input: &mut dyn BufRead, out: &mut dyn Write) -> Result<u64> {
+        // Only strict descendants of the root can be deleted
+        let candidates: Vec<&Node> = nodes
+            .iter()
+            .filter(|node| node.path != self.root && node.path.starts_with(self.root))
+            .collect();
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        writeln!(out)?;
+        writeln!(out, " Entries that can be deleted:")?;
+        for (idx, node) in candidates.iter().enumerate() {
+            self.print_entry(out, idx + 1, node)?;
+        }
+
+        let selected = loop {
+            let Some(line) = prompt(
+                input,
+                out,
+                "Entries to delete (e.g. 1 3-5), or Enter to skip: ",
+            )?
+            else {
+                return Ok(0);
+            };
+            if line.is_empty() {
+                writeln!(out, "Nothing deleted.")?;
+                return Ok(0);
+            }
+            match parse_selection(&line, candidates.len()) {
+                Ok(indices) => break indices,
+                Err(err) => writeln!(out, "  {:#}", err)?,
+            }
+        };
+
+        // Entries inside another selected directory go with it
+        let picked: Vec<&Node> = selected.iter().map(|&idx| candidates[idx]).collect();
+        let picked: Vec<&Node> = picked
+            .iter()
+            .filter(|node| {
+                !picked
+                    .iter()
+                    .any(|other| other.path != node.path && node.path.starts_with(&other.path))
+            })
+            .copied()
+            .collect();
+        let total: u64 = picked.iter().map(|node| node.size).sum();
+
+        writeln!(out)?;
+        writeln!(out, " Selected {} entries:", picked.len())?;
+        for node in &picked {
+            writeln!(out, "  {}", node.path.display())?;
+        }
+
+        if !self.assume_yes {
+            let question = format!(
+                "Delete {} entries ({})? Type 'yes' to confirm: ",
+                picked.len(),
+                self.size(total)
+            );
+            let answer = prompt(input, out, &question)?;
+            if answer.as_deref() != Some("yes") {
+                writeln!(
+                    out,
+                    "Dry run: nothing deleted (would free {}).",
+                    self.size(total)
+                )?;
+                return Ok(0);
+            }
+        }
+
+        let mut freed = 0;
+        let mut deleted = 0;
+        for node in &picked {
+            match remove(&node.path) {
+                Ok(()) => {
+                    freed += node.size;
+                    deleted += 1;
+                }
+                Err(err) => eprintln!("warning: {:#}", err),
+            }
+        }
+        writeln!(
+            out,
+            "Freed {} ({} of {} entries deleted).",
+            self.size(freed),
+            deleted,
+            picked.len()
+        )?;
+        Ok(freed)
+    }
+
+    /// Format a size in the configured units and decimal separator
+    fn size(&self, bytes: u64) -> String {
+        self.numbers
+            .localize(format_bytes(bytes, self.units, self.numbers.precision))
+    }
+
+    /// Print a numbered entry with its size and path relative to the root
+    fn print_entry(&self, out: &mut dyn Write, number: usize, node: &Node) -> Result<()> {
+        let path = node.path.strip_prefix(self.root).unwrap_or(&node.path);
+        let suffix = if node.is_dir { "/" } else { "" };
+        writeln!(
+            out,
+            "  {:>3}. {:>12}  {}{}",
+            number,
+            self.size(node.size),
+            path.display(),
+            suffix
+        )?;
+        Ok(())
+    }
+}
+
+/// Ask a question and read the trimmed answer (None at end of input)
+fn prompt(input: &mut dyn BufRead, out: &mut dyn Write, question: &str) -> Result<Option<String>> {
+    write!(out, "{}", question)?;
+    out.flush()?;
+    let mut line = String::new();
+    if input
+        .read_line(&mut line)
+        .context("Failed to read answer")?
+        == 0
+    {
+        writeln!(out)?;
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// Parse a selection such as `1 3-5,7` into sorted zero-based indices of `len` numbered entries
+fn parse_selection(input: &str, len: usize) -> Result<Vec<usize>> {
+    let mut selected = Vec::new();
+    for part in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+    {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let number = |text: &str| -> Result<usize> {
+            let number: usize = text
+                .parse()
+                .with_context(|| format!("Invalid selection '{}'", part))?;
+            if number == 0 || number > len {
+                anyhow::bail!("Entry {} is out of range (1-{})", number, len);
+            }
+            Ok(number)
+        };
+        let (start, end) = (number(start)?, number(end)?);
+        if start > end {
+            anyhow::bail!("Invalid range '{}'", part);
+        }
+        selected.extend(start - 1..end);
+    }
+    selected.sort_unstable();
+    selected.dedup();
+    Ok(selected)
+}
+
+/// Delete a file, symlink or directory tree (symlinks are removed, not followed)
+fn remove(path: &Path) -> Result<()> {
+    let metadata = std::fs::symlink_metadata(path)
+        .with_context(|| format!("Cannot access '{}'", path.display()))?;
+    if metadata.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+    .with_context(|| format!("Failed to delete '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("1 3-5,2", 5).unwrap(), [0, 1, 2, 3, 4]);
+        assert_eq!(parse_selection(" 2, 2 ", 3).unwrap(), [1]);
+        assert!(parse_selection("0", 3).is_err());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("3-1", 3).is_err());
+        assert!(parse_selection("x", 3).is_err());
+    }
+
+    fn sample(root: &Path) -> Vec<Node> {
+        fs::create_dir_all(root.join("cache/nested")).unwrap();
+        fs::write(root.join("cache/nested/blob"), "x").unwrap();
+        fs::write(root.join("big.iso"), "x").unwrap();
+        vec![
+            Node::new(root.to_path_buf(), 300, true, 0),
+            Node::new(root.join("cache"), 200, true, 1),
+            Node::new(root.join("big.iso"), 100, false, 1),
+            Node::new(root.join("cache/nested"), 50, true, 2),
+        ]
+    }
+
+    #[test]
+    fn test_dry_run_by_default() {
+        let dir = tempdir().unwrap();
+        let nodes = sample(dir.path());
+        let deleter = Deleter::new(dir.path()).with_units(Units::Bytes);
+
+        // Anything but "yes" keeps the files
+        let mut out = Vec::new();
+        let freed = deleter
+            .run(&nodes, &mut Cursor::new("1 2\ny\n"), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(freed, 0);
+        assert!(out.contains("would free 300"));
+        assert!(dir.path().join("cache").exists());
+
+        // The root itself is never listed
+        assert!(out.contains("  1.          200  cache/"));
+        assert!(!out.contains("  4."));
+    }
+
+    #[test]
+    fn test_delete_confirmed() {
+        let dir = tempdir().unwrap();
+        let nodes = sample(dir.path());
+
+        // Invalid input is asked again; the nested entry is covered by its parent
+        let mut out = Vec::new();
+        let freed = Deleter::new(dir.path())
+            .run(&nodes, &mut Cursor::new("9\n1,3\nyes\n"), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("out of range"));
+        assert!(out.contains("Selected 1 entries"));
+        assert_eq!(freed, 200);
+        assert!(!dir.path().join("cache").exists());
+        assert!(dir.path().join("big.iso").exists());
+
+        let freed = Deleter::new(dir.path())
+            .assume_yes(true)
+            .run(&nodes[2..3], &mut Cursor::new("1\n"), &mut Vec::new())
+            .unwrap();
+        assert_eq!(freed, 100);
+        assert!(!dir.path().join("big.iso").exists());
+    }
+}
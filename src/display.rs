@@ -1,126 +1,2418 @@
 //! Output formatting and display logic.
 
-use crate::node::{Node, ScanResult};
-use humansize::{BINARY, format_size};
-use owo_colors::OwoColorize;
+use crate::cli::{NumberFormat, OutputFormat, SortKey, Truncate, Units};
+use crate::progress::{format_duration, format_number};
+use anyhow::{Context, Result};
+use disk_scanner::node::{
+    DepthSummary, DiskInfo, DuplicateGroup, Node, Profile, ScanResult, ScanStats, SizeBucket,
+    SizeChange, TypeSummary, common_ancestor,
+};
+use humansize::{BINARY, DECIMAL, format_size};
+use owo_colors::{OwoColorize, Style};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Display configuration
 pub struct Display {
-    /// Maximum number of items to show
+    /// Maximum number of items to show (0 for all)
     pub count: usize,
+    /// Width of banners and separators in columns
+    pub width: usize,
     /// Maximum path width before truncation
     pub max_path_width: usize,
+    /// Part of long paths to elide
+    pub truncate: Truncate,
+    /// Directory that paths are shown relative to instead of the scan root (None = root)
+    pub relative_to: Option<PathBuf>,
+    /// Show full paths instead of paths relative to the scan root
+    pub absolute_paths: bool,
+    /// Output format
+    pub format: OutputFormat,
+    /// Print NUL-terminated full paths instead of a report
+    pub print0: bool,
+    /// Render results as an indented tree instead of a flat list
+    pub tree: bool,
+    /// In the tree, fold directories smaller than this into one row per parent (None = off)
+    pub collapse_under: Option<u64>,
+    /// Show per-extension totals instead of paths
+    pub by_type: bool,
+    /// Show file counts by size range instead of paths
+    pub histogram: bool,
+    /// Show directory totals per depth instead of paths
+    pub by_depth: bool,
+    /// Show duplicate file groups instead of paths
+    pub duplicates: bool,
+    /// Show size changes since a baseline instead of paths
+    pub compare: bool,
+    /// List the paths that could not be read after the report
+    pub show_errors: bool,
+    /// Show only the totals, without the entry list
+    pub summary_only: bool,
+    /// List every empty directory instead of the largest entries
+    pub empty_dirs: bool,
+    /// Add a column with the average file size of each entry
+    pub show_avg: bool,
+    /// Add a column with how long ago each entry was last modified
+    pub show_mtime: bool,
+    /// Add a column with the size of the files directly in each directory
+    pub self_size: bool,
+    /// Sizes above which entry sizes turn yellow and red (None = always green)
+    pub heat: Option<(u64, u64)>,
+    /// Key the results are sorted by
+    pub sort: SortKey,
+    /// Whether results are sorted smallest first
+    pub ascending: bool,
+    /// Units for human-readable sizes
+    pub units: Units,
+    /// Separators for counts and sizes
+    pub numbers: NumberFormat,
+    /// Whether to emit ANSI colors
+    pub color: bool,
+    /// Pick file icons and colors by extension instead of a generic 📄
+    pub icons: bool,
+}
+
+/// File icons and colors by (lowercase) extension, for --icons
+const FILE_ICONS: &[(&str, Style, &[&str])] = &[
+    (
+        "🎬",
+        Style::new().magenta(),
+        &["mp4", "mkv", "avi", "mov", "webm", "wmv", "m4v", "flv"],
+    ),
+    (
+        "🖼️",
+        Style::new().bright_magenta(),
+        &[
+            "jpg", "jpeg", "png", "gif", "bmp", "webp", "heic", "tiff", "svg", "ico",
+        ],
+    ),
+    (
+        "🎵",
+        Style::new().cyan(),
+        &["mp3", "flac", "wav", "ogg", "m4a", "aac", "opus"],
+    ),
+    (
+        "📦",
+        Style::new().red(),
+        &[
+            "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "iso", "dmg", "deb", "rpm",
+        ],
+    ),
+    (
+        "📝",
+        Style::new().bright_white(),
+        &["txt", "md", "pdf", "doc", "docx", "odt", "rtf", "epub"],
+    ),
+    (
+        "📊",
+        Style::new().bright_green(),
+        &[
+            "csv", "xls", "xlsx", "ods", "json", "xml", "yaml", "yml", "toml",
+        ],
+    ),
+    (
+        "🗄️",
+        Style::new().yellow(),
+        &["db", "sqlite", "sqlite3", "sql", "parquet"],
+    ),
+    (
+        "📜",
+        Style::new().bright_yellow(),
+        &[
+            "rs", "py", "js", "ts", "go", "c", "h", "cpp", "java", "rb", "sh", "php",
+        ],
+    ),
+    (
+        "⚙️",
+        Style::new().bright_red(),
+        &[
+            "exe", "dll", "so", "dylib", "bin", "o", "a", "class", "wasm",
+        ],
+    ),
+];
+
+/// Icon and color for a file from its extension, falling back to a plain 📄
+fn file_icon(path: &Path) -> (&'static str, Style) {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    FILE_ICONS
+        .iter()
+        .find(|(_, _, extensions)| extensions.contains(&extension.as_str()))
+        .map_or(("📄", Style::new().white()), |&(icon, style, _)| {
+            (icon, style)
+        })
+}
+
+/// Columns of a full-length bar in the profile and histogram charts
+const BAR_WIDTH: usize = 30;
+
+/// Mount points listed in the summary note before the rest are counted
+const MAX_LISTED_MOUNTS: usize = 5;
+
+/// Version of the JSON and JSON Lines output, bumped whenever their fields change
+pub const JSON_SCHEMA_VERSION: u32 = 5;
+
+/// Totals for one root, shared by the JSON report and the JSON Lines summary line
+#[derive(Serialize)]
+struct JsonSummary<'a> {
+    schema: u32,
+    root: &'a Path,
+    total_size: u64,
+    file_count: u64,
+    dir_count: u64,
+    error_count: u64,
+    cycles_skipped: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_scan_depth: Option<usize>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    interrupted: bool,
+    scan_duration_ms: u64,
+    files_per_second: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk: Option<DiskInfo>,
+}
+
+/// Machine-readable report emitted in JSON mode
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    #[serde(flatten)]
+    summary: JsonSummary<'a>,
+    nodes: &'a [Node],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    types: &'a [TypeSummary],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    histogram: &'a [SizeBucket],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    depths: Vec<DepthSummary>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    duplicates: &'a [DuplicateGroup],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    changes: &'a [SizeChange],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    errors: &'a [(PathBuf, String)],
+}
+
+/// One record of JSON Lines output, tagged with a `type` field
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonLine<'a> {
+    Node(&'a Node),
+    Extension(&'a TypeSummary),
+    Bucket(&'a SizeBucket),
+    Depth(DepthSummary),
+    Duplicate(&'a DuplicateGroup),
+    Change(&'a SizeChange),
+    Error { path: &'a Path, message: &'a str },
+    Summary(JsonSummary<'a>),
+}
+
+/// Metadata object in the header of an ncdu export
+#[derive(Serialize)]
+struct NcduMeta {
+    progname: &'static str,
+    progver: &'static str,
+    timestamp: u64,
+}
+
+/// A file in an ncdu export, or the info object heading a directory's array
+#[derive(Serialize)]
+struct NcduEntry<'a> {
+    name: Cow<'a, str>,
+    asize: u64,
+    dsize: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    read_error: bool,
+}
+
+/// Report entries read back from a saved JSON report
+#[derive(Deserialize)]
+struct BaselineReport {
+    /// Missing in reports saved before the schema was versioned, which match version 1
+    schema: Option<u32>,
+    nodes: Vec<BaselineNode>,
+}
+
+/// Single entry of a saved JSON report
+#[derive(Deserialize)]
+struct BaselineNode {
+    path: PathBuf,
+    size: u64,
+    is_dir: bool,
+}
+
+/// A saved JSON report: one object per root, or an array for several roots
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Baseline {
+    Single(BaselineReport),
+    Multiple(Vec<BaselineReport>),
 }
 
 impl Default for Display {
     fn default() -> Self {
         Self {
             count: 10,
+            width: 70,
             max_path_width: 60,
+            truncate: Truncate::default(),
+            relative_to: None,
+            absolute_paths: false,
+            format: OutputFormat::default(),
+            print0: false,
+            tree: false,
+            collapse_under: None,
+            by_type: false,
+            histogram: false,
+            by_depth: false,
+            duplicates: false,
+            compare: false,
+            show_errors: false,
+            summary_only: false,
+            empty_dirs: false,
+            show_avg: false,
+            show_mtime: false,
+            self_size: false,
+            heat: None,
+            sort: SortKey::default(),
+            ascending: false,
+            units: Units::default(),
+            numbers: NumberFormat::default(),
+            color: true,
+            icons: false,
+        }
+    }
+}
+
+impl Display {
+    /// Create a new Display with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of items to display, or 0 for all
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Set the output format
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Print NUL-terminated full paths instead of a report
+    pub fn with_print0(mut self, print0: bool) -> Self {
+        self.print0 = print0;
+        self
+    }
+
+    /// Size banners and separators to `width` columns, leaving paths 10 columns less
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self.max_path_width = width.saturating_sub(10);
+        self
+    }
+
+    /// Render results as a tree
+    pub fn with_tree(mut self, tree: bool) -> Self {
+        self.tree = tree;
+        self
+    }
+
+    /// Fold tree directories smaller than `size` into one summary row under their parent
+    pub fn with_collapse_under(mut self, size: Option<u64>) -> Self {
+        self.collapse_under = size;
+        self
+    }
+
+    /// Set the key the results are sorted by
+    pub fn with_sort(mut self, sort: SortKey) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Show per-extension totals instead of paths
+    pub fn with_by_type(mut self, by_type: bool) -> Self {
+        self.by_type = by_type;
+        self
+    }
+
+    /// Show file counts by size range instead of paths
+    pub fn with_histogram(mut self, histogram: bool) -> Self {
+        self.histogram = histogram;
+        self
+    }
+
+    /// Show directory totals per depth instead of paths
+    pub fn with_by_depth(mut self, by_depth: bool) -> Self {
+        self.by_depth = by_depth;
+        self
+    }
+
+    /// Show duplicate file groups instead of paths
+    pub fn with_duplicates(mut self, duplicates: bool) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Show size changes since a baseline instead of paths
+    pub fn with_compare(mut self, compare: bool) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// List the paths that could not be read after the report
+    pub fn with_show_errors(mut self, show_errors: bool) -> Self {
+        self.show_errors = show_errors;
+        self
+    }
+
+    /// Mark results as sorted smallest first
+    pub fn with_ascending(mut self, ascending: bool) -> Self {
+        self.ascending = ascending;
+        self
+    }
+
+    /// Show only the totals, without the entry list
+    pub fn with_summary_only(mut self, summary_only: bool) -> Self {
+        self.summary_only = summary_only;
+        self
+    }
+
+    /// List every empty directory instead of the largest entries
+    pub fn with_empty_dirs(mut self, empty_dirs: bool) -> Self {
+        self.empty_dirs = empty_dirs;
+        self
+    }
+
+    /// Add a column with the average file size of each entry
+    pub fn with_show_avg(mut self, show_avg: bool) -> Self {
+        self.show_avg = show_avg;
+        self
+    }
+
+    /// Add a column with the size of the files directly in each directory
+    pub fn with_self_size(mut self, self_size: bool) -> Self {
+        self.self_size = self_size;
+        self
+    }
+
+    /// Add a column with how long ago each entry was last modified
+    pub fn with_show_mtime(mut self, show_mtime: bool) -> Self {
+        self.show_mtime = show_mtime;
+        self
+    }
+
+    /// Color entry sizes by magnitude using (warm, hot) thresholds
+    pub fn with_heat(mut self, heat: Option<(u64, u64)>) -> Self {
+        self.heat = heat;
+        self
+    }
+
+    /// Set which part of long paths is elided
+    pub fn with_truncate(mut self, truncate: Truncate) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Show paths relative to `base` instead of the scan root
+    pub fn with_relative_to(mut self, base: Option<PathBuf>) -> Self {
+        self.relative_to = base;
+        self
+    }
+
+    /// Show full paths instead of paths relative to the scan root
+    pub fn with_absolute_paths(mut self, absolute_paths: bool) -> Self {
+        self.absolute_paths = absolute_paths;
+        self
+    }
+
+    /// Set the units for human-readable sizes
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Set the separators for counts and sizes
+    pub fn with_number_format(mut self, numbers: NumberFormat) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Enable or disable colored output
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Enable or disable icons and colors by file extension
+    pub fn with_icons(mut self, icons: bool) -> Self {
+        self.icons = icons;
+        self
+    }
+
+    /// Write the scan results to `out` in the configured format
+    pub fn print_results(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        if self.print0 {
+            return self.write_print0(out, result);
+        }
+        match self.format {
+            OutputFormat::Table => self.print_table(out, result, root_path),
+            OutputFormat::Json => write_json(out, &self.json_report(result, root_path)),
+            OutputFormat::Jsonl => self.write_json_lines(out, result, root_path),
+            OutputFormat::Ncdu => write_ncdu(out, result, root_path),
+            OutputFormat::Markdown => self.write_markdown(out, result, root_path),
+            OutputFormat::Html => self.write_html(out, &[(root_path, result)]),
+            #[cfg(feature = "protobuf")]
+            OutputFormat::Bin => {
+                disk_scanner::proto::write_report(out, result, root_path, self.json_nodes(result))
+            }
+            OutputFormat::Csv => {
+                writeln!(out, "{}", self.csv_header())?;
+                if self.summary_only {
+                    self.write_csv_summary(out, result, root_path)
+                } else {
+                    self.write_csv_rows(out, result, root_path)
+                }
+            }
+        }
+    }
+
+    /// Print results for several independently scanned roots.
+    ///
+    /// Tables, Markdown and the HTML page get one section per root, JSON becomes an array of
+    /// reports, and CSV rows share one header with paths relative to the roots' common
+    /// ancestor.
+    pub fn print_all(&self, out: &mut dyn Write, reports: &[(PathBuf, ScanResult)]) -> Result<()> {
+        if self.print0 {
+            for (_, result) in reports {
+                self.write_print0(out, result)?;
+            }
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Table => {
+                for (root, result) in reports {
+                    self.print_table(out, result, root)?;
+                }
+                Ok(())
+            }
+            OutputFormat::Markdown => {
+                for (idx, (root, result)) in reports.iter().enumerate() {
+                    if idx > 0 {
+                        writeln!(out)?;
+                    }
+                    self.write_markdown(out, result, root)?;
+                }
+                Ok(())
+            }
+            OutputFormat::Html => {
+                let reports: Vec<(&Path, &ScanResult)> = reports
+                    .iter()
+                    .map(|(root, result)| (root.as_path(), result))
+                    .collect();
+                self.write_html(out, &reports)
+            }
+            OutputFormat::Json => {
+                let json_reports: Vec<JsonReport> = reports
+                    .iter()
+                    .map(|(root, result)| self.json_report(result, root))
+                    .collect();
+                write_json(out, &json_reports)
+            }
+            OutputFormat::Jsonl => {
+                for (root, result) in reports {
+                    self.write_json_lines(out, result, root)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "protobuf")]
+            OutputFormat::Bin => {
+                for (root, result) in reports {
+                    disk_scanner::proto::write_report(out, result, root, self.json_nodes(result))?;
+                }
+                Ok(())
+            }
+            OutputFormat::Ncdu => anyhow::bail!("--format ncdu exports a single directory tree"),
+            OutputFormat::Csv => {
+                let roots: Vec<PathBuf> = reports.iter().map(|(root, _)| root.clone()).collect();
+                let base = common_ancestor(&roots);
+                writeln!(out, "{}", self.csv_header())?;
+                for (root, result) in reports {
+                    if self.summary_only {
+                        self.write_csv_summary(out, result, root)?;
+                    } else {
+                        self.write_csv_rows(out, result, &base)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the full path of each shown entry followed by a NUL byte, the format read by
+    /// `xargs -0`; on Unix paths are written as raw bytes, even if they aren't valid UTF-8
+    fn write_print0(&self, out: &mut dyn Write, result: &ScanResult) -> Result<()> {
+        for node in self.shown_nodes(result) {
+            out.write_all(node.path.as_os_str().as_encoded_bytes())?;
+            out.write_all(b"\0")?;
+        }
+        Ok(())
+    }
+
+    /// CSV header row for the current mode
+    fn csv_header(&self) -> &'static str {
+        if self.summary_only {
+            "path,total_size_bytes,file_count,dir_count,error_count"
+        } else if self.by_type {
+            "extension,size_bytes,file_count"
+        } else if self.histogram {
+            "min_bytes,max_bytes,file_count,size_bytes"
+        } else if self.by_depth {
+            "depth,dir_count,size_bytes"
+        } else if self.duplicates {
+            "group,path,size_bytes"
+        } else if self.compare {
+            "path,old_size_bytes,new_size_bytes,change_bytes"
+        } else {
+            "path,size_bytes,type,depth"
+        }
+    }
+
+    /// Write the totals for one root as a single CSV row
+    fn write_csv_summary(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            csv_escape(&root_path.display().to_string()),
+            result.total_size,
+            result.file_count,
+            result.dir_count,
+            result.error_count
+        )?;
+        Ok(())
+    }
+
+    /// Write CSV rows for one result, one row per node
+    fn write_csv_rows(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        if self.by_type {
+            for summary in self.top_types(result) {
+                writeln!(
+                    out,
+                    "{},{},{}",
+                    csv_escape(summary.extension.as_deref().unwrap_or("")),
+                    summary.size,
+                    summary.file_count
+                )?;
+            }
+            return Ok(());
+        }
+
+        if self.histogram {
+            for bucket in &result.histogram {
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    bucket.min,
+                    bucket.max.map(|max| max.to_string()).unwrap_or_default(),
+                    bucket.file_count,
+                    bucket.size
+                )?;
+            }
+            return Ok(());
+        }
+
+        if self.by_depth {
+            for level in self.depths(result) {
+                writeln!(out, "{},{},{}", level.depth, level.dir_count, level.size)?;
+            }
+            return Ok(());
+        }
+
+        if self.duplicates {
+            for (idx, group) in self.top_duplicates(result).iter().enumerate() {
+                for path in &group.paths {
+                    let path_str = self.relative_path(path, root_path).display();
+                    writeln!(
+                        out,
+                        "{},{},{}",
+                        idx + 1,
+                        csv_escape(&path_str.to_string()),
+                        group.size
+                    )?;
+                }
+            }
+            return Ok(());
+        }
+
+        if self.compare {
+            for change in self.top_changes(result) {
+                let path_str = self.relative_path(&change.path, root_path);
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    csv_escape(&path_str.display().to_string()),
+                    change.old_size.map(|s| s.to_string()).unwrap_or_default(),
+                    change.new_size.map(|s| s.to_string()).unwrap_or_default(),
+                    change.change()
+                )?;
+            }
+            return Ok(());
+        }
+
+        for node in self.shown_nodes(result) {
+            let path_str = self
+                .relative_path(&node.path, root_path)
+                .display()
+                .to_string();
+            writeln!(
+                out,
+                "{},{},{},{}",
+                csv_escape(&path_str),
+                node.size,
+                if node.is_dir { "dir" } else { "file" },
+                node.depth
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write one result as a heading, a summary line and a GitHub-flavored Markdown table
+    fn write_markdown(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        writeln!(
+            out,
+            "## Disk usage: {}",
+            markdown_code(&root_path.display().to_string())
+        )?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Total size: **{}** in {} files and {} directories",
+            self.size(result.total_size),
+            self.count_text(result.file_count),
+            self.count_text(result.dir_count)
+        )?;
+        if result.interrupted {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "> **PARTIAL (interrupted):** totals only cover what was found so far"
+            )?;
+        }
+        if result.error_count > 0 {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{} entries could not be read (permission denied or inaccessible)",
+                self.count_text(result.error_count)
+            )?;
+        }
+        if self.summary_only {
+            return Ok(());
+        }
+
+        let (columns, rows) = self.report_table(result, root_path);
+        writeln!(out)?;
+        write_markdown_table(out, &columns, &rows)
+    }
+
+    /// Write a standalone HTML page (inline CSS and JavaScript) with a summary and a table
+    /// for each result; clicking a column heading sorts the table by it
+    fn write_html(&self, out: &mut dyn Write, reports: &[(&Path, &ScanResult)]) -> Result<()> {
+        let title = match reports {
+            [(root, _)] => format!("Disk usage: {}", root.display()),
+            _ => "Disk usage".to_string(),
+        };
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>{}</title>", html_escape(&title))?;
+        writeln!(out, "<style>{}</style>", HTML_STYLE)?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+        for (root, result) in reports {
+            self.write_html_section(out, result, root)?;
         }
+        writeln!(out, "<script>{}</script>", HTML_SCRIPT)?;
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")?;
+        Ok(())
+    }
+
+    /// Write one result of the HTML page: a heading, the summary and the table
+    fn write_html_section(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        writeln!(out, "<section>")?;
+        writeln!(
+            out,
+            "<h2>Disk usage: <code>{}</code></h2>",
+            html_escape(&root_path.display().to_string())
+        )?;
+        writeln!(
+            out,
+            "<p>Total size: <strong>{}</strong> in {} files and {} directories</p>",
+            html_escape(&self.size(result.total_size)),
+            html_escape(&self.count_text(result.file_count)),
+            html_escape(&self.count_text(result.dir_count))
+        )?;
+        if result.interrupted {
+            writeln!(
+                out,
+                "<p class=\"warning\"><strong>PARTIAL (interrupted):</strong> totals only cover what was found so far</p>"
+            )?;
+        }
+        if result.error_count > 0 {
+            writeln!(
+                out,
+                "<p>{} entries could not be read (permission denied or inaccessible)</p>",
+                html_escape(&self.count_text(result.error_count))
+            )?;
+        }
+
+        if !self.summary_only {
+            let (columns, rows) = self.report_table(result, root_path);
+            writeln!(out, "<table>")?;
+            writeln!(out, "<thead>")?;
+            write!(out, "<tr>")?;
+            for (title, right) in &columns {
+                let class = if *right { " class=\"num\"" } else { "" };
+                write!(out, "<th{}>{}</th>", class, html_escape(title))?;
+            }
+            writeln!(out, "</tr>")?;
+            writeln!(out, "</thead>")?;
+            writeln!(out, "<tbody>")?;
+            for row in &rows {
+                write!(out, "<tr>")?;
+                for (cell, (_, right)) in row.iter().zip(&columns) {
+                    write!(out, "<td")?;
+                    if *right {
+                        write!(out, " class=\"num\"")?;
+                    }
+                    if let Some(value) = cell.value {
+                        write!(out, " data-value=\"{}\"", value)?;
+                    }
+                    write!(out, ">{}</td>", cell.html())?;
+                }
+                writeln!(out, "</tr>")?;
+            }
+            writeln!(out, "</tbody>")?;
+            writeln!(out, "</table>")?;
+        }
+        writeln!(out, "</section>")?;
+        Ok(())
+    }
+
+    /// Columns (title, right-aligned) and rows of the Markdown and HTML reports, for the
+    /// active view or the largest entries
+    fn report_table(
+        &self,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> (Vec<(&'static str, bool)>, Vec<Vec<Cell>>) {
+        let relative =
+            |path: &Path| Cell::code(self.relative_path(path, root_path).display().to_string());
+        if self.by_type {
+            let rows = self
+                .top_types(result)
+                .iter()
+                .map(|summary| {
+                    vec![
+                        summary.extension.as_deref().map_or_else(
+                            || Cell::text("(no extension)"),
+                            |ext| Cell::code(format!(".{}", ext)),
+                        ),
+                        self.size_cell(summary.size),
+                        self.count_cell(summary.file_count),
+                    ]
+                })
+                .collect();
+            (
+                vec![("Extension", false), ("Size", true), ("Files", true)],
+                rows,
+            )
+        } else if self.histogram {
+            let rows = result
+                .histogram
+                .iter()
+                .map(|bucket| {
+                    vec![
+                        Cell::number(self.bucket_range(bucket), bucket.min as f64),
+                        self.count_cell(bucket.file_count),
+                        self.size_cell(bucket.size),
+                    ]
+                })
+                .collect();
+            (
+                vec![("Range", false), ("Files", true), ("Size", true)],
+                rows,
+            )
+        } else if self.by_depth {
+            let rows = self
+                .depths(result)
+                .iter()
+                .map(|level| {
+                    vec![
+                        Cell::number(level.depth.to_string(), level.depth as f64),
+                        self.count_cell(level.dir_count),
+                        self.size_cell(level.size),
+                    ]
+                })
+                .collect();
+            (
+                vec![("Depth", true), ("Directories", true), ("Size", true)],
+                rows,
+            )
+        } else if self.duplicates {
+            let mut rows = Vec::new();
+            for (idx, group) in self.top_duplicates(result).iter().enumerate() {
+                for path in &group.paths {
+                    rows.push(vec![
+                        Cell::number((idx + 1).to_string(), (idx + 1) as f64),
+                        self.size_cell(group.size),
+                        relative(path),
+                    ]);
+                }
+            }
+            (vec![("Group", true), ("Size", true), ("Path", false)], rows)
+        } else if self.compare {
+            let rows = self
+                .top_changes(result)
+                .iter()
+                .map(|change| {
+                    let delta = change.change();
+                    let sign = if delta < 0 { "-" } else { "+" };
+                    let size_or_dash = |size: Option<u64>| {
+                        size.map_or_else(|| Cell::text("-"), |s| self.size_cell(s))
+                    };
+                    vec![
+                        size_or_dash(change.old_size),
+                        size_or_dash(change.new_size),
+                        Cell::number(
+                            format!("{}{}", sign, self.size(delta.unsigned_abs() as u64)),
+                            delta as f64,
+                        ),
+                        relative(&change.path),
+                    ]
+                })
+                .collect();
+            (
+                vec![
+                    ("Old", true),
+                    ("New", true),
+                    ("Change", true),
+                    ("Path", false),
+                ],
+                rows,
+            )
+        } else {
+            // The type column only helps when files are listed alongside directories
+            let nodes = self.shown_nodes(result);
+            let show_type = nodes.iter().any(|node| !node.is_dir);
+            let mut columns = vec![("Size", true), ("%", true)];
+            if self.self_size {
+                columns.push(("Self", true));
+            }
+            if show_type {
+                columns.push(("Type", false));
+            }
+            if self.show_file_counts() {
+                columns.push(("Files", true));
+            }
+            if self.show_avg {
+                columns.push(("Avg file", true));
+            }
+            if self.show_mtime {
+                columns.push(("Modified", true));
+            }
+            columns.push(("Path", false));
+
+            let now = SystemTime::now();
+            let rows = nodes
+                .iter()
+                .map(|node| {
+                    let share = if result.total_size == 0 {
+                        0.0
+                    } else {
+                        node.size as f64 * 100.0 / result.total_size as f64
+                    };
+                    let mut row = vec![
+                        self.size_cell(node.size),
+                        Cell::number(format!("{:.1}%", share), share),
+                    ];
+                    if self.self_size {
+                        row.push(self.size_cell(node.self_size));
+                    }
+                    if show_type {
+                        row.push(Cell::text(if node.is_dir { "dir" } else { "file" }));
+                    }
+                    if self.show_file_counts() {
+                        row.push(self.count_cell(node.file_count));
+                    }
+                    if self.show_avg {
+                        row.push(
+                            node.average_file_size()
+                                .map_or_else(|| Cell::text("-"), |avg| self.size_cell(avg)),
+                        );
+                    }
+                    if self.show_mtime {
+                        // Older entries sort lower, like smaller sizes
+                        row.push(node.modified.map_or_else(
+                            || Cell::text("-"),
+                            |time| {
+                                let since_epoch = time
+                                    .duration_since(UNIX_EPOCH)
+                                    .map_or(0.0, |since| since.as_secs_f64());
+                                Cell::number(format_age(time, now), since_epoch)
+                            },
+                        ));
+                    }
+                    row.push(relative(&node.path));
+                    row
+                })
+                .collect();
+            (columns, rows)
+        }
+    }
+
+    /// Build the machine-readable report for one result
+    fn json_report<'a>(&self, result: &'a ScanResult, root_path: &'a Path) -> JsonReport<'a> {
+        JsonReport {
+            summary: json_summary(result, root_path),
+            nodes: self.json_nodes(result),
+            types: self.top_types(result),
+            histogram: &result.histogram,
+            depths: self.depths(result),
+            duplicates: self.top_duplicates(result),
+            changes: self.top_changes(result),
+            errors: self.json_errors(result),
+        }
+    }
+
+    /// Write one JSON object per line for each record, then a summary line for the root
+    fn write_json_lines(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        let records = self
+            .json_nodes(result)
+            .iter()
+            .map(JsonLine::Node)
+            .chain(self.top_types(result).iter().map(JsonLine::Extension))
+            .chain(result.histogram.iter().map(JsonLine::Bucket))
+            .chain(self.depths(result).into_iter().map(JsonLine::Depth))
+            .chain(self.top_duplicates(result).iter().map(JsonLine::Duplicate))
+            .chain(self.top_changes(result).iter().map(JsonLine::Change))
+            .chain(
+                self.json_errors(result)
+                    .iter()
+                    .map(|(path, message)| JsonLine::Error { path, message }),
+            )
+            .chain(std::iter::once(JsonLine::Summary(json_summary(
+                result, root_path,
+            ))));
+        for record in records {
+            serde_json::to_writer(&mut *out, &record)
+                .context("Failed to serialize results as JSON")?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Nodes included in machine-readable output (none when another view replaces them)
+    fn json_nodes<'a>(&self, result: &'a ScanResult) -> &'a [Node] {
+        if self.summary_only
+            || self.by_type
+            || self.histogram
+            || self.by_depth
+            || self.duplicates
+            || self.compare
+        {
+            &[]
+        } else {
+            self.shown_nodes(result)
+        }
+    }
+
+    /// Per-depth directory totals (only with --by-depth)
+    fn depths(&self, result: &ScanResult) -> Vec<DepthSummary> {
+        if self.by_depth {
+            result.depth_summary()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Errors included in machine-readable output (only with --show-errors)
+    fn json_errors<'a>(&self, result: &'a ScanResult) -> &'a [(PathBuf, String)] {
+        if self.show_errors {
+            &result.errors
+        } else {
+            &[]
+        }
+    }
+
+    /// Print the results as a table, colored unless color is disabled
+    fn print_table(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        let dimmed = Style::new().dimmed();
+        let bold = Style::new().bold();
+
+        writeln!(out)?;
+        writeln!(out, "{}", self.paint("═".repeat(self.width), dimmed))?;
+        write!(
+            out,
+            "{}",
+            self.paint(format!(" Disk Usage Report: {}", root_path.display()), bold)
+        )?;
+        if result.interrupted {
+            write!(
+                out,
+                " {}",
+                self.paint("PARTIAL (interrupted)", Style::new().yellow().bold())
+            )?;
+        }
+        writeln!(out)?;
+        writeln!(out, "{}", self.paint("═".repeat(self.width), dimmed))?;
+        writeln!(out)?;
+
+        // Print summary
+        writeln!(
+            out,
+            "  {} {}",
+            self.paint("Total size:", dimmed),
+            self.paint(self.size(result.total_size), Style::new().green().bold())
+        )?;
+        if let Some(disk) = &result.disk {
+            writeln!(
+                out,
+                "  {} {} used of {}, {} available (scan is {} of disk)",
+                self.paint("Disk:", dimmed),
+                self.size(disk.used()),
+                self.size(disk.total),
+                self.size(disk.available),
+                self.paint(
+                    format!("{:.1}%", disk.percent_of_total(result.total_size)),
+                    Style::new().yellow()
+                )
+            )?;
+        }
+        writeln!(
+            out,
+            "  {} {} files, {} directories",
+            self.paint("Scanned:", dimmed),
+            self.paint(result.file_count, Style::new().cyan()),
+            self.paint(result.dir_count, Style::new().cyan())
+        )?;
+        writeln!(
+            out,
+            "  {} {} ({} files/s)",
+            self.paint("Scan time:", dimmed),
+            self.paint(
+                format_duration(Duration::from_millis(result.scan_duration_ms)),
+                Style::new().cyan()
+            ),
+            format_number(
+                result.files_per_second().round() as u64,
+                self.numbers.separator,
+            )
+        )?;
+        if let Some((path, size)) = &result.largest_file {
+            writeln!(
+                out,
+                "  {} {} ({})",
+                self.paint("Largest file:", dimmed),
+                self.relative_path(path, root_path).display(),
+                self.paint(self.size(*size), Style::new().green())
+            )?;
+        }
+
+        if result.error_count > 0 {
+            writeln!(
+                out,
+                "  {} {} (permission denied or inaccessible)",
+                self.paint("Errors:", dimmed),
+                self.paint(result.error_count, Style::new().red())
+            )?;
+        }
+
+        if result.cached_dirs > 0 {
+            writeln!(
+                out,
+                "  {} {} directories reused from cache",
+                self.paint("Cached:", dimmed),
+                self.paint(result.cached_dirs, Style::new().cyan())
+            )?;
+        }
+
+        if result.cycles_skipped > 0 {
+            writeln!(
+                out,
+                "  {} {} (already scanned via another path)",
+                self.paint("Symlinks skipped:", dimmed),
+                self.paint(result.cycles_skipped, Style::new().yellow())
+            )?;
+        }
+
+        if result.interrupted {
+            writeln!(
+                out,
+                "  {} scan was interrupted; totals only cover what was found so far",
+                self.paint("Note:", dimmed),
+            )?;
+        }
+
+        if let Some(depth) = result.max_scan_depth {
+            writeln!(
+                out,
+                "  {} scan stopped at depth {}; sizes leave out deeper files",
+                self.paint("Note:", dimmed),
+                self.paint(depth, Style::new().yellow())
+            )?;
+        }
+
+        if !result.mount_crossings.is_empty() {
+            writeln!(
+                out,
+                "  {} scan crossed {} mount points; sizes include other filesystems",
+                self.paint("Note:", dimmed),
+                self.paint(result.mount_crossings.len(), Style::new().yellow())
+            )?;
+            for path in result.mount_crossings.iter().take(MAX_LISTED_MOUNTS) {
+                let path = self.relative_path(path, root_path);
+                writeln!(out, "        {}", path.display())?;
+            }
+            let unlisted = result
+                .mount_crossings
+                .len()
+                .saturating_sub(MAX_LISTED_MOUNTS);
+            if unlisted > 0 {
+                writeln!(
+                    out,
+                    "        {}",
+                    self.paint(format!("... and {} more", unlisted), dimmed)
+                )?;
+            }
+        }
+
+        if !self.summary_only {
+            self.print_entries(out, result, root_path)?;
+        }
+
+        if self.show_errors && result.error_count > 0 {
+            self.print_errors(out, result, root_path)?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "{}", self.paint("═".repeat(self.width), dimmed))?;
+
+        Ok(())
+    }
+
+    /// Print the section header and the entries for the current mode
+    fn print_entries(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        let dimmed = Style::new().dimmed();
+        let bold = Style::new().bold();
+
+        writeln!(out)?;
+        writeln!(out, "{}", self.paint("─".repeat(self.width), dimmed))?;
+        if self.by_type {
+            writeln!(
+                out,
+                "{}",
+                self.paint(self.heading("file types by size:"), bold)
+            )?;
+        } else if self.histogram {
+            writeln!(out, "{}", self.paint(" File size histogram:", bold))?;
+        } else if self.by_depth {
+            writeln!(out, "{}", self.paint(" Directories by depth:", bold))?;
+        } else if self.duplicates {
+            writeln!(
+                out,
+                "{}",
+                self.paint(self.heading("duplicate groups by wasted space:"), bold)
+            )?;
+        } else if self.compare {
+            writeln!(
+                out,
+                "{}",
+                self.paint(self.heading("changes since baseline:"), bold)
+            )?;
+        } else if self.tree {
+            writeln!(out, "{}", self.paint(" Directory tree:", bold))?;
+        } else if self.empty_dirs {
+            writeln!(
+                out,
+                "{}",
+                self.paint(format!(" {} empty directories:", result.nodes.len()), bold)
+            )?;
+        } else {
+            let rank = match (self.sort, self.ascending) {
+                (SortKey::Name | SortKey::Depth, _) => "First",
+                (_, true) => "Smallest",
+                (_, false) => "Top",
+            };
+            let key = match self.sort {
+                SortKey::Size => "size",
+                SortKey::Count => "file count",
+                SortKey::SelfSize => "self size",
+                SortKey::Name => "name",
+                SortKey::Depth => "depth",
+            };
+            let heading = if self.count == 0 {
+                format!(" All entries by {}:", key)
+            } else {
+                format!(" {} {} by {}:", rank, self.count, key)
+            };
+            writeln!(out, "{}", self.paint(heading, bold))?;
+        }
+        writeln!(out, "{}", self.paint("─".repeat(self.width), dimmed))?;
+        writeln!(out)?;
+
+        if self.by_type {
+            self.print_types(out, result)?;
+        } else if self.histogram {
+            self.print_histogram(out, result)?;
+        } else if self.by_depth {
+            self.print_depths(out, result)?;
+        } else if self.duplicates {
+            self.print_duplicates(out, result, root_path)?;
+        } else if self.compare {
+            self.print_changes(out, result, root_path)?;
+        } else if self.tree {
+            self.print_tree(out, result, root_path)?;
+        } else if self.empty_dirs {
+            self.print_empty_dirs(out, result, root_path)?;
+        } else {
+            self.print_list(out, result, root_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Print how the scan of `root_path` was spread over threads and phases
+    pub fn print_stats(
+        &self,
+        out: &mut dyn Write,
+        stats: &ScanStats,
+        root_path: &Path,
+    ) -> Result<()> {
+        let dimmed = Style::new().dimmed();
+        let total: u64 = stats.entries_per_thread.iter().sum();
+
+        writeln!(
+            out,
+            "{}",
+            self.paint(
+                format!(" Scan statistics: {}", root_path.display()),
+                Style::new().bold()
+            )
+        )?;
+        writeln!(
+            out,
+            "  {} {}",
+            self.paint("Threads:", dimmed),
+            self.paint(stats.threads, Style::new().cyan())
+        )?;
+        for (thread, entries) in stats.entries_per_thread.iter().enumerate() {
+            let share = if total > 0 {
+                *entries as f64 * 100.0 / total as f64
+            } else {
+                0.0
+            };
+            writeln!(
+                out,
+                "    {} {:>12} entries ({:.1}%)",
+                self.paint(format!("thread {:>2}:", thread), dimmed),
+                format_number(*entries, self.numbers.separator),
+                share
+            )?;
+        }
+        if let Some(waits) = stats.visited_lock_waits {
+            writeln!(
+                out,
+                "  {} {}",
+                self.paint("Visited-directory lock waits:", dimmed),
+                self.paint(waits, Style::new().yellow())
+            )?;
+        }
+        writeln!(
+            out,
+            "  {} {} walking, {} building results",
+            self.paint("Phases:", dimmed),
+            format_duration(Duration::from_millis(stats.walk_ms)),
+            format_duration(Duration::from_millis(stats.aggregate_ms))
+        )?;
+
+        Ok(())
+    }
+
+    /// Print the time spent in each phase with its share of the total as a bar
+    pub fn print_profile(&self, out: &mut dyn Write, profile: &Profile) -> Result<()> {
+        let dimmed = Style::new().dimmed();
+        let total = profile.total();
+
+        writeln!(out, "{}", self.paint(" Time profile", Style::new().bold()))?;
+        for timing in &profile.phases {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                timing.duration.as_secs_f64() * 100.0 / total.as_secs_f64()
+            };
+            let bar = "█".repeat((share * BAR_WIDTH as f64 / 100.0).round() as usize);
+            writeln!(
+                out,
+                "  {} {:>8} {:>6.1}%  {}",
+                self.paint(format!("{:<16}", timing.phase), dimmed),
+                format_duration(timing.duration),
+                share,
+                self.paint(bar, Style::new().cyan())
+            )?;
+        }
+        writeln!(
+            out,
+            "  {} {:>8}",
+            self.paint(format!("{:<16}", "total"), dimmed),
+            format_duration(total)
+        )?;
+
+        Ok(())
+    }
+
+    /// Print the paths that could not be read, noting any beyond the recorded cap
+    fn print_errors(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        let dimmed = Style::new().dimmed();
+
+        writeln!(out)?;
+        writeln!(out, "{}", self.paint("─".repeat(self.width), dimmed))?;
+        writeln!(
+            out,
+            "{}",
+            self.paint(" Inaccessible entries:", Style::new().bold())
+        )?;
+        writeln!(out, "{}", self.paint("─".repeat(self.width), dimmed))?;
+        writeln!(out)?;
+
+        for (path, message) in &result.errors {
+            let path = self.relative_path(path, root_path);
+            writeln!(
+                out,
+                "  {}  {}",
+                self.paint(path.display(), Style::new().red()),
+                self.paint(message, dimmed)
+            )?;
+        }
+
+        let unlisted = result
+            .error_count
+            .saturating_sub(result.errors.len() as u64);
+        if unlisted > 0 {
+            writeln!(
+                out,
+                "  {}",
+                self.paint(
+                    format!(
+                        "... and {} more",
+                        format_number(unlisted, self.numbers.separator)
+                    ),
+                    dimmed
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Print the paths of empty directories
+    fn print_empty_dirs(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        if result.nodes.is_empty() {
+            writeln!(
+                out,
+                "  {}",
+                self.paint("No empty directories found.", Style::new().dimmed())
+            )?;
+            return Ok(());
+        }
+
+        for node in &result.nodes {
+            let path_str = self
+                .relative_path(&node.path, root_path)
+                .display()
+                .to_string();
+            let (icon, styled_path) = self.styled_name(node, self.truncate_path(&path_str));
+            writeln!(out, "  {} {}", icon, styled_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Print the top entries as a flat list
+    fn print_list(&self, out: &mut dyn Write, result: &ScanResult, root_path: &Path) -> Result<()> {
+        let dimmed = Style::new().dimmed();
+        let header = Style::new().dimmed().bold();
+
+        // Print header, with optional columns between size and path
+        let mut titles = vec![self.paint(format!("{:>12}", "SIZE"), header)];
+        let mut rules = vec![self.paint(format!("{:>12}", "────"), dimmed)];
+        if self.self_size {
+            titles.push(self.paint(format!("{:>12}", "SELF"), header));
+            rules.push(self.paint(format!("{:>12}", "────"), dimmed));
+        }
+        if self.show_file_counts() {
+            titles.push(self.paint(format!("{:>10}", "FILES"), header));
+            rules.push(self.paint(format!("{:>10}", "─────"), dimmed));
+        }
+        if self.show_avg {
+            titles.push(self.paint(format!("{:>12}", "AVG FILE"), header));
+            rules.push(self.paint(format!("{:>12}", "────────"), dimmed));
+        }
+        if self.show_mtime {
+            titles.push(self.paint(format!("{:>14}", "MODIFIED"), header));
+            rules.push(self.paint(format!("{:>14}", "────────"), dimmed));
+        }
+        titles.push(self.paint("PATH", header));
+        rules.push(self.paint("────", dimmed));
+        writeln!(out, "  {}", titles.join("  "))?;
+        writeln!(out, "  {}", rules.join("  "))?;
+
+        // Print top entries
+        let top_nodes = self.shown_nodes(result);
+
+        if top_nodes.is_empty() {
+            writeln!(out, "  {}", self.paint("No entries found.", dimmed))?;
+        } else {
+            for node in top_nodes {
+                self.print_node(out, node, root_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Print per-extension totals
+    fn print_types(&self, out: &mut dyn Write, result: &ScanResult) -> Result<()> {
+        let types = self.top_types(result);
+        if types.is_empty() {
+            writeln!(
+                out,
+                "  {}",
+                self.paint("No files found.", Style::new().dimmed())
+            )?;
+            return Ok(());
+        }
+
+        for summary in types {
+            let label = match &summary.extension {
+                Some(ext) => format!(".{}", ext),
+                None => "(no extension)".to_string(),
+            };
+            writeln!(
+                out,
+                "  {}  {} files  {}",
+                self.paint(
+                    format!("{:>12}", self.size(summary.size)),
+                    Style::new().green()
+                ),
+                self.paint(format!("{:>10}", summary.file_count), Style::new().cyan()),
+                self.paint(label, Style::new().bold())
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Print the file count and total size of each size range, with a bar for the count
+    fn print_histogram(&self, out: &mut dyn Write, result: &ScanResult) -> Result<()> {
+        let most = result
+            .histogram
+            .iter()
+            .map(|bucket| bucket.file_count)
+            .max()
+            .unwrap_or(0);
+        if most == 0 {
+            writeln!(
+                out,
+                "  {}",
+                self.paint("No files found.", Style::new().dimmed())
+            )?;
+            return Ok(());
+        }
+
+        for bucket in &result.histogram {
+            let range = self.bucket_range(bucket);
+            let bar = "█".repeat((bucket.file_count * BAR_WIDTH as u64).div_ceil(most) as usize);
+            writeln!(
+                out,
+                "  {:>21}  {} files  {}  {}",
+                range,
+                self.paint(
+                    format!(
+                        "{:>10}",
+                        format_number(bucket.file_count, self.numbers.separator)
+                    ),
+                    Style::new().cyan()
+                ),
+                self.paint(
+                    format!("{:>12}", self.size(bucket.size)),
+                    Style::new().green()
+                ),
+                self.paint(bar, Style::new().cyan())
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Format a count with the configured thousands separator
+    fn count_text(&self, count: u64) -> String {
+        format_number(count, self.numbers.separator)
+    }
+
+    /// Report table cell for a size, sorting by its byte count
+    fn size_cell(&self, bytes: u64) -> Cell {
+        Cell::number(self.size(bytes), bytes as f64)
+    }
+
+    /// Report table cell for a count
+    fn count_cell(&self, count: u64) -> Cell {
+        Cell::number(self.count_text(count), count as f64)
+    }
+
+    /// Label for the size range of a histogram bucket, e.g. `1 KiB - 10 KiB`
+    fn bucket_range(&self, bucket: &SizeBucket) -> String {
+        match (bucket.min, bucket.max) {
+            (0, Some(max)) => format!("< {}", self.size(max)),
+            (min, Some(max)) => format!("{} - {}", self.size(min), self.size(max)),
+            (min, None) => format!(">= {}", self.size(min)),
+        }
+    }
+
+    /// Print the directory count and total size at each depth, with a bar for the share of
+    /// the total size
+    fn print_depths(&self, out: &mut dyn Write, result: &ScanResult) -> Result<()> {
+        let levels = result.depth_summary();
+        if levels.is_empty() {
+            writeln!(
+                out,
+                "  {}",
+                self.paint("No directories found.", Style::new().dimmed())
+            )?;
+            return Ok(());
+        }
+
+        for level in levels {
+            let share = if result.total_size == 0 {
+                0.0
+            } else {
+                level.size as f64 / result.total_size as f64
+            };
+            let bar = "█".repeat((share * BAR_WIDTH as f64).round() as usize);
+            writeln!(
+                out,
+                "  depth {:>3}  {} dirs  {}  {:>5.1}%  {}",
+                level.depth,
+                self.paint(
+                    format!(
+                        "{:>10}",
+                        format_number(level.dir_count, self.numbers.separator)
+                    ),
+                    Style::new().cyan()
+                ),
+                self.paint(
+                    format!("{:>12}", self.size(level.size)),
+                    Style::new().green()
+                ),
+                share * 100.0,
+                self.paint(bar, Style::new().cyan())
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Print groups of duplicate files with the space they waste
+    fn print_duplicates(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        let groups = self.top_duplicates(result);
+        if groups.is_empty() {
+            writeln!(
+                out,
+                "  {}",
+                self.paint("No duplicate files found.", Style::new().dimmed())
+            )?;
+            return Ok(());
+        }
+
+        let total_wasted: u64 = result.duplicates.iter().map(|g| g.wasted_size()).sum();
+        writeln!(
+            out,
+            "  {} {}",
+            self.paint("Reclaimable:", Style::new().dimmed()),
+            self.paint(self.size(total_wasted), Style::new().green().bold())
+        )?;
+
+        for group in groups {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "  {}  {} copies of {}",
+                self.paint(
+                    format!("{:>12}", self.size(group.wasted_size())),
+                    Style::new().green()
+                ),
+                self.paint(group.paths.len(), Style::new().cyan()),
+                self.size(group.size)
+            )?;
+            for path in &group.paths {
+                let path_str = self.relative_path(path, root_path).display();
+                writeln!(
+                    out,
+                    "  {:>12}  📄 {}",
+                    "",
+                    self.paint(
+                        self.truncate_path(&path_str.to_string()),
+                        Style::new().white()
+                    )
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Print size changes since a baseline, increases in red and decreases in green
+    fn print_changes(
+        &self,
+        out: &mut dyn Write,
+        result: &ScanResult,
+        root_path: &Path,
+    ) -> Result<()> {
+        let changes = self.top_changes(result);
+        if changes.is_empty() {
+            writeln!(
+                out,
+                "  {}",
+                self.paint("No changes since baseline.", Style::new().dimmed())
+            )?;
+            return Ok(());
+        }
+
+        for change in changes {
+            let delta = change.change();
+            let sign = if delta < 0 { "-" } else { "+" };
+            let amount = self.size(delta.unsigned_abs() as u64);
+            let style = if delta < 0 {
+                Style::new().green()
+            } else {
+                Style::new().red()
+            };
+            let status = match (change.old_size, change.new_size) {
+                (None, _) => "+new",
+                (_, None) => "-gone",
+                _ if delta < 0 => "shrank",
+                _ => "grew",
+            };
+
+            let path_str = self.relative_path(&change.path, root_path);
+            let node = Node::new(change.path.clone(), 0, change.is_dir, 0);
+            let (icon, styled_path) =
+                self.styled_name(&node, self.truncate_path(&path_str.display().to_string()));
+            writeln!(
+                out,
+                "  {}  {}  {} {}",
+                self.paint(format!("{:>12}", format!("{sign}{amount}")), style),
+                self.paint(format!("{:<6}", status), style),
+                icon,
+                styled_path
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Nodes to show: all empty directories, or the configured number of top entries
+    fn shown_nodes<'a>(&self, result: &'a ScanResult) -> &'a [Node] {
+        if self.empty_dirs {
+            &result.nodes
+        } else {
+            result.top_n(self.count)
+        }
+    }
+
+    /// Number of items to show out of `len`; a count of 0 shows everything
+    fn limit(&self, len: usize) -> usize {
+        if self.count == 0 {
+            len
+        } else {
+            std::cmp::min(self.count, len)
+        }
+    }
+
+    /// Section heading such as "Top 10 file types", or "All file types" when unlimited
+    fn heading(&self, what: &str) -> String {
+        if self.count == 0 {
+            format!(" All {}", what)
+        } else {
+            format!(" Top {} {}", self.count, what)
+        }
+    }
+
+    /// Get the configured number of largest changes
+    fn top_changes<'a>(&self, result: &'a ScanResult) -> &'a [SizeChange] {
+        &result.changes[..self.limit(result.changes.len())]
+    }
+
+    /// Get the configured number of duplicate groups
+    fn top_duplicates<'a>(&self, result: &'a ScanResult) -> &'a [DuplicateGroup] {
+        &result.duplicates[..self.limit(result.duplicates.len())]
     }
-}
 
-impl Display {
-    /// Create a new Display with default settings
-    pub fn new() -> Self {
-        Self::default()
+    /// Get the configured number of largest file types
+    fn top_types<'a>(&self, result: &'a ScanResult) -> &'a [TypeSummary] {
+        &result.types[..self.limit(result.types.len())]
     }
 
-    /// Set the number of items to display
-    pub fn with_count(mut self, count: usize) -> Self {
-        self.count = count;
-        self
+    /// Print the entries as an indented tree rooted at the scan path
+    fn print_tree(&self, out: &mut dyn Write, result: &ScanResult, root_path: &Path) -> Result<()> {
+        // Rebuild parent/child relationships from the flat node list;
+        // siblings keep the order the nodes were sorted in
+        let mut children: HashMap<&Path, Vec<&Node>> = HashMap::new();
+        for node in &result.nodes {
+            if let Some(parent) = node.path.parent() {
+                children.entry(parent).or_default().push(node);
+            }
+        }
+
+        writeln!(
+            out,
+            "  {}  📁 {}",
+            self.paint(
+                format!("{:>12}", self.size(result.total_size)),
+                Style::new().green()
+            ),
+            self.paint(root_path.display(), Style::new().blue().bold())
+        )?;
+        self.print_subtree(out, &children, root_path, "")
     }
 
-    /// Print the scan results to stdout
-    pub fn print_results(&self, result: &ScanResult, root_path: &std::path::Path) {
-        println!();
-        println!("{}", "═".repeat(70).dimmed());
-        println!(
-            "{}",
-            format!(" Disk Usage Report: {}", root_path.display()).bold()
-        );
-        println!("{}", "═".repeat(70).dimmed());
-        println!();
+    /// Print the children of `dir`, recursing into subdirectories
+    fn print_subtree(
+        &self,
+        out: &mut dyn Write,
+        children: &HashMap<&Path, Vec<&Node>>,
+        dir: &Path,
+        prefix: &str,
+    ) -> Result<()> {
+        let Some(siblings) = children.get(dir) else {
+            return Ok(());
+        };
 
-        // Print summary
-        println!(
-            "  {} {}",
-            "Total size:".dimmed(),
-            format_size(result.total_size, BINARY).green().bold()
-        );
-        println!(
-            "  {} {} files, {} directories",
-            "Scanned:".dimmed(),
-            result.file_count.to_string().cyan(),
-            result.dir_count.to_string().cyan()
-        );
+        // Small directories are summed into one row (subtrees included) instead of listed
+        let (collapsed, siblings): (Vec<&Node>, Vec<&Node>) = siblings.iter().partition(|node| {
+            node.is_dir && self.collapse_under.is_some_and(|min| node.size < min)
+        });
 
-        if result.error_count > 0 {
-            println!(
-                "  {} {} (permission denied or inaccessible)",
-                "Errors:".dimmed(),
-                result.error_count.to_string().red()
-            );
+        // Limit each level to the configured count
+        let shown = &siblings[..self.limit(siblings.len())];
+        for (idx, node) in shown.iter().enumerate() {
+            let is_last = idx + 1 == shown.len() && collapsed.is_empty();
+            let (branch, indent) = if is_last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+
+            let name = node
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| node.path.display().to_string());
+            let (icon, styled_name) = self.styled_name(node, name);
+
+            writeln!(
+                out,
+                "  {}  {}{} {}",
+                self.paint(
+                    format!("{:>12}", self.size(node.size)),
+                    self.size_style(node.size)
+                ),
+                self.paint(format!("{prefix}{branch}"), Style::new().dimmed()),
+                icon,
+                styled_name
+            )?;
+
+            if node.is_dir {
+                self.print_subtree(out, children, &node.path, &format!("{prefix}{indent}"))?;
+            }
         }
 
-        println!();
-        println!("{}", "─".repeat(70).dimmed());
-        println!("{}", format!(" Top {} by size:", self.count).bold());
-        println!("{}", "─".repeat(70).dimmed());
-        println!();
+        if !collapsed.is_empty() {
+            let total: u64 = collapsed.iter().map(|node| node.size).sum();
+            let noun = if collapsed.len() == 1 { "dir" } else { "dirs" };
+            writeln!(
+                out,
+                "  {}  {}{}",
+                self.paint(format!("{:>12}", self.size(total)), self.size_style(total)),
+                self.paint(format!("{prefix}└── "), Style::new().dimmed()),
+                self.paint(
+                    format!(
+                        "({} small {}: total {})",
+                        collapsed.len(),
+                        noun,
+                        self.size(total)
+                    ),
+                    Style::new().dimmed()
+                )
+            )?;
+        }
 
-        // Print header
-        println!(
-            "  {:>12}  {}",
-            "SIZE".dimmed().bold(),
-            "PATH".dimmed().bold()
-        );
-        println!("  {:>12}  {}", "────".dimmed(), "────".dimmed());
+        Ok(())
+    }
 
-        // Print top entries
-        let top_nodes = result.top_n(self.count);
+    /// Print a single node
+    fn print_node(&self, out: &mut dyn Write, node: &Node, root_path: &Path) -> Result<()> {
+        let mut columns = vec![self.paint(
+            format!("{:>12}", self.size(node.size)),
+            self.size_style(node.size),
+        )];
+        if self.self_size {
+            columns.push(self.paint(
+                format!("{:>12}", self.size(node.self_size)),
+                Style::new().green(),
+            ));
+        }
+        if self.show_file_counts() {
+            columns.push(self.paint(format!("{:>10}", node.file_count), Style::new().cyan()));
+        }
+        if self.show_avg {
+            let average = node
+                .average_file_size()
+                .map_or_else(|| "-".to_string(), |avg| self.size(avg));
+            columns.push(self.paint(format!("{:>12}", average), Style::new().yellow()));
+        }
+        if self.show_mtime {
+            // Directories carry the time of their newest file
+            let age = node.modified.map_or_else(
+                || "-".to_string(),
+                |time| format_age(time, SystemTime::now()),
+            );
+            columns.push(self.paint(format!("{:>14}", age), Style::new().blue()));
+        }
+        let path_str = self
+            .relative_path(&node.path, root_path)
+            .display()
+            .to_string();
+        let (icon, styled_path) = self.styled_name(node, self.truncate_path(&path_str));
 
-        if top_nodes.is_empty() {
-            println!("  {}", "No entries found.".dimmed());
+        writeln!(out, "  {}  {} {}", columns.join("  "), icon, styled_path)?;
+
+        Ok(())
+    }
+
+    /// Icon and styled label for a node
+    fn styled_name(&self, node: &Node, name: String) -> (&'static str, String) {
+        if !node.accessible {
+            // A 0 size here means "couldn't look inside", not "empty"
+            let label = format!("{} (unreadable)", name);
+            ("⚠️", self.paint(label, Style::new().yellow().bold()))
+        } else if node.is_dir {
+            ("📁", self.paint(name, Style::new().blue().bold()))
+        } else if self.icons {
+            let (icon, style) = file_icon(&node.path);
+            (icon, self.paint(name, style))
         } else {
-            for node in top_nodes {
-                self.print_node(node, root_path);
-            }
+            ("📄", self.paint(name, Style::new().white()))
         }
+    }
 
-        println!();
-        println!("{}", "═".repeat(70).dimmed());
+    /// Style for an entry's size: green, or by magnitude with --heat
+    fn size_style(&self, size: u64) -> Style {
+        match self.heat {
+            Some((_, hot)) if size > hot => Style::new().red(),
+            Some((warm, _)) if size >= warm => Style::new().yellow(),
+            _ => Style::new().green(),
+        }
     }
 
-    /// Print a single node
-    fn print_node(&self, node: &Node, root_path: &std::path::Path) {
-        let size_str = format_size(node.size, BINARY);
-        let relative_path = node.path.strip_prefix(root_path).unwrap_or(&node.path);
+    /// Path relative to the scan root, or to --relative-to when given; paths outside
+    /// that base, and all paths with --absolute-paths, stay absolute
+    fn relative_path<'a>(&self, path: &'a Path, root_path: &Path) -> &'a Path {
+        if self.absolute_paths {
+            return path;
+        }
+        let base = self.relative_to.as_deref().unwrap_or(root_path);
+        match path.strip_prefix(base) {
+            // The base itself, e.g. the root shown with --include-root
+            Ok(relative) if relative.as_os_str().is_empty() => Path::new("."),
+            Ok(relative) => relative,
+            Err(_) => path,
+        }
+    }
 
-        let path_str = relative_path.display().to_string();
-        let display_path = self.truncate_path(&path_str);
+    /// Format a size in the configured units with the configured decimal separator
+    fn size(&self, bytes: u64) -> String {
+        self.numbers
+            .localize(format_bytes(bytes, self.units, self.numbers.precision))
+    }
 
-        let (icon, styled_path) = if node.is_dir {
-            ("📁", display_path.blue().bold().to_string())
+    /// Apply a style to text when color output is enabled
+    fn paint(&self, text: impl std::fmt::Display, style: Style) -> String {
+        if self.color {
+            text.style(style).to_string()
         } else {
-            ("📄", display_path.white().to_string())
-        };
+            text.to_string()
+        }
+    }
 
-        // Calculate percentage of total if we had access to it
-        println!("  {:>12}  {} {}", size_str.green(), icon, styled_path);
+    /// Whether the file count column is shown
+    fn show_file_counts(&self) -> bool {
+        self.sort == SortKey::Count
     }
 
     /// Truncate a path if it's too long
     fn truncate_path(&self, path: &str) -> String {
-        if path.len() <= self.max_path_width {
-            path.to_string()
+        let width = self.max_path_width;
+        let len = path.chars().count();
+        if len <= width {
+            return path.to_string();
+        }
+
+        let keep = width.saturating_sub(3);
+        match self.truncate {
+            Truncate::Start => format!("...{}", path.chars().skip(len - keep).collect::<String>()),
+            Truncate::End => format!("{}...", path.chars().take(keep).collect::<String>()),
+            Truncate::Middle => truncate_middle(path, width),
+        }
+    }
+}
+
+/// Shorten a path to `width` characters by eliding whole components from the middle,
+/// falling back to characters when the first and last components alone don't fit
+fn truncate_middle(path: &str, width: usize) -> String {
+    let sep = std::path::MAIN_SEPARATOR_STR;
+    let parts: Vec<&str> = path.split(sep).collect();
+    let chars = |s: &str| s.chars().count();
+
+    // Components [..head] and [tail..] are kept, with "..." in place of the rest; the
+    // empty component before a leading separator is kept together with the next one
+    let mut head = if parts[0].is_empty() { 2 } else { 1 };
+    let mut tail = parts.len() - 1;
+    let mut used = if tail > head {
+        chars(path) - chars(&parts[head..tail].join(sep)) + 3
+    } else {
+        usize::MAX
+    };
+    if used <= width {
+        loop {
+            let before = (head, tail);
+            if tail > head && used + chars(parts[tail - 1]) + sep.len() <= width {
+                tail -= 1;
+                used += chars(parts[tail]) + sep.len();
+            }
+            if head < tail && used + chars(parts[head]) + sep.len() <= width {
+                used += chars(parts[head]) + sep.len();
+                head += 1;
+            }
+            if (head, tail) == before {
+                break;
+            }
+        }
+        return format!(
+            "{}{sep}...{sep}{}",
+            parts[..head].join(sep),
+            parts[tail..].join(sep)
+        );
+    }
+
+    let all: Vec<char> = path.chars().collect();
+    let keep = width.saturating_sub(3);
+    let (front, back) = (keep / 2, keep - keep / 2);
+    format!(
+        "{}...{}",
+        all[..front].iter().collect::<String>(),
+        all[all.len() - back..].iter().collect::<String>()
+    )
+}
+
+/// Totals for one root in machine-readable output
+fn json_summary<'a>(result: &ScanResult, root_path: &'a Path) -> JsonSummary<'a> {
+    JsonSummary {
+        schema: JSON_SCHEMA_VERSION,
+        root: root_path,
+        total_size: result.total_size,
+        file_count: result.file_count,
+        dir_count: result.dir_count,
+        error_count: result.error_count,
+        cycles_skipped: result.cycles_skipped,
+        max_scan_depth: result.max_scan_depth,
+        interrupted: result.interrupted,
+        scan_duration_ms: result.scan_duration_ms,
+        files_per_second: result.files_per_second(),
+        disk: result.disk,
+    }
+}
+
+/// Write the whole tree in ncdu's JSON export format, version 1.2
+/// (`[1, 2, {metadata}, [{root}, {file}, [{dir}, ...], ...]]`).
+///
+/// Directories carry no size of their own since ncdu sums their contents, so every file
+/// must be in `result.nodes`.
+fn write_ncdu(out: &mut dyn Write, result: &ScanResult, root_path: &Path) -> Result<()> {
+    let mut children: HashMap<&Path, Vec<&Node>> = HashMap::new();
+    for node in &result.nodes {
+        if let Some(parent) = node.path.parent() {
+            children.entry(parent).or_default().push(node);
+        }
+    }
+
+    let meta = NcduMeta {
+        progname: env!("CARGO_PKG_NAME"),
+        progver: env!("CARGO_PKG_VERSION"),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+    };
+    write!(out, "[1,2,")?;
+    serde_json::to_writer(&mut *out, &meta).context("Failed to serialize ncdu export")?;
+    write!(out, ",")?;
+    // ncdu expects the full path as the root's name
+    let root = NcduEntry {
+        name: root_path.to_string_lossy(),
+        asize: 0,
+        dsize: 0,
+        read_error: false,
+    };
+    write_ncdu_dir(out, &children, root_path, &root)?;
+    writeln!(out, "]")?;
+    Ok(())
+}
+
+/// Write a directory as an array of its info object followed by its children
+fn write_ncdu_dir(
+    out: &mut dyn Write,
+    children: &HashMap<&Path, Vec<&Node>>,
+    dir: &Path,
+    info: &NcduEntry,
+) -> Result<()> {
+    write!(out, "[")?;
+    serde_json::to_writer(&mut *out, info).context("Failed to serialize ncdu export")?;
+    for node in children.get(dir).into_iter().flatten() {
+        write!(out, ",")?;
+        let name = node.path.file_name().map_or_else(
+            || node.path.to_string_lossy(),
+            |name| name.to_string_lossy(),
+        );
+        if node.is_dir {
+            let info = NcduEntry {
+                name,
+                asize: 0,
+                dsize: 0,
+                read_error: !node.accessible,
+            };
+            write_ncdu_dir(out, children, &node.path, &info)?;
+        } else {
+            let entry = NcduEntry {
+                name,
+                asize: node.apparent_size,
+                dsize: node.size,
+                read_error: false,
+            };
+            serde_json::to_writer(&mut *out, &entry).context("Failed to serialize ncdu export")?;
+        }
+    }
+    write!(out, "]")?;
+    Ok(())
+}
+
+/// Write a value as pretty-printed JSON
+fn write_json(out: &mut dyn Write, value: &impl Serialize) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *out, value)
+        .context("Failed to serialize results as JSON")?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Load the entries of a JSON report saved with `--format json`, keyed by path.
+///
+/// Files are skipped unless `include_files` is set.
+pub fn load_baseline(path: &Path, include_files: bool) -> Result<HashMap<PathBuf, (u64, bool)>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open baseline '{}'", path.display()))?;
+    let baseline: Baseline = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to parse baseline JSON '{}'", path.display()))?;
+    let reports = match baseline {
+        Baseline::Single(report) => vec![report],
+        Baseline::Multiple(reports) => reports,
+    };
+    // Every version so far has the node fields read here; only a newer one may not
+    for report in &reports {
+        let schema = report.schema.unwrap_or(1);
+        if schema > JSON_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Baseline '{}' has JSON schema {}, newer than this version reads (up to {}); compare it with a newer disk-scanner",
+                path.display(),
+                schema,
+                JSON_SCHEMA_VERSION
+            );
+        }
+    }
+
+    Ok(reports
+        .into_iter()
+        .flat_map(|report| report.nodes)
+        .filter(|node| node.is_dir || include_files)
+        .map(|node| (node.path, (node.size, node.is_dir)))
+        .collect())
+}
+
+/// Format a byte count with binary (KiB) or SI (KB) units, or as a plain number.
+///
+/// `precision` fixes the decimal places; by default up to two are shown, without trailing
+/// zeros.
+pub fn format_bytes(bytes: u64, units: Units, precision: Option<usize>) -> String {
+    let options = match units {
+        Units::Binary => BINARY,
+        Units::Si => DECIMAL,
+        Units::Bytes => return bytes.to_string(),
+    };
+    match precision {
+        Some(places) => format_size(bytes, options.decimal_places(places).decimal_zeroes(places)),
+        None => format_size(bytes, options),
+    }
+}
+
+/// Describe how long before `now` a modification `time` was, e.g. `3 days ago`.
+///
+/// Months are counted as 30 days and years as 365; times in the future (clock skew,
+/// odd archive dates) read `in the future`.
+pub fn format_age(time: SystemTime, now: SystemTime) -> String {
+    let Ok(age) = now.duration_since(time) else {
+        return "in the future".to_string();
+    };
+    const UNITS: [(&str, u64); 5] = [
+        ("year", 365 * 86_400),
+        ("month", 30 * 86_400),
+        ("day", 86_400),
+        ("hour", 3_600),
+        ("minute", 60),
+    ];
+    let secs = age.as_secs();
+    if secs < 10 {
+        return "just now".to_string();
+    }
+    let (unit, count) = UNITS
+        .iter()
+        .map(|&(unit, len)| (unit, secs / len))
+        .find(|&(_, count)| count > 0)
+        .unwrap_or(("second", secs));
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+/// One cell of a Markdown or HTML report table
+struct Cell {
+    text: String,
+    /// Whether the text is a path or name, shown as code
+    code: bool,
+    /// Number the HTML report sorts the column by (bytes for sizes)
+    value: Option<f64>,
+}
+
+impl Cell {
+    /// Plain text
+    fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            code: false,
+            value: None,
+        }
+    }
+
+    /// A path or name, shown as code
+    fn code(text: impl Into<String>) -> Self {
+        Self {
+            code: true,
+            ..Self::text(text)
+        }
+    }
+
+    /// Formatted text for a number the column sorts by
+    fn number(text: impl Into<String>, value: f64) -> Self {
+        Self {
+            value: Some(value),
+            ..Self::text(text)
+        }
+    }
+
+    /// The cell's Markdown source
+    fn markdown(&self) -> String {
+        if self.code {
+            markdown_code(&self.text)
+        } else {
+            self.text.clone()
+        }
+    }
+
+    /// The cell's HTML content
+    fn html(&self) -> String {
+        if self.code {
+            format!("<code>{}</code>", html_escape(&self.text))
         } else {
-            let start = path.len() - self.max_path_width + 3;
-            format!("...{}", &path[start..])
+            html_escape(&self.text)
+        }
+    }
+}
+
+/// Style sheet of the HTML report
+const HTML_STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { padding: 0.3em 0.8em; border-bottom: 1px solid #ddd; text-align: left; }
+th { background: #f4f4f4; cursor: pointer; user-select: none; }
+th[data-order="asc"]::after { content: " \25B2"; }
+th[data-order="desc"]::after { content: " \25BC"; }
+.num { text-align: right; font-variant-numeric: tabular-nums; }
+.warning { color: #a15c00; }
+"#;
+
+/// Sorts a report table by the clicked column: numerically by `data-value` in number
+/// columns (cells without one, such as `-`, count as the smallest), else by text
+const HTML_SCRIPT: &str = r#"
+document.querySelectorAll("th").forEach((th) => {
+  th.addEventListener("click", () => {
+    const table = th.closest("table");
+    const body = table.tBodies[0];
+    const column = th.cellIndex;
+    const numeric = th.classList.contains("num");
+    const descending = th.dataset.order !== "desc";
+    table.querySelectorAll("th").forEach((other) => delete other.dataset.order);
+    th.dataset.order = descending ? "desc" : "asc";
+    const key = (row) => {
+      const cell = row.cells[column];
+      if (!numeric) return cell.textContent;
+      return cell.dataset.value === undefined ? -Infinity : Number(cell.dataset.value);
+    };
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const order = numeric ? (x > y) - (x < y) : x.localeCompare(y, undefined, { numeric: true });
+      return descending ? -order : order;
+    });
+    rows.forEach((row) => body.appendChild(row));
+  });
+});
+"#;
+
+/// Write a Markdown table with `(title, right-aligned)` columns
+fn write_markdown_table(
+    out: &mut dyn Write,
+    columns: &[(&str, bool)],
+    rows: &[Vec<Cell>],
+) -> Result<()> {
+    let titles: Vec<&str> = columns.iter().map(|(title, _)| *title).collect();
+    let rules: Vec<&str> = columns
+        .iter()
+        .map(|(_, right)| if *right { "---:" } else { "---" })
+        .collect();
+    writeln!(out, "| {} |", titles.join(" | "))?;
+    writeln!(out, "|{}|", rules.join("|"))?;
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(Cell::markdown).collect();
+        writeln!(out, "| {} |", cells.join(" | "))?;
+    }
+    Ok(())
+}
+
+/// Quote text as inline code for a Markdown table cell, escaping pipes so they don't end
+/// the cell (paths such as `__init__.py` would otherwise be rendered as emphasis)
+fn markdown_code(text: &str) -> String {
+    let text = text.replace('|', "\\|");
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// Escape text for HTML element content and quoted attribute values
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
         }
     }
+    escaped
+}
+
+/// Quote a CSV field if it contains separators, quotes, or line breaks
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
 }
 
 #[cfg(test)]
@@ -134,6 +2426,20 @@ mod tests {
         assert_eq!(display.truncate_path(path), "short/path");
     }
 
+    #[test]
+    fn test_with_width() {
+        let display = Display::new().with_width(100).with_color(false);
+        assert_eq!(display.max_path_width, 90);
+
+        let mut out = Vec::new();
+        display
+            .print_results(&mut out, &ScanResult::new(), Path::new("/data"))
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.lines().any(|line| line == "═".repeat(100)));
+        assert!(!out.contains(&"═".repeat(101)));
+    }
+
     #[test]
     fn test_truncate_path_long() {
         let display = Display {
@@ -145,4 +2451,549 @@ mod tests {
         assert!(truncated.starts_with("..."));
         assert!(truncated.len() <= 23); // 20 + "..."
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_truncate_strategies() {
+        let display = |truncate| Display {
+            max_path_width: 20,
+            truncate,
+            ..Default::default()
+        };
+        let path = "home/user/projects/deep/file.txt";
+
+        assert_eq!(
+            display(Truncate::Start).truncate_path(path),
+            "...cts/deep/file.txt"
+        );
+        assert_eq!(
+            display(Truncate::End).truncate_path(path),
+            "home/user/project..."
+        );
+        assert_eq!(
+            display(Truncate::Middle).truncate_path(path),
+            "home/.../file.txt"
+        );
+        assert_eq!(
+            display(Truncate::Middle).truncate_path("/home/user/a/deep/file.txt"),
+            "/home/.../file.txt"
+        );
+
+        // A single long component falls back to eliding characters
+        assert_eq!(
+            display(Truncate::Middle).truncate_path("a_very_long_file_name_indeed.txt"),
+            "a_very_l...ndeed.txt"
+        );
+    }
+
+    #[test]
+    fn test_truncate_boundaries() {
+        for truncate in [Truncate::Start, Truncate::Middle, Truncate::End] {
+            let display = Display {
+                max_path_width: 10,
+                truncate,
+                ..Default::default()
+            };
+            assert_eq!(display.truncate_path("short"), "short");
+            assert_eq!(display.truncate_path("exactly/10"), "exactly/10");
+            let truncated = display.truncate_path("eleven/char");
+            assert_eq!(truncated.chars().count(), 10, "{truncate:?}");
+            assert!(truncated.contains("..."));
+            assert_eq!(display.truncate_path("ünïcödé/pâth/ok").chars().count(), 10);
+        }
+    }
+
+    #[test]
+    fn test_truncate_multibyte_boundary() {
+        // Every cut point lands between multi-byte characters (3-byte CJK, 4-byte emoji)
+        let path = "写真/🎉🎉🎉🎉/データ/ファイル🎵.txt";
+        for width in 3..path.chars().count() {
+            for truncate in [Truncate::Start, Truncate::Middle, Truncate::End] {
+                let display = Display {
+                    max_path_width: width,
+                    truncate,
+                    ..Default::default()
+                };
+                let truncated = display.truncate_path(path);
+                // Middle elides whole components, so it may come out shorter
+                let len = truncated.chars().count();
+                assert!(len <= width, "{truncate:?} {width}: {truncated}");
+                if truncate != Truncate::Middle {
+                    assert_eq!(len, width, "{truncate:?} {width}");
+                }
+                assert!(truncated.contains("..."), "{truncate:?} {width}");
+            }
+        }
+
+        let display = Display {
+            max_path_width: 10,
+            ..Default::default()
+        };
+        assert_eq!(display.truncate_path(path), "...イル🎵.txt");
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("plain/path"), "plain/path");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_format_bytes_units() {
+        assert_eq!(format_bytes(1_000_000, Units::Si, None), "1 MB");
+        assert_eq!(format_bytes(1_000_000, Units::Binary, None), "976.56 KiB");
+        assert_eq!(format_bytes(1024, Units::Binary, None), "1 KiB");
+        assert_eq!(format_bytes(1_000_000, Units::Bytes, None), "1000000");
+    }
+
+    #[test]
+    fn test_format_age() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let ago = |secs: u64| format_age(now - Duration::from_secs(secs), now);
+        assert_eq!(ago(0), "just now");
+        assert_eq!(ago(9), "just now");
+        assert_eq!(ago(45), "45 seconds ago");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(2 * 3_600 + 59 * 60), "2 hours ago");
+        assert_eq!(ago(3 * 86_400), "3 days ago");
+        assert_eq!(ago(29 * 86_400), "29 days ago");
+        assert_eq!(ago(45 * 86_400), "1 month ago");
+        assert_eq!(ago(364 * 86_400), "12 months ago");
+        assert_eq!(ago(800 * 86_400), "2 years ago");
+        assert_eq!(
+            format_age(now + Duration::from_secs(60), now),
+            "in the future"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes_precision() {
+        assert_eq!(format_bytes(1_234_567, Units::Si, Some(0)), "1 MB");
+        assert_eq!(format_bytes(1_234_567, Units::Si, Some(2)), "1.23 MB");
+        assert_eq!(format_bytes(1_234_567, Units::Si, Some(4)), "1.2346 MB");
+        assert_eq!(format_bytes(1024, Units::Binary, Some(2)), "1.00 KiB");
+        assert_eq!(format_bytes(1_234_567, Units::Bytes, Some(4)), "1234567");
+    }
+
+    #[test]
+    fn test_size_style_heat_buckets() {
+        let plain = Display::new();
+        assert_eq!(plain.size_style(5_000_000_000), Style::new().green());
+
+        let heat = Display::new().with_heat(Some((100_000_000, 1_000_000_000)));
+        assert_eq!(heat.size_style(0), Style::new().green());
+        assert_eq!(heat.size_style(99_999_999), Style::new().green());
+        assert_eq!(heat.size_style(100_000_000), Style::new().yellow());
+        assert_eq!(heat.size_style(1_000_000_000), Style::new().yellow());
+        assert_eq!(heat.size_style(1_000_000_001), Style::new().red());
+    }
+
+    #[test]
+    fn test_styled_name_inaccessible() {
+        let display = Display::new().with_color(false);
+        let dir = Node::new(PathBuf::from("/r/open"), 0, true, 1);
+        assert_eq!(
+            display.styled_name(&dir, "open".into()),
+            ("📁", "open".into())
+        );
+
+        let locked = Node::new(PathBuf::from("/r/locked"), 0, true, 1).with_accessible(false);
+        assert_eq!(
+            display.styled_name(&locked, "locked".into()),
+            ("⚠️", "locked (unreadable)".into())
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let root = Path::new("/work/project/src");
+        let display = Display::new();
+        assert_eq!(
+            display.relative_path(Path::new("/work/project/src/lib"), root),
+            Path::new("lib")
+        );
+        assert_eq!(display.relative_path(root, root), Path::new("."));
+
+        // A base above the scan root keeps the leading directories
+        let display = Display::new().with_relative_to(Some(PathBuf::from("/work/project")));
+        assert_eq!(
+            display.relative_path(Path::new("/work/project/src/lib"), root),
+            Path::new("src/lib")
+        );
+        assert_eq!(
+            display.relative_path(Path::new("/work/project"), root),
+            Path::new(".")
+        );
+
+        // Paths outside the base are shown in full
+        let display = Display::new().with_relative_to(Some(PathBuf::from("/elsewhere")));
+        assert_eq!(
+            display.relative_path(Path::new("/work/project/src/lib"), root),
+            Path::new("/work/project/src/lib")
+        );
+
+        let display = Display::new().with_absolute_paths(true);
+        assert_eq!(display.relative_path(root, root), root);
+        assert_eq!(
+            display.relative_path(Path::new("/work/project/src/lib"), root),
+            Path::new("/work/project/src/lib")
+        );
+    }
+
+    #[test]
+    fn test_file_icon() {
+        assert_eq!(file_icon(Path::new("/r/clip.MKV")).0, "🎬");
+        assert_eq!(file_icon(Path::new("/r/photo.jpeg")).0, "🖼️");
+        assert_eq!(file_icon(Path::new("/r/backup.tar.gz")).0, "📦");
+        assert_eq!(file_icon(Path::new("/r/main.rs")).0, "📜");
+        assert_eq!(
+            file_icon(Path::new("/r/Makefile")),
+            ("📄", Style::new().white())
+        );
+        assert_eq!(file_icon(Path::new("/r/data.unknown")).0, "📄");
+
+        // The generic icon is kept unless --icons is on
+        let video = Node::new(PathBuf::from("/r/clip.mp4"), 10, false, 1);
+        let display = Display::new().with_color(false);
+        assert_eq!(display.styled_name(&video, "clip.mp4".into()).0, "📄");
+        assert_eq!(
+            display
+                .with_icons(true)
+                .styled_name(&video, "clip.mp4".into())
+                .0,
+            "🎬"
+        );
+    }
+
+    #[test]
+    fn test_tree_collapse_under() {
+        let mut result = ScanResult::new();
+        result.total_size = 1030;
+        result.nodes = vec![
+            Node::new(PathBuf::from("/r/big"), 1000, true, 1),
+            Node::new(PathBuf::from("/r/big/tiny"), 5, true, 2),
+            Node::new(PathBuf::from("/r/a"), 20, true, 1),
+            Node::new(PathBuf::from("/r/b"), 6, true, 1),
+            Node::new(PathBuf::from("/r/b/c"), 6, true, 2),
+            Node::new(PathBuf::from("/r/note.txt"), 3, false, 1),
+            Node::new(PathBuf::from("/r/d"), 1, true, 1),
+        ];
+        let display = Display::new()
+            .with_color(false)
+            .with_units(Units::Bytes)
+            .with_collapse_under(Some(100));
+
+        let mut out = Vec::new();
+        display
+            .print_tree(&mut out, &result, Path::new("/r"))
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = out.lines().map(str::trim).collect();
+        assert_eq!(
+            rows,
+            [
+                "1030  📁 /r",
+                "1000  ├── 📁 big",
+                "5  │   └── (1 small dir: total 5)",
+                "3  ├── 📄 note.txt",
+                "27  └── (3 small dirs: total 27)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_print_stats() {
+        let stats = ScanStats {
+            threads: 2,
+            entries_per_thread: vec![3000, 1000],
+            visited_lock_waits: Some(7),
+            walk_ms: 1500,
+            aggregate_ms: 20,
+        };
+        let mut out = Vec::new();
+        Display::new()
+            .with_color(false)
+            .print_stats(&mut out, &stats, Path::new("/data"))
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Threads: 2"));
+        assert!(out.contains("thread  0:        3,000 entries (75.0%)"));
+        assert!(out.contains("thread  1:        1,000 entries (25.0%)"));
+        assert!(out.contains("Visited-directory lock waits: 7"));
+
+        // Without -L the lock isn't used, so there is nothing to show
+        let stats = ScanStats {
+            visited_lock_waits: None,
+            ..stats
+        };
+        let mut out = Vec::new();
+        Display::new()
+            .with_color(false)
+            .print_stats(&mut out, &stats, Path::new("/data"))
+            .unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains("lock waits"));
+    }
+
+    #[test]
+    fn test_print_profile() {
+        let mut profile = Profile::default();
+        profile.add("walk", Duration::from_millis(750));
+        profile.add("display", Duration::from_millis(250));
+        let mut out = Vec::new();
+        Display::new()
+            .with_color(false)
+            .print_profile(&mut out, &profile)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&format!(
+            "  walk                750ms   75.0%  {}\n",
+            "█".repeat(23)
+        )));
+        assert!(out.contains(&format!(
+            "  display             250ms   25.0%  {}\n",
+            "█".repeat(8)
+        )));
+        assert!(out.contains("  total               1.00s"));
+    }
+
+    #[test]
+    fn test_print_histogram() {
+        let mut result = ScanResult::new();
+        result.histogram = vec![
+            SizeBucket {
+                min: 0,
+                max: Some(1000),
+                file_count: 4,
+                size: 2000,
+            },
+            SizeBucket {
+                min: 1000,
+                max: Some(1_000_000),
+                file_count: 0,
+                size: 0,
+            },
+            SizeBucket {
+                min: 1_000_000,
+                max: None,
+                file_count: 1,
+                size: 5_000_000,
+            },
+        ];
+        let mut out = Vec::new();
+        Display::new()
+            .with_color(false)
+            .with_units(Units::Si)
+            .print_histogram(&mut out, &result)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&format!(
+            "                 < 1 kB           4 files          2 kB  {}\n",
+            "█".repeat(BAR_WIDTH)
+        )));
+        assert!(out.contains("            1 kB - 1 MB           0 files           0 B  \n"));
+        assert!(out.contains(&format!(
+            "                >= 1 MB           1 files          5 MB  {}\n",
+            "█".repeat(8)
+        )));
+
+        let mut out = Vec::new();
+        Display::new()
+            .with_color(false)
+            .print_histogram(&mut out, &ScanResult::new())
+            .unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("No files found."));
+    }
+
+    #[test]
+    fn test_print_depths() {
+        let mut result = ScanResult::new();
+        result.total_size = 1000;
+        result.nodes = vec![
+            Node::new(PathBuf::from("/r/a"), 800, true, 1),
+            Node::new(PathBuf::from("/r/b"), 200, true, 1),
+            Node::new(PathBuf::from("/r/a/x"), 500, true, 2),
+        ];
+        let mut out = Vec::new();
+        Display::new()
+            .with_color(false)
+            .with_units(Units::Bytes)
+            .print_depths(&mut out, &result)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&format!(
+            "  depth   1           2 dirs          1000  100.0%  {}\n",
+            "█".repeat(BAR_WIDTH)
+        )));
+        assert!(out.contains(&format!(
+            "  depth   2           1 dirs           500   50.0%  {}\n",
+            "█".repeat(15)
+        )));
+    }
+
+    #[test]
+    fn test_print_interrupted() {
+        let mut result = ScanResult::new();
+        result.interrupted = true;
+        let display = Display::new().with_color(false);
+        let mut out = Vec::new();
+        display
+            .print_results(&mut out, &result, Path::new("/r"))
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(" Disk Usage Report: /r PARTIAL (interrupted)\n"));
+        assert!(out.contains("Note: scan was interrupted"));
+
+        // JSON only mentions it when set
+        let json = serde_json::to_value(display.json_report(&result, Path::new("/r"))).unwrap();
+        assert_eq!(json["interrupted"], true);
+        result.interrupted = false;
+        let json = serde_json::to_value(display.json_report(&result, Path::new("/r"))).unwrap();
+        assert!(json.get("interrupted").is_none());
+    }
+
+    #[test]
+    fn test_write_markdown() {
+        let mut result = ScanResult::new();
+        result.total_size = 400;
+        result.file_count = 2;
+        result.dir_count = 1;
+        result.nodes = vec![
+            Node::new(PathBuf::from("/r/a|b"), 300, true, 1),
+            Node::new(PathBuf::from("/r/a|b/__init__.py"), 100, false, 2),
+        ];
+        let mut out = Vec::new();
+        Display::new()
+            .with_format(OutputFormat::Markdown)
+            .with_units(Units::Bytes)
+            .print_results(&mut out, &result, Path::new("/r"))
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("## Disk usage: `/r`\n\nTotal size: **400** in 2 files"));
+        assert!(out.contains("| Size | % | Type | Path |\n|---:|---:|---|---|\n"));
+        assert!(out.contains("| 300 | 75.0% | dir | `a\\|b` |\n"));
+        assert!(out.contains("| 100 | 25.0% | file | `a\\|b/__init__.py` |\n"));
+
+        // Without files there is no type column
+        result.nodes.truncate(1);
+        let mut out = Vec::new();
+        Display::new()
+            .with_format(OutputFormat::Markdown)
+            .with_units(Units::Bytes)
+            .print_results(&mut out, &result, Path::new("/r"))
+            .unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("| Size | % | Path |\n")
+        );
+    }
+
+    #[test]
+    fn test_write_print0() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/r/with space"), 300, true, 1),
+            Node::new(PathBuf::from("/r/line\nbreak"), 200, true, 1),
+            Node::new(PathBuf::from("/r/small"), 100, true, 1),
+        ];
+        let mut out = Vec::new();
+        Display::new()
+            .with_print0(true)
+            .with_count(2)
+            .print_results(&mut out, &result, Path::new("/r"))
+            .unwrap();
+        assert_eq!(out, b"/r/with space\0/r/line\nbreak\0");
+    }
+
+    #[test]
+    fn test_write_html() {
+        let mut result = ScanResult::new();
+        result.total_size = 400;
+        result.file_count = 1;
+        result.interrupted = true;
+        result.nodes = vec![Node::new(PathBuf::from("/r/<b>&co"), 400, false, 1)];
+        let mut out = Vec::new();
+        Display::new()
+            .with_format(OutputFormat::Html)
+            .with_units(Units::Si)
+            .print_results(&mut out, &result, Path::new("/r"))
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("<!DOCTYPE html>\n"));
+        assert!(out.contains("<title>Disk usage: /r</title>"));
+        assert!(out.contains("<p>Total size: <strong>400 B</strong> in 1 files"));
+        assert!(out.contains("PARTIAL (interrupted)"));
+        assert!(out.contains("<th class=\"num\">Size</th>"));
+        // Sizes are human-readable with the raw bytes kept for sorting; names are escaped
+        assert!(out.contains(
+            "<tr><td class=\"num\" data-value=\"400\">400 B</td><td class=\"num\" data-value=\"100\">100.0%</td><td>file</td><td><code>&lt;b&gt;&amp;co</code></td></tr>"
+        ));
+        assert!(out.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(html_escape("plain"), "plain");
+        assert_eq!(
+            html_escape(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_markdown_code() {
+        assert_eq!(markdown_code("src/main.rs"), "`src/main.rs`");
+        assert_eq!(markdown_code("a|b"), "`a\\|b`");
+        assert_eq!(markdown_code("odd`name"), "`` odd`name ``");
+    }
+
+    #[test]
+    fn test_write_ncdu() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/r/a"), 8192, true, 1),
+            Node::new(PathBuf::from("/r/a/x.bin"), 8192, false, 2).with_apparent_size(5000),
+            Node::new(PathBuf::from("/r/locked"), 0, true, 1).with_accessible(false),
+            Node::new(PathBuf::from("/r/top.txt"), 4096, false, 1).with_apparent_size(10),
+        ];
+        let mut out = Vec::new();
+        write_ncdu(&mut out, &result, Path::new("/r")).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(json[0], 1);
+        assert_eq!(json[1], 2);
+        assert_eq!(json[2]["progname"], "disk-scanner");
+        let root = &json[3];
+        assert_eq!(root[0]["name"], "/r");
+        assert_eq!(root.as_array().unwrap().len(), 4);
+        assert_eq!(
+            root[1],
+            serde_json::json!([
+                {"name": "a", "asize": 0, "dsize": 0},
+                {"name": "x.bin", "asize": 5000, "dsize": 8192}
+            ])
+        );
+        assert_eq!(
+            root[2],
+            serde_json::json!([{"name": "locked", "asize": 0, "dsize": 0, "read_error": true}])
+        );
+        assert_eq!(
+            root[3],
+            serde_json::json!({"name": "top.txt", "asize": 10, "dsize": 4096})
+        );
+    }
+
+    #[test]
+    fn test_paint_without_color() {
+        let display = Display::new().with_color(false);
+        assert_eq!(display.paint("text", Style::new().green().bold()), "text");
+
+        let colored = Display::new().with_color(true);
+        assert!(
+            colored
+                .paint("text", Style::new().green())
+                .contains("\x1b[")
+        );
+    }
 }
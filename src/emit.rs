@@ -0,0 +1,185 @@
+//! Sending the JSON report to a Unix socket or TCP endpoint (`--emit`, `emit` feature).
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io::Write;
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where the report is sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Path of a Unix domain socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// `host:port` of a TCP listener
+    Tcp(String),
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+            Endpoint::Tcp(addr) => write!(f, "tcp:{}", addr),
+        }
+    }
+}
+
+/// Parse an endpoint such as `unix:/run/collector.sock` or `tcp:localhost:9000` for `--emit`
+pub fn parse_endpoint(s: &str) -> Result<Endpoint, String> {
+    if let Some(path) = s.strip_prefix("unix:") {
+        if path.is_empty() {
+            return Err(format!("invalid endpoint '{}': missing socket path", s));
+        }
+        #[cfg(unix)]
+        return Ok(Endpoint::Unix(PathBuf::from(path)));
+        #[cfg(not(unix))]
+        return Err(format!(
+            "invalid endpoint '{}': Unix sockets are only supported on Unix",
+            s
+        ));
+    }
+    if let Some(addr) = s.strip_prefix("tcp:") {
+        let valid = addr
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if !valid {
+            return Err(format!("invalid endpoint '{}': expected tcp:HOST:PORT", s));
+        }
+        return Ok(Endpoint::Tcp(addr.to_string()));
+    }
+    Err(format!(
+        "invalid endpoint '{}': expected unix:PATH or tcp:HOST:PORT",
+        s
+    ))
+}
+
+/// Connect to `endpoint`, write `report` and close the connection.
+///
+/// `timeout` bounds connecting to each resolved TCP address and every write; connecting to
+/// a Unix socket doesn't block, so there it only bounds the writes.
+pub fn send(endpoint: &Endpoint, report: &[u8], timeout: Duration) -> Result<()> {
+    match endpoint {
+        #[cfg(unix)]
+        Endpoint::Unix(path) => {
+            let mut stream = UnixStream::connect(path)
+                .with_context(|| format!("Failed to connect to {}", endpoint))?;
+            stream.set_write_timeout(Some(timeout))?;
+            stream
+                .write_all(report)
+                .and_then(|()| stream.shutdown(Shutdown::Write))
+        }
+        Endpoint::Tcp(addr) => {
+            let mut stream = connect_tcp(addr, timeout)
+                .with_context(|| format!("Failed to connect to {}", endpoint))?;
+            stream.set_write_timeout(Some(timeout))?;
+            stream
+                .write_all(report)
+                .and_then(|()| stream.shutdown(Shutdown::Write))
+        }
+    }
+    .with_context(|| format!("Failed to send the report to {}", endpoint))
+}
+
+/// Try each address `addr` resolves to in turn, returning the last error if none accepts
+fn connect_tcp(addr: &str, timeout: Duration) -> std::io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "no addresses resolved")
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            parse_endpoint("tcp:localhost:9000"),
+            Ok(Endpoint::Tcp("localhost:9000".to_string()))
+        );
+        assert_eq!(
+            parse_endpoint("tcp:[::1]:9000"),
+            Ok(Endpoint::Tcp("[::1]:9000".to_string()))
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            parse_endpoint("unix:/run/collector.sock"),
+            Ok(Endpoint::Unix(PathBuf::from("/run/collector.sock")))
+        );
+        assert!(parse_endpoint("tcp:localhost").is_err());
+        assert!(parse_endpoint("tcp::9000").is_err());
+        assert!(parse_endpoint("unix:").is_err());
+        assert!(parse_endpoint("http://localhost").is_err());
+    }
+
+    #[test]
+    fn test_send_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = Endpoint::Tcp(listener.local_addr().unwrap().to_string());
+        let reader = std::thread::spawn(move || {
+            let mut received = String::new();
+            listener
+                .accept()
+                .unwrap()
+                .0
+                .read_to_string(&mut received)
+                .unwrap();
+            received
+        });
+
+        send(&endpoint, b"{\"schema\":1}", Duration::from_secs(5)).unwrap();
+        assert_eq!(reader.join().unwrap(), "{\"schema\":1}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_unix() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("collector.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            listener
+                .accept()
+                .unwrap()
+                .0
+                .read_to_end(&mut received)
+                .unwrap();
+            received
+        });
+
+        send(&Endpoint::Unix(path), b"report", Duration::from_secs(5)).unwrap();
+        assert_eq!(reader.join().unwrap(), b"report");
+    }
+
+    #[test]
+    fn test_send_refused() {
+        // Nothing listens on a port that was just released
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let err = send(
+            &Endpoint::Tcp(addr.to_string()),
+            b"report",
+            Duration::from_secs(5),
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains(&format!("Failed to connect to tcp:{}", addr)));
+    }
+}
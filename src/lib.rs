@@ -0,0 +1,24 @@
+//! disk-scanner library: parallel directory scanning and disk usage aggregation.
+//!
+//! ```no_run
+//! use disk_scanner::Scanner;
+//!
+//! let mut result = Scanner::new().scan(std::path::Path::new("."))?;
+//! result.sort_by_size_desc();
+//! for node in result.top_n(10) {
+//!     println!("{} {}", node.size, node.path.display());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+#[cfg(feature = "archives")]
+pub mod archive;
+pub mod cache;
+pub mod node;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+pub mod scanner;
+
+pub use cache::ScanCache;
+pub use node::{Node, ScanResult};
+pub use scanner::{ProgressReporter, Scanner};
@@ -1,57 +1,618 @@
-﻿//! disk-scanner: A fast, cross-platform CLI tool for analyzing disk usage.
+//! disk-scanner: A fast, cross-platform CLI tool for analyzing disk usage.
 //!
 //! This tool scans directories and identifies the largest files and folders,
 //! displaying them sorted by size in descending order.
 
 mod cli;
+mod config;
+mod delete;
 mod display;
-mod node;
-mod scanner;
+#[cfg(feature = "emit")]
+mod emit;
+mod progress;
+mod tui;
+mod units;
 
 use anyhow::{Context, Result};
-use cli::Args;
+use cli::{Args, OutputFormat, SortKey};
+use delete::Deleter;
+use disk_scanner::node::{Profile, common_ancestor};
+use disk_scanner::{ScanCache, ScanResult, Scanner};
 use display::Display;
-use scanner::Scanner;
+use progress::SpinnerProgress;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use tui::Browser;
+
+/// Exit status after Ctrl-C, as for a shell command killed by SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
 
 fn main() -> Result<()> {
-    let args = Args::parse_args();
+    let args = Args::parse_args()?;
+    let use_color = args.use_color();
 
-    // Validate the path exists
-    let path = args.path.canonicalize().with_context(|| {
-        format!(
-            "Cannot access path '{}': No such file or directory",
-            args.path.display()
-        )
-    })?;
+    if args.interactive && !std::io::stdout().is_terminal() {
+        anyhow::bail!("--interactive requires stdout to be a terminal");
+    }
+
+    if args.stream && args.output.is_none() && !matches!(args.format, OutputFormat::Table) {
+        anyhow::bail!(
+            "--stream with --format json/jsonl/csv/markdown/html requires --output to keep stdout parseable"
+        );
+    }
+
+    #[cfg(feature = "protobuf")]
+    if args.format == OutputFormat::Bin && args.output.is_none() && std::io::stdout().is_terminal()
+    {
+        anyhow::bail!("--format bin writes binary data; redirect stdout or use --output");
+    }
+
+    // Dry run: report whether each path is scannable without scanning it
+    if args.check {
+        let paths: Vec<PathBuf> = if args.stdin {
+            std::io::stdin()
+                .lock()
+                .lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| PathBuf::from(line.trim()))
+                .collect()
+        } else {
+            args.paths.clone()
+        };
+        std::process::exit(if check_paths(&paths) { 0 } else { 1 });
+    }
+
+    // Validate every path before scanning any of them
+    let paths = if args.stdin {
+        read_paths(std::io::stdin().lock())?
+    } else {
+        args.paths
+            .iter()
+            .map(|path| validate_path(path))
+            .collect::<Result<Vec<_>>>()?
+    };
 
-    if !path.is_dir() {
-        anyhow::bail!("'{}' is not a directory", path.display());
+    if args.format == OutputFormat::Ncdu && paths.len() > 1 {
+        anyhow::bail!("--format ncdu exports a single directory tree; scan one path at a time");
     }
 
+    // Load the baseline before scanning so a bad file fails fast
+    let baseline = args
+        .compare
+        .as_deref()
+        .map(|path| display::load_baseline(path, args.all || args.top_files))
+        .transpose()?;
+
+    let relative_to = args.relative_to.as_deref().map(validate_path).transpose()?;
+
+    // Open the output early so a bad path fails before a long scan
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path).with_context(|| {
+            format!("Failed to create output file '{}'", path.display())
+        })?)),
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    };
+
     // Configure and run the scanner
-    let scanner = Scanner::new()
+    let mut scanner = Scanner::new()
         .with_threads(args.threads)
-        .include_files(args.all);
+        // ncdu sums directory sizes from their files, so the export needs every file
+        .include_files(args.all || args.top_files || args.format == OutputFormat::Ncdu)
+        .include_files_matching(args.show_files_matching.clone())
+        .include_root(args.include_root)
+        .disk_info(!args.no_disk_info)
+        .one_file_system(args.one_file_system)
+        .scan_special(args.scan_special)
+        .detect_mount_crossings(args.same_device_warning)
+        .count_links_once(args.count_links_once)
+        .max_scan_depth(args.max_scan_depth)
+        .apparent_size(args.apparent_size)
+        .block_size(args.block_size)
+        .follow_symlinks(args.follow_symlinks)
+        .with_excludes(args.exclude.clone())
+        .with_include_regexes(args.include_regex.clone())
+        .with_exclude_regexes(args.exclude_regex.clone())
+        .with_extensions(args.extensions.clone())
+        .skip_hidden(args.no_hidden)
+        .respect_gitignore(args.respect_gitignore)
+        .summarize_types(args.by_type)
+        .size_histogram(args.histogram.then(|| args.histogram_buckets.0.clone()))
+        .find_duplicates(args.duplicates)
+        .max_results(args.max_results)
+        .collect_stats(args.stats)
+        .profile(args.profile);
+    #[cfg(feature = "archives")]
+    {
+        scanner = scanner.into_archives(args.into_archives);
+    }
+    let progress_interval = args.progress_interval.map(Duration::from_millis);
+    scanner = scanner.progress_interval(progress_interval);
+    let show_progress = args.show_progress();
+    let spinner = (show_progress || args.stream).then(|| {
+        Arc::new(
+            SpinnerProgress::new()
+                .with_units(args.size_units())
+                .with_number_format(args.number_format())
+                .with_hidden(!show_progress)
+                .with_forced(args.force_progress)
+                .with_interval(progress_interval),
+        )
+    });
+    if let Some(spinner) = &spinner {
+        scanner = scanner.with_progress(spinner.clone());
+    }
+    if args.stream {
+        scanner = scanner.stream_files(Some(args.min_size.unwrap_or(0)));
+    }
+
+    // Ctrl-C during the scan stops it and reports what was found; a second one quits
+    let cancel = Arc::new(AtomicBool::new(false));
+    let scanning = Arc::new(AtomicBool::new(true));
+    {
+        let (cancel, scanning) = (cancel.clone(), scanning.clone());
+        ctrlc::set_handler(move || {
+            if !scanning.load(Ordering::Relaxed) || cancel.swap(true, Ordering::Relaxed) {
+                std::process::exit(EXIT_INTERRUPTED);
+            }
+        })
+        .context("Failed to install the Ctrl-C handler")?;
+    }
+    scanner = scanner.cancel_on(cancel.clone());
+
+    // Reuse a previous scan's directory listings when a cache is given
+    let previous = args.cache.as_deref().and_then(load_cache);
+    let mut next_cache = args.cache.as_ref().map(|_| ScanCache::default());
 
-    let mut result = scanner
-        .scan(&path)
+    let mut reports = Vec::with_capacity(paths.len());
+    let mut interrupted = false;
+    let path_count = paths.len();
+    for (index, path) in paths.into_iter().enumerate() {
+        // A first pass over the tree gives the progress bar its total
+        if let (true, true, Some(spinner)) = (args.progress_bar, show_progress, &spinner) {
+            let total = scanner
+                .count_entries(&path)
+                .with_context(|| format!("Failed to scan '{}'", path.display()))?;
+            spinner.set_total(total);
+        }
+
+        let result = match next_cache.as_mut() {
+            Some(cache) => {
+                scanner
+                    .scan_incremental(&path, previous.as_ref())
+                    .map(|(result, scanned)| {
+                        cache.merge(scanned);
+                        result
+                    })
+            }
+            None => scanner.scan(&path),
+        }
         .with_context(|| format!("Failed to scan '{}'", path.display()))?;
+        interrupted = result.interrupted;
+        reports.push((path, result));
+
+        // Paths after an interrupted one are not scanned at all
+        if cancel.load(Ordering::Relaxed) {
+            interrupted |= index + 1 < path_count;
+            break;
+        }
+    }
+    scanning.store(false, Ordering::Relaxed);
+
+    // A partial scan would leave the cache missing most directories
+    if let (Some(path), Some(cache), false) = (&args.cache, &next_cache, interrupted) {
+        cache.save(path)?;
+    }
+
+    // With --emit the JSON report is collected here and sent once complete
+    #[cfg(feature = "emit")]
+    let emitting = args.emit.is_some();
+    #[cfg(not(feature = "emit"))]
+    let emitting = false;
+    let mut emitted: Vec<u8> = Vec::new();
+
+    // Display results
+    let display = Display::new()
+        .with_count(args.count)
+        .with_format(if emitting {
+            OutputFormat::Json
+        } else {
+            args.format
+        })
+        .with_print0(args.print0)
+        .with_tree(args.tree)
+        .with_collapse_under(args.collapse_under)
+        .with_by_type(args.by_type)
+        .with_histogram(args.histogram)
+        .with_by_depth(args.by_depth)
+        .with_duplicates(args.duplicates)
+        .with_compare(args.compare.is_some())
+        .with_show_errors(args.show_errors)
+        .with_summary_only(args.summary_only)
+        .with_empty_dirs(args.empty_dirs)
+        .with_show_avg(args.show_avg)
+        .with_show_mtime(args.show_mtime)
+        .with_self_size(args.self_size || args.sort == SortKey::SelfSize)
+        .with_heat(args.heat.then_some(args.heat_thresholds))
+        .with_units(args.size_units())
+        .with_number_format(args.number_format())
+        .with_truncate(args.truncate)
+        .with_relative_to(relative_to)
+        .with_absolute_paths(args.absolute_paths)
+        .with_width(args.report_width())
+        .with_sort(args.sort)
+        .with_ascending(args.reverse)
+        .with_color(use_color)
+        .with_icons(args.icons && use_color);
+
+    let sort_start = Instant::now();
+
+    // The interactive browser needs a single tree, so it always merges
+    if (args.merge || args.interactive) && reports.len() > 1 {
+        let roots: Vec<_> = reports.iter().map(|(root, _)| root.clone()).collect();
+        let mut merged = ScanResult::new();
+        for (_, result) in reports {
+            merged.merge(result);
+        }
+        reports = vec![(common_ancestor(&roots), merged)];
+    }
+
+    if let Some(baseline) = &baseline {
+        for (root, result) in reports.iter_mut() {
+            let under_root = baseline
+                .iter()
+                .filter(|(path, _)| path.starts_with(&*root))
+                .map(|(path, entry)| (path.clone(), *entry))
+                .collect();
+            result.compare_with(&under_root);
+        }
+    }
+
+    for (_, result) in reports.iter_mut() {
+        prepare_result(result, &args);
+    }
+    let sort_duration = sort_start.elapsed();
+
+    let display_start = Instant::now();
+    let out: &mut dyn Write = if emitting { &mut emitted } else { &mut out };
+    if let [(path, result)] = reports.as_slice() {
+        if args.interactive {
+            Browser::new(result, path)
+                .with_color(use_color)
+                .with_units(args.size_units())
+                .with_number_format(args.number_format())
+                .run()?;
+        } else {
+            display.print_results(out, result, path)?;
+        }
+    } else {
+        display.print_all(out, &reports)?;
+    }
+    out.flush().context("Failed to write results")?;
+    let display_duration = display_start.elapsed();
+
+    #[cfg(feature = "emit")]
+    if let Some(endpoint) = &args.emit {
+        emit::send(endpoint, &emitted, Duration::from_secs(args.emit_timeout))?;
+    }
+
+    // Diagnostics go to stderr so they never mix with the report
+    if args.stats {
+        let mut stderr = std::io::stderr().lock();
+        for (root, result) in &reports {
+            if let Some(stats) = &result.stats {
+                writeln!(stderr)?;
+                display.print_stats(&mut stderr, stats, root)?;
+            }
+        }
+    }
 
-    // Apply filters
-    if !args.all {
+    if args.profile {
+        let mut profile = Profile::default();
+        for (_, result) in &reports {
+            if let Some(scan) = &result.profile {
+                profile.merge(scan);
+            }
+        }
+        profile.add("filter and sort", sort_duration);
+        profile.add("display", display_duration);
+        let mut stderr = std::io::stderr().lock();
+        writeln!(stderr)?;
+        display.print_profile(&mut stderr, &profile)?;
+    }
+
+    if args.interactive_delete {
+        let mut input = std::io::stdin().lock();
+        let mut stdout = std::io::stdout().lock();
+        for (root, result) in &reports {
+            Deleter::new(root)
+                .with_units(args.size_units())
+                .with_number_format(args.number_format())
+                .assume_yes(args.yes)
+                .run(result.top_n(args.count), &mut input, &mut stdout)?;
+        }
+    }
+
+    if interrupted {
+        eprintln!("error: scan interrupted; the report is partial");
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+
+    // Exit status 2 tells scripts the scan finished but skipped inaccessible entries
+    let error_count: u64 = reports.iter().map(|(_, result)| result.error_count).sum();
+    if args.fail_on_error && error_count > 0 {
+        eprintln!("error: {} entries could not be read", error_count);
+        std::process::exit(2);
+    }
+
+    Ok(())
+}
+
+/// Load a scan cache, falling back to a full scan if it is missing or unusable
+fn load_cache(path: &Path) -> Option<ScanCache> {
+    if !path.exists() {
+        return None;
+    }
+
+    match ScanCache::load(path) {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            eprintln!("warning: ignoring cache, performing a full scan: {:#}", err);
+            None
+        }
+    }
+}
+
+/// Resolve a path argument, ensuring it exists and is a directory
+fn validate_path(path: &Path) -> Result<PathBuf> {
+    let expanded = expand_path(path)?;
+    let canonical = expanded.canonicalize().with_context(|| {
+        if expanded == path {
+            format!(
+                "Cannot access path '{}': No such file or directory",
+                path.display()
+            )
+        } else {
+            format!(
+                "Cannot access path '{}' (expanded from '{}'): No such file or directory",
+                expanded.display(),
+                path.display()
+            )
+        }
+    })?;
+
+    if !canonical.is_dir() {
+        anyhow::bail!("'{}' is not a directory", canonical.display());
+    }
+
+    Ok(canonical)
+}
+
+/// Expand a leading `~` or `~user` and `$VAR`/`${VAR}` references (and `%VAR%` on Windows),
+/// for paths that didn't go through a shell
+fn expand_path(path: &Path) -> Result<PathBuf> {
+    // Non-UTF-8 paths are used as given
+    let Some(text) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+    let expanded = expand_user_home(text).and_then(|text| {
+        #[cfg(windows)]
+        let text: Cow<'_, str> = expand_percent_vars(&text).into();
+        shellexpand::full(text.as_ref())
+            .map(|expanded| PathBuf::from(expanded.as_ref()))
+            .map_err(|err| err.to_string())
+    });
+    match expanded {
+        Ok(expanded) => Ok(expanded),
+        // An existing path with a literal `$` or `~` in it is not an error
+        Err(_) if path.exists() => Ok(path.to_path_buf()),
+        Err(err) => anyhow::bail!("Cannot expand path '{}': {}", path.display(), err),
+    }
+}
+
+/// Replace a leading `~user` with that user's home directory from the user database
+/// (a plain `~` is left to shellexpand)
+#[cfg(unix)]
+fn expand_user_home(text: &str) -> Result<Cow<'_, str>, String> {
+    let Some(rest) = text.strip_prefix('~') else {
+        return Ok(Cow::Borrowed(text));
+    };
+    let (user, tail) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if user.is_empty() {
+        return Ok(Cow::Borrowed(text));
+    }
+    match nix::unistd::User::from_name(user) {
+        Ok(Some(entry)) => Ok(Cow::Owned(format!("{}{}", entry.dir.display(), tail))),
+        Ok(None) => Err(format!("unknown user '{}'", user)),
+        Err(err) => Err(format!("cannot look up user '{}': {}", user, err)),
+    }
+}
+
+/// Replace a leading `~user` with that user's home directory (only supported on Unix)
+#[cfg(not(unix))]
+fn expand_user_home(text: &str) -> Result<Cow<'_, str>, String> {
+    Ok(Cow::Borrowed(text))
+}
+
+/// Replace `%VAR%` references to set environment variables, leaving others as typed like cmd
+#[cfg(windows)]
+fn expand_percent_vars(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => {
+                expanded.push_str(&rest[..start]);
+                expanded.push_str(&value);
+                rest = &rest[start + len + 2..];
+            }
+            _ => {
+                expanded.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Print whether each path can be scanned, returning false if any cannot
+fn check_paths(paths: &[PathBuf]) -> bool {
+    let mut all_ok = !paths.is_empty();
+    for path in paths {
+        match check_path(path) {
+            Ok(details) => println!("ok: {}", details),
+            Err(err) => {
+                eprintln!("error: {:#}", err);
+                all_ok = false;
+            }
+        }
+    }
+    all_ok
+}
+
+/// Validate a path like a scan would and describe it, also making sure it can be listed
+fn check_path(path: &Path) -> Result<String> {
+    let canonical = validate_path(path)?;
+    std::fs::read_dir(&canonical)
+        .with_context(|| format!("Cannot read directory '{}'", canonical.display()))?;
+    let metadata = std::fs::metadata(&canonical)
+        .with_context(|| format!("Cannot read metadata of '{}'", canonical.display()))?;
+    Ok(format!(
+        "{} ({})",
+        canonical.display(),
+        describe_permissions(&metadata)
+    ))
+}
+
+/// Device and permission bits of a directory
+#[cfg(unix)]
+fn describe_permissions(metadata: &std::fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    format!(
+        "device {}, mode {:o}, owner {}:{}",
+        metadata.dev(),
+        metadata.mode() & 0o7777,
+        metadata.uid(),
+        metadata.gid()
+    )
+}
+
+/// Whether a directory is read-only (device ids aren't available here)
+#[cfg(not(unix))]
+fn describe_permissions(metadata: &std::fs::Metadata) -> String {
+    if metadata.permissions().readonly() {
+        "read-only".to_string()
+    } else {
+        "writable".to_string()
+    }
+}
+
+/// Read newline-separated directories, reporting invalid lines instead of aborting
+fn read_paths(reader: impl BufRead) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.context("Failed to read paths from stdin")?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        match validate_path(Path::new(trimmed)) {
+            Ok(path) => paths.push(path),
+            Err(err) => eprintln!("warning: skipping stdin line {}: {:#}", idx + 1, err),
+        }
+    }
+
+    if paths.is_empty() {
+        anyhow::bail!("No valid directories were read from stdin");
+    }
+    Ok(paths)
+}
+
+/// Apply the requested filters and sort order to a scan result
+fn prepare_result(result: &mut ScanResult, args: &Args) {
+    // The ncdu export is the complete tree, so display filters don't apply
+    if args.format == OutputFormat::Ncdu {
+        return;
+    }
+
+    // Only the totals are shown, so there is nothing to filter or sort
+    if args.summary_only {
+        result.nodes.clear();
+        return;
+    }
+
+    // Empty directories are listed in full, by path
+    if args.empty_dirs {
+        result.filter_empty_dirs();
+        return;
+    }
+
+    // Files matching --show-files-matching are the only ones the scanner listed
+    if args.top_files {
+        result.filter_files_only();
+    } else if !args.all && args.show_files_matching.is_empty() {
         result.filter_dirs_only();
     }
 
+    if args.exclude_hidden_files {
+        result.filter_hidden_files();
+    }
+
     if let Some(depth) = args.depth {
         result.filter_by_depth(depth);
     }
 
-    // Sort by size descending
-    result.sort_by_size_desc();
+    if let Some(min_depth) = args.min_depth {
+        result.filter_by_min_depth(min_depth);
+    }
 
-    // Display results
-    let display = Display::new().with_count(args.count);
-    display.print_results(&result, &path);
+    if let Some(min_size) = args.min_size {
+        result.filter_by_min_size(min_size);
+    }
 
-    Ok(())
+    if let Some(min_files) = args.min_files {
+        result.filter_by_file_count(min_files);
+    }
+
+    if args.exclude_empty {
+        result.filter_nonzero();
+    }
+
+    // Age filters compare against the time the results are shown; ages reaching back
+    // past the epoch, or past what SystemTime can hold, cut off at the epoch
+    let now = SystemTime::now();
+    let cutoff = |age| {
+        now.checked_sub(age).map_or(SystemTime::UNIX_EPOCH, |time| {
+            time.max(SystemTime::UNIX_EPOCH)
+        })
+    };
+    let modified_before = args.older_than.map(cutoff);
+    let modified_after = args.newer_than.map(cutoff);
+    result.filter_by_age(modified_before, modified_after);
+
+    // Sort by the requested key: largest, A-Z or shallowest first unless reversed
+    match (args.sort, args.reverse) {
+        (SortKey::Size, false) => result.sort_by_size_desc(),
+        (SortKey::Size, true) => result.sort_by_size_asc(),
+        (SortKey::Count, false) => result.sort_by_count_desc(),
+        (SortKey::Count, true) => result.sort_by_count_asc(),
+        (SortKey::SelfSize, false) => result.sort_by_self_size_desc(),
+        (SortKey::SelfSize, true) => result.sort_by_self_size_asc(),
+        (SortKey::Name, _) => result.sort_by_name(),
+        (SortKey::Depth, _) => result.sort_by_depth(),
+    }
+    if args.reverse && matches!(args.sort, SortKey::Name | SortKey::Depth) {
+        result.nodes.reverse();
+    }
 }
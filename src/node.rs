@@ -1,34 +1,275 @@
 //! Data structures representing file system entries with their sizes.
 
-use std::path::PathBuf;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 /// Represents a file system entry (file or directory) with its size.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Node {
     /// Absolute path to the entry
     pub path: PathBuf,
-    /// Size in bytes
+    /// Size in bytes (size on disk unless apparent sizes were requested)
     pub size: u64,
+    /// Size of the files directly inside a directory, leaving out subdirectories
+    /// (for files, the file's own size)
+    pub self_size: u64,
+    /// Apparent (logical) size in bytes
+    pub apparent_size: u64,
+    /// Number of files contained (recursively for directories, 1 for files)
+    pub file_count: u64,
     /// Whether this is a directory
     pub is_dir: bool,
     /// Depth relative to the scan root
     pub depth: usize,
+    /// Whether the directory's contents could be listed (false means its size is incomplete)
+    pub accessible: bool,
+    /// Last modification time (for directories, that of the newest file inside)
+    #[serde(skip)]
+    pub modified: Option<SystemTime>,
+    /// Number of entries directly inside a directory, including ones the scan filtered
+    /// out (None for files and for directories that weren't listed)
+    #[serde(skip)]
+    pub entry_count: Option<u64>,
 }
 
 impl Node {
-    /// Create a new Node
+    /// Create a new Node whose apparent size equals `size`; a directory's self size starts at 0
     pub fn new(path: PathBuf, size: u64, is_dir: bool, depth: usize) -> Self {
         Self {
             path,
             size,
+            self_size: if is_dir { 0 } else { size },
+            apparent_size: size,
+            file_count: if is_dir { 0 } else { 1 },
             is_dir,
             depth,
+            accessible: true,
+            modified: None,
+            entry_count: None,
         }
     }
+
+    /// Set the apparent size
+    pub fn with_apparent_size(mut self, apparent_size: u64) -> Self {
+        self.apparent_size = apparent_size;
+        self
+    }
+
+    /// Set the size of the files directly inside the directory
+    pub fn with_self_size(mut self, self_size: u64) -> Self {
+        self.self_size = self_size;
+        self
+    }
+
+    /// Set the number of contained files
+    pub fn with_file_count(mut self, file_count: u64) -> Self {
+        self.file_count = file_count;
+        self
+    }
+
+    /// Mark whether the directory's contents could be listed
+    pub fn with_accessible(mut self, accessible: bool) -> Self {
+        self.accessible = accessible;
+        self
+    }
+
+    /// Average size of the contained files (None for directories without files)
+    pub fn average_file_size(&self) -> Option<u64> {
+        self.size.checked_div(self.file_count)
+    }
+
+    /// Set the modification time
+    pub fn with_modified(mut self, modified: Option<SystemTime>) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    /// Set the number of entries listed directly inside the directory
+    pub fn with_entry_count(mut self, entry_count: Option<u64>) -> Self {
+        self.entry_count = entry_count;
+        self
+    }
+}
+
+/// Aggregate size and file count for one file extension
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TypeSummary {
+    /// Lowercase extension without the leading dot (None for extensionless files)
+    pub extension: Option<String>,
+    /// Total size in bytes of matching files
+    pub size: u64,
+    /// Number of matching files
+    pub file_count: u64,
+}
+
+/// Files whose sizes fall in one range of a size histogram
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizeBucket {
+    /// Smallest size in the bucket, in bytes
+    pub min: u64,
+    /// Size the bucket ends before, in bytes (None for the last, open-ended bucket)
+    pub max: Option<u64>,
+    /// Number of files in the bucket
+    pub file_count: u64,
+    /// Total size in bytes of the files in the bucket
+    pub size: u64,
+}
+
+/// Directories at one depth below the root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DepthSummary {
+    /// Depth relative to the root (1 for its direct subdirectories)
+    pub depth: usize,
+    /// Number of directories at this depth
+    pub dir_count: u64,
+    /// Total size in bytes of those directories
+    pub size: u64,
+}
+
+/// A set of files with identical content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateGroup {
+    /// Size in bytes of each copy
+    pub size: u64,
+    /// Paths of all copies, sorted
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Space that could be reclaimed by keeping a single copy
+    pub fn wasted_size(&self) -> u64 {
+        self.size * (self.paths.len() as u64).saturating_sub(1)
+    }
+}
+
+/// How the work of a scan was spread over threads and phases
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ScanStats {
+    /// Threads in the walk's pool
+    pub threads: usize,
+    /// Directory entries read by each pool thread
+    pub entries_per_thread: Vec<u64>,
+    /// Times a thread had to wait for the shared visited-directories lock, the only lock
+    /// in the walk (None unless symlinks were followed, since it isn't taken otherwise)
+    pub visited_lock_waits: Option<u64>,
+    /// Time spent walking the tree, in milliseconds
+    pub walk_ms: u64,
+    /// Time spent building results after the walk, in milliseconds
+    pub aggregate_ms: u64,
+}
+
+impl ScanStats {
+    /// Add the statistics of another scan, thread by thread
+    pub fn merge(&mut self, other: &ScanStats) {
+        self.threads = self.threads.max(other.threads);
+        if self.entries_per_thread.len() < other.entries_per_thread.len() {
+            self.entries_per_thread
+                .resize(other.entries_per_thread.len(), 0);
+        }
+        for (total, entries) in self
+            .entries_per_thread
+            .iter_mut()
+            .zip(&other.entries_per_thread)
+        {
+            *total += entries;
+        }
+        if let Some(waits) = other.visited_lock_waits {
+            *self.visited_lock_waits.get_or_insert(0) += waits;
+        }
+        self.walk_ms += other.walk_ms;
+        self.aggregate_ms += other.aggregate_ms;
+    }
+}
+
+/// Time spent in one phase of a scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PhaseTiming {
+    /// Phase name, such as `walk` or `aggregate`
+    pub phase: &'static str,
+    /// Time spent in the phase
+    pub duration: Duration,
+}
+
+/// Time spent in each phase of a scan, in the order the phases ran
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Profile {
+    /// Phases in the order they first ran
+    pub phases: Vec<PhaseTiming>,
+}
+
+impl Profile {
+    /// Add time to a phase, appending it if it hasn't run before
+    pub fn add(&mut self, phase: &'static str, duration: Duration) {
+        match self.phases.iter_mut().find(|timing| timing.phase == phase) {
+            Some(timing) => timing.duration += duration,
+            None => self.phases.push(PhaseTiming { phase, duration }),
+        }
+    }
+
+    /// Add the phase timings of another scan
+    pub fn merge(&mut self, other: &Profile) {
+        for timing in &other.phases {
+            self.add(timing.phase, timing.duration);
+        }
+    }
+
+    /// Time spent in all phases
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|timing| timing.duration).sum()
+    }
+}
+
+/// Capacity of the filesystem holding the scan root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DiskInfo {
+    /// Total capacity in bytes
+    pub total: u64,
+    /// Free bytes, including space reserved for the superuser
+    pub free: u64,
+    /// Bytes available to unprivileged users
+    pub available: u64,
+}
+
+impl DiskInfo {
+    /// Bytes in use
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+
+    /// Share of the total capacity taken by `size` bytes, in percent
+    pub fn percent_of_total(&self, size: u64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        size as f64 * 100.0 / self.total as f64
+    }
+}
+
+/// Size difference of one entry between a baseline and the current scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizeChange {
+    /// Absolute path to the entry
+    pub path: PathBuf,
+    /// Whether this is a directory
+    pub is_dir: bool,
+    /// Size in the baseline (None if the entry is new)
+    pub old_size: Option<u64>,
+    /// Size in the current scan (None if the entry is gone)
+    pub new_size: Option<u64>,
+}
+
+impl SizeChange {
+    /// Signed change in bytes
+    pub fn change(&self) -> i128 {
+        i128::from(self.new_size.unwrap_or(0)) - i128::from(self.old_size.unwrap_or(0))
+    }
 }
 
 /// Collection of nodes with aggregate statistics
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct ScanResult {
     /// All scanned entries
     pub nodes: Vec<Node>,
@@ -38,8 +279,39 @@ pub struct ScanResult {
     pub file_count: u64,
     /// Total number of directories scanned
     pub dir_count: u64,
+    /// Biggest single file anywhere in the scan, with its size
+    pub largest_file: Option<(PathBuf, u64)>,
     /// Number of errors encountered
     pub error_count: u64,
+    /// Paths that could not be read and why, capped at the first
+    /// [`MAX_RECORDED_ERRORS`](crate::scanner::MAX_RECORDED_ERRORS) per scan
+    pub errors: Vec<(PathBuf, String)>,
+    /// Symlinked directories skipped because their target was already scanned
+    pub cycles_skipped: u64,
+    /// Wall-clock time spent scanning, in milliseconds
+    pub scan_duration_ms: u64,
+    /// Directories whose file sizes were reused from a cache
+    pub cached_dirs: u64,
+    /// Capacity of the scanned volume (only filled when requested and available)
+    pub disk: Option<DiskInfo>,
+    /// Thread and phase diagnostics (only filled when requested)
+    pub stats: Option<ScanStats>,
+    /// Time spent in each phase of the scan (only filled when requested)
+    pub profile: Option<Profile>,
+    /// Directories where the walk entered another filesystem, sorted (only filled when requested)
+    pub mount_crossings: Vec<PathBuf>,
+    /// Depth the walk stopped at, if limited (sizes then leave out deeper files)
+    pub max_scan_depth: Option<usize>,
+    /// Whether the scan was cancelled before the walk finished (totals are then incomplete)
+    pub interrupted: bool,
+    /// Per-extension totals, largest first (only filled when requested)
+    pub types: Vec<TypeSummary>,
+    /// File counts and sizes by size range, smallest range first (only filled when requested)
+    pub histogram: Vec<SizeBucket>,
+    /// Groups of identical files, most wasted space first (only filled when requested)
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Differences from a baseline scan, largest change first (only filled when requested)
+    pub changes: Vec<SizeChange>,
 }
 
 impl ScanResult {
@@ -48,31 +320,407 @@ impl ScanResult {
         Self::default()
     }
 
-    /// Sort nodes by size in descending order
+    /// Sort nodes by size in descending order (ties by path, so the order is deterministic)
     pub fn sort_by_size_desc(&mut self) {
-        self.nodes.sort_by(|a, b| b.size.cmp(&a.size));
+        self.nodes
+            .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    }
+
+    /// Sort nodes by size in ascending order (ties by path)
+    pub fn sort_by_size_asc(&mut self) {
+        self.nodes
+            .sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.path.cmp(&b.path)));
+    }
+
+    /// Sort nodes by self size in descending order (ties by path)
+    pub fn sort_by_self_size_desc(&mut self) {
+        self.nodes.sort_by(|a, b| {
+            b.self_size
+                .cmp(&a.self_size)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// Sort nodes by self size in ascending order (ties by path)
+    pub fn sort_by_self_size_asc(&mut self) {
+        self.nodes.sort_by(|a, b| {
+            a.self_size
+                .cmp(&b.self_size)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// Sort nodes by contained file count in descending order (ties by path)
+    pub fn sort_by_count_desc(&mut self) {
+        self.nodes.sort_by(|a, b| {
+            b.file_count
+                .cmp(&a.file_count)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// Sort nodes by contained file count in ascending order (ties by path)
+    pub fn sort_by_count_asc(&mut self) {
+        self.nodes.sort_by(|a, b| {
+            a.file_count
+                .cmp(&b.file_count)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// Sort nodes by path in natural order ([`natural_path_cmp`]), which within one scan is the
+    /// order of their paths relative to the root
+    pub fn sort_by_name(&mut self) {
+        self.nodes
+            .sort_by(|a, b| natural_path_cmp(&a.path, &b.path));
     }
 
-    /// Get the top N nodes by size
+    /// Sort nodes by depth, shallowest first and largest first within a level (then by path)
+    pub fn sort_by_depth(&mut self) {
+        self.nodes.sort_by(|a, b| {
+            a.depth
+                .cmp(&b.depth)
+                .then_with(|| b.size.cmp(&a.size))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+
+    /// Iterate over the nodes largest first (ties by path), like `sort_by_size_desc`, without
+    /// reordering `nodes`
+    pub fn iter_by_size(&self) -> impl Iterator<Item = &Node> {
+        let mut order: Vec<usize> = (0..self.nodes.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.nodes[a], &self.nodes[b]);
+            b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path))
+        });
+        order.into_iter().map(|idx| &self.nodes[idx])
+    }
+
+    /// Number and total size of the directory nodes at each depth, shallowest first
+    pub fn depth_summary(&self) -> Vec<DepthSummary> {
+        let mut levels: Vec<DepthSummary> = Vec::new();
+        for node in self.nodes.iter().filter(|node| node.is_dir) {
+            while levels.len() <= node.depth {
+                levels.push(DepthSummary {
+                    depth: levels.len(),
+                    dir_count: 0,
+                    size: 0,
+                });
+            }
+            levels[node.depth].dir_count += 1;
+            levels[node.depth].size += node.size;
+        }
+        levels.retain(|level| level.dir_count > 0);
+        levels
+    }
+
+    /// Get the first N nodes in sort order, or all of them when `n` is 0
     pub fn top_n(&self, n: usize) -> &[Node] {
+        if n == 0 {
+            return &self.nodes;
+        }
         let end = std::cmp::min(n, self.nodes.len());
         &self.nodes[..end]
     }
 
+    /// Scan throughput in files per second
+    pub fn files_per_second(&self) -> f64 {
+        if self.scan_duration_ms == 0 {
+            return 0.0;
+        }
+        self.file_count as f64 * 1000.0 / self.scan_duration_ms as f64
+    }
+
+    /// Combine another scan into this one, summing totals and concatenating entries.
+    ///
+    /// Duplicate detection is not re-run, so identical files across the two scans are not grouped.
+    pub fn merge(&mut self, other: ScanResult) {
+        self.nodes.extend(other.nodes);
+        self.total_size += other.total_size;
+        self.file_count += other.file_count;
+        self.dir_count += other.dir_count;
+        if other.largest_file.as_ref().map(|(_, size)| size)
+            > self.largest_file.as_ref().map(|(_, size)| size)
+        {
+            self.largest_file = other.largest_file;
+        }
+        self.error_count += other.error_count;
+        self.errors.extend(other.errors);
+        self.cycles_skipped += other.cycles_skipped;
+        self.scan_duration_ms += other.scan_duration_ms;
+        self.cached_dirs += other.cached_dirs;
+        self.max_scan_depth = self.max_scan_depth.or(other.max_scan_depth);
+        self.interrupted |= other.interrupted;
+        self.mount_crossings.extend(other.mount_crossings);
+        self.mount_crossings.sort();
+        match (&mut self.stats, &other.stats) {
+            (Some(stats), Some(other)) => stats.merge(other),
+            (None, Some(_)) => self.stats = other.stats,
+            _ => {}
+        }
+        match (&mut self.profile, &other.profile) {
+            (Some(profile), Some(other)) => profile.merge(other),
+            (None, Some(_)) => self.profile = other.profile,
+            _ => {}
+        }
+        // Capacity only makes sense if both scans were on the same volume
+        if self.disk != other.disk {
+            self.disk = None;
+        }
+
+        // Scans made with the same boundaries add up bucket by bucket
+        if self.histogram.is_empty() {
+            self.histogram = other.histogram;
+        } else {
+            for (bucket, other) in self.histogram.iter_mut().zip(other.histogram) {
+                bucket.file_count += other.file_count;
+                bucket.size += other.size;
+            }
+        }
+
+        if !other.types.is_empty() {
+            let mut by_type: HashMap<Option<String>, TypeSummary> = HashMap::new();
+            for summary in self.types.drain(..).chain(other.types) {
+                by_type
+                    .entry(summary.extension.clone())
+                    .and_modify(|t| {
+                        t.size += summary.size;
+                        t.file_count += summary.file_count;
+                    })
+                    .or_insert(summary);
+            }
+            self.types = by_type.into_values().collect();
+            self.types.sort_by_key(|t| std::cmp::Reverse(t.size));
+        }
+
+        self.duplicates.extend(other.duplicates);
+        self.duplicates
+            .sort_by_key(|g| std::cmp::Reverse(g.wasted_size()));
+
+        self.changes.extend(other.changes);
+        self.changes
+            .sort_by_key(|c| std::cmp::Reverse(c.change().abs()));
+    }
+
+    /// Fill `changes` with the entries whose size differs from `baseline` (path to
+    /// size and whether it is a directory), including new and removed entries
+    pub fn compare_with(&mut self, baseline: &HashMap<PathBuf, (u64, bool)>) {
+        let mut changes: Vec<SizeChange> = self
+            .nodes
+            .iter()
+            .map(|node| SizeChange {
+                path: node.path.clone(),
+                is_dir: node.is_dir,
+                old_size: baseline.get(&node.path).map(|&(size, _)| size),
+                new_size: Some(node.size),
+            })
+            .collect();
+
+        let current: HashSet<&Path> = self.nodes.iter().map(|n| n.path.as_path()).collect();
+        changes.extend(
+            baseline
+                .iter()
+                .filter(|(path, _)| !current.contains(path.as_path()))
+                .map(|(path, &(size, is_dir))| SizeChange {
+                    path: path.clone(),
+                    is_dir,
+                    old_size: Some(size),
+                    new_size: None,
+                }),
+        );
+
+        changes.retain(|c| c.old_size != c.new_size);
+        changes.sort_by_key(|c| std::cmp::Reverse(c.change().abs()));
+        self.changes = changes;
+    }
+
     /// Filter nodes by maximum depth
     pub fn filter_by_depth(&mut self, max_depth: usize) {
         self.nodes.retain(|node| node.depth <= max_depth);
     }
 
+    /// Filter nodes by minimum depth
+    pub fn filter_by_min_depth(&mut self, min_depth: usize) {
+        self.nodes.retain(|node| node.depth >= min_depth);
+    }
+
+    /// Filter nodes by modification time: keep entries last modified before
+    /// `modified_before` and after `modified_after`.
+    ///
+    /// Entries without a known modification time (e.g. empty directories) are dropped
+    /// when either bound is set.
+    pub fn filter_by_age(
+        &mut self,
+        modified_before: Option<SystemTime>,
+        modified_after: Option<SystemTime>,
+    ) {
+        if modified_before.is_none() && modified_after.is_none() {
+            return;
+        }
+        self.nodes.retain(|node| {
+            node.modified.is_some_and(|modified| {
+                modified_before.is_none_or(|before| modified <= before)
+                    && modified_after.is_none_or(|after| modified >= after)
+            })
+        });
+    }
+
+    /// Filter nodes to those at least `min` bytes large
+    pub fn filter_by_min_size(&mut self, min: u64) {
+        self.nodes.retain(|node| node.size >= min);
+    }
+
+    /// Keep only directories containing at least `min` files anywhere beneath them
+    pub fn filter_by_file_count(&mut self, min: u64) {
+        self.nodes
+            .retain(|node| node.is_dir && node.file_count >= min);
+    }
+
+    /// Drop zero-byte files and directories whose total is zero, including directories whose
+    /// files were all filtered out during the scan
+    pub fn filter_nonzero(&mut self) {
+        self.nodes.retain(|node| node.size > 0);
+    }
+
+    /// Drop files whose name starts with `.`; directories, hidden or not, and their sizes
+    /// are kept
+    pub fn filter_hidden_files(&mut self) {
+        self.nodes.retain(|node| {
+            node.is_dir
+                || !node
+                    .path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        });
+    }
+
     /// Filter to only include directories
     pub fn filter_dirs_only(&mut self) {
         self.nodes.retain(|node| node.is_dir);
     }
+
+    /// Keep only directories with no files anywhere beneath them, sorted by path.
+    ///
+    /// A directory counts as empty when every entry the scanner listed in it is an empty
+    /// directory, so ones whose files were filtered out, or that couldn't be listed, are
+    /// dropped.
+    pub fn filter_empty_dirs(&mut self) {
+        self.nodes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        // Walking backwards settles each directory's subdirectories before the directory
+        let mut empty_subdirs: HashMap<&Path, u64> = HashMap::new();
+        let mut empty = vec![false; self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate().rev() {
+            let nested = empty_subdirs.get(node.path.as_path()).copied().unwrap_or(0);
+            if node.is_dir && node.entry_count == Some(nested) {
+                empty[index] = true;
+                if let Some(parent) = node.path.parent() {
+                    *empty_subdirs.entry(parent).or_default() += 1;
+                }
+            }
+        }
+
+        let mut empty = empty.into_iter();
+        self.nodes.retain(|_| empty.next().unwrap_or(false));
+    }
+
+    /// Filter to only include files
+    pub fn filter_files_only(&mut self) {
+        self.nodes.retain(|node| !node.is_dir);
+    }
+}
+
+/// Iterates over the nodes in their current order
+impl<'a> IntoIterator for &'a ScanResult {
+    type Item = &'a Node;
+    type IntoIter = std::slice::Iter<'a, Node>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.iter()
+    }
+}
+
+/// Compare strings in natural order: runs of ASCII digits compare by their numeric value, so
+/// `img2` sorts before `img10`, and everything else compares character by character.
+///
+/// Numbers that differ only in leading zeros (`01` and `1`) fall back to plain order, so
+/// distinct strings never compare equal.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    while let (Some(x), Some(y)) = (a_rest.chars().next(), b_rest.chars().next()) {
+        let ordering = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (x_digits, x_tail) = split_digits(a_rest);
+            let (y_digits, y_tail) = split_digits(b_rest);
+            (a_rest, b_rest) = (x_tail, y_tail);
+            // Without leading zeros, a longer run of digits is a larger number
+            let x_digits = x_digits.trim_start_matches('0');
+            let y_digits = y_digits.trim_start_matches('0');
+            x_digits
+                .len()
+                .cmp(&y_digits.len())
+                .then_with(|| x_digits.cmp(y_digits))
+        } else {
+            (a_rest, b_rest) = (&a_rest[x.len_utf8()..], &b_rest[y.len_utf8()..]);
+            x.cmp(&y)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    // A string that ran out first is a prefix of the other
+    a_rest.cmp(b_rest).then_with(|| a.cmp(b))
+}
+
+/// Compare paths component by component in [`natural_cmp`] order, so a directory's
+/// children sort right after it
+pub fn natural_path_cmp(a: &Path, b: &Path) -> Ordering {
+    let (mut a_parts, mut b_parts) = (a.components(), b.components());
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (Some(x), Some(y)) => {
+                let ordering = natural_cmp(
+                    &x.as_os_str().to_string_lossy(),
+                    &y.as_os_str().to_string_lossy(),
+                );
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        }
+    }
+}
+
+/// Split a string into its leading ASCII digits and the rest
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Deepest directory containing all of the given paths
+pub fn common_ancestor(paths: &[PathBuf]) -> PathBuf {
+    let Some((first, rest)) = paths.split_first() else {
+        return PathBuf::new();
+    };
+
+    let mut ancestor: &Path = first;
+    for path in rest {
+        while !path.starts_with(ancestor) {
+            match ancestor.parent() {
+                Some(parent) => ancestor = parent,
+                None => return PathBuf::new(),
+            }
+        }
+    }
+    ancestor.to_path_buf()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[test]
     fn test_sort_by_size() {
@@ -86,12 +734,214 @@ mod tests {
         result
             .nodes
             .push(Node::new(PathBuf::from("medium"), 500, false, 1));
+        // Equal sizes are ordered by path, whatever order they were found in
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("tie-b"), 500, false, 1));
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("tie-a"), 500, false, 1));
+
+        result.sort_by_size_desc();
 
+        let paths: Vec<_> = result.nodes.iter().map(|n| n.path.clone()).collect();
+        assert_eq!(
+            paths,
+            ["large", "medium", "tie-a", "tie-b", "small"].map(PathBuf::from)
+        );
+
+        result.nodes.reverse();
         result.sort_by_size_desc();
+        let again: Vec<_> = result.nodes.iter().map(|n| n.path.clone()).collect();
+        assert_eq!(again, paths);
+    }
 
-        assert_eq!(result.nodes[0].size, 1000);
+    #[test]
+    fn test_sort_by_size_asc() {
+        let mut result = ScanResult::new();
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("small"), 100, false, 1));
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("large"), 1000, false, 1));
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("medium"), 500, false, 1));
+
+        result.sort_by_size_asc();
+
+        assert_eq!(result.nodes[0].size, 100);
         assert_eq!(result.nodes[1].size, 500);
-        assert_eq!(result.nodes[2].size, 100);
+        assert_eq!(result.nodes[2].size, 1000);
+    }
+
+    #[test]
+    fn test_sort_by_count() {
+        let mut result = ScanResult::new();
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("few"), 1000, true, 1).with_file_count(2));
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("many"), 10, true, 1).with_file_count(5000));
+
+        result.sort_by_count_desc();
+
+        assert_eq!(result.nodes[0].file_count, 5000);
+        assert_eq!(result.nodes[1].file_count, 2);
+    }
+
+    #[test]
+    fn test_sort_by_self_size() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("nested"), 1000, true, 1).with_self_size(10),
+            Node::new(PathBuf::from("flat"), 500, true, 1).with_self_size(500),
+            Node::new(PathBuf::from("file"), 100, false, 2),
+        ];
+
+        result.sort_by_self_size_desc();
+        let order: Vec<_> = result.nodes.iter().map(|n| n.self_size).collect();
+        assert_eq!(order, [500, 100, 10]);
+
+        result.sort_by_self_size_asc();
+        assert_eq!(result.nodes[0].path, PathBuf::from("nested"));
+    }
+
+    #[test]
+    fn test_sort_by_name() {
+        let mut result = ScanResult::new();
+        for path in ["/r/b", "/r/a-b", "/r/a/z", "/r/a", "/r/C"] {
+            result
+                .nodes
+                .push(Node::new(PathBuf::from(path), 1, true, 1));
+        }
+
+        result.sort_by_name();
+
+        // Paths compare component by component, so a directory's children follow it
+        let paths: Vec<_> = result
+            .nodes
+            .iter()
+            .map(|n| n.path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["/r/C", "/r/a", "/r/a/z", "/r/a-b", "/r/b"]);
+
+        // Numbers inside names sort by value
+        let mut result = ScanResult::new();
+        for path in ["/r/img10", "/r/img2/x", "/r/img100", "/r/img2"] {
+            result
+                .nodes
+                .push(Node::new(PathBuf::from(path), 1, true, 1));
+        }
+        result.sort_by_name();
+        let paths: Vec<_> = result
+            .nodes
+            .iter()
+            .map(|n| n.path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["/r/img2", "/r/img2/x", "/r/img10", "/r/img100"]);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec![
+            "img100",
+            "img10",
+            "img2",
+            "img",
+            "file2.txt",
+            "file10.txt",
+            "file1a",
+            "file1",
+            "2024-10-01",
+            "2024-9-30",
+            "a01",
+            "a1",
+            "a001b",
+            "ä3",
+            "a3",
+            "",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "",
+                "2024-9-30",
+                "2024-10-01",
+                "a01",
+                "a1",
+                "a001b",
+                "a3",
+                "file1",
+                "file1a",
+                "file2.txt",
+                "file10.txt",
+                "img",
+                "img2",
+                "img10",
+                "img100",
+                "ä3",
+            ]
+        );
+        assert_eq!(natural_cmp("img2", "img10"), Ordering::Less);
+        assert_eq!(natural_cmp("img10", "img100"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("x99999999999999999999999", "x3"),
+            Ordering::Greater
+        );
+        assert_eq!(natural_cmp("same", "same"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort_by_depth() {
+        let mut result = ScanResult::new();
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("a/b/c"), 900, true, 3));
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("a"), 100, true, 1));
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("a/b"), 50, true, 2));
+        result
+            .nodes
+            .push(Node::new(PathBuf::from("z"), 800, true, 1));
+
+        result.sort_by_depth();
+
+        let paths: Vec<_> = result
+            .nodes
+            .iter()
+            .map(|n| n.path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["z", "a", "a/b", "a/b/c"]);
+    }
+
+    #[test]
+    fn test_duplicate_wasted_size() {
+        let group = DuplicateGroup {
+            size: 100,
+            paths: vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")],
+        };
+        assert_eq!(group.wasted_size(), 200);
+    }
+
+    #[test]
+    fn test_average_file_size() {
+        let dir = Node::new(PathBuf::from("dir"), 1000, true, 1).with_file_count(4);
+        assert_eq!(dir.average_file_size(), Some(250));
+        assert_eq!(
+            Node::new(PathBuf::from("empty"), 0, true, 1).average_file_size(),
+            None
+        );
+        assert_eq!(
+            Node::new(PathBuf::from("file"), 42, false, 1).average_file_size(),
+            Some(42)
+        );
     }
 
     #[test]
@@ -110,5 +960,359 @@ mod tests {
         let top5 = result.top_n(5);
         assert_eq!(top5.len(), 5);
         assert_eq!(top5[0].size, 1900);
+
+        // 0 is a sentinel for "everything"
+        assert_eq!(result.top_n(0).len(), 20);
+        assert_eq!(result.top_n(100).len(), 20);
+    }
+
+    #[test]
+    fn test_depth_window() {
+        let mut result = ScanResult::new();
+        result.dir_count = 4;
+        for depth in 1..=4 {
+            result.nodes.push(Node::new(
+                PathBuf::from(format!("level{}", depth)),
+                100,
+                true,
+                depth,
+            ));
+        }
+
+        result.filter_by_min_depth(2);
+        result.filter_by_depth(3);
+
+        let depths: Vec<usize> = result.nodes.iter().map(|n| n.depth).collect();
+        assert_eq!(depths, vec![2, 3]);
+        assert_eq!(result.dir_count, 4);
+    }
+
+    #[test]
+    fn test_filter_by_min_size() {
+        let mut result = ScanResult::new();
+        for size in [100, 500, 1000] {
+            result
+                .nodes
+                .push(Node::new(PathBuf::from(size.to_string()), size, true, 1));
+        }
+
+        result.filter_by_min_size(500);
+
+        assert_eq!(result.nodes.len(), 2);
+        assert!(result.nodes.iter().all(|n| n.size >= 500));
+    }
+
+    #[test]
+    fn test_filter_by_file_count() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/a/dense"), 100, true, 1).with_file_count(100),
+            Node::new(PathBuf::from("/a/sparse"), 5000, true, 1).with_file_count(3),
+            Node::new(PathBuf::from("/a/big.bin"), 9000, false, 1),
+        ];
+
+        result.filter_by_file_count(50);
+
+        assert_eq!(result.nodes.len(), 1);
+        assert_eq!(result.nodes[0].path, PathBuf::from("/a/dense"));
+    }
+
+    #[test]
+    fn test_filter_nonzero() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/a"), 300, true, 0),
+            Node::new(PathBuf::from("/a/empty"), 0, true, 1),
+            Node::new(PathBuf::from("/a/data"), 300, true, 1),
+            Node::new(PathBuf::from("/a/data/x.bin"), 300, false, 2),
+            Node::new(PathBuf::from("/a/data/.keep"), 0, false, 2),
+        ];
+
+        result.filter_nonzero();
+
+        let paths: Vec<_> = result
+            .nodes
+            .iter()
+            .map(|n| n.path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["/a", "/a/data", "/a/data/x.bin"]);
+    }
+
+    #[test]
+    fn test_filter_hidden_files() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/h/.config"), 500, true, 1),
+            Node::new(PathBuf::from("/h/.config/app.toml"), 300, false, 2),
+            Node::new(PathBuf::from("/h/.config/.lock"), 200, false, 2),
+            Node::new(PathBuf::from("/h/.bashrc"), 100, false, 1),
+            Node::new(PathBuf::from("/h/notes.txt"), 50, false, 1),
+        ];
+
+        result.filter_hidden_files();
+
+        let paths: Vec<_> = result
+            .nodes
+            .iter()
+            .map(|n| n.path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["/h/.config", "/h/.config/app.toml", "/h/notes.txt"]);
+        assert_eq!(result.nodes[0].size, 500);
+    }
+
+    #[test]
+    fn test_iter_by_size() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/a/small"), 10, true, 1),
+            Node::new(PathBuf::from("/a/large"), 900, true, 1),
+            Node::new(PathBuf::from("/a/tie-b"), 50, false, 1),
+            Node::new(PathBuf::from("/a/tie-a"), 50, false, 1),
+        ];
+        let original: Vec<PathBuf> = result.nodes.iter().map(|n| n.path.clone()).collect();
+
+        let sorted: Vec<&str> = result
+            .iter_by_size()
+            .map(|n| n.path.to_str().unwrap())
+            .collect();
+        assert_eq!(sorted, ["/a/large", "/a/tie-a", "/a/tie-b", "/a/small"]);
+
+        // The nodes keep their order, which iterating the result by reference follows
+        let in_place: Vec<PathBuf> = (&result).into_iter().map(|n| n.path.clone()).collect();
+        assert_eq!(in_place, original);
+        let mut total = 0;
+        for node in &result {
+            total += node.size;
+        }
+        assert_eq!(total, 1010);
+    }
+
+    #[test]
+    fn test_depth_summary() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/r/a"), 700, true, 1),
+            Node::new(PathBuf::from("/r/b"), 300, true, 1),
+            Node::new(PathBuf::from("/r/a/x"), 600, true, 2),
+            Node::new(PathBuf::from("/r/a/x/deep"), 500, true, 3),
+            Node::new(PathBuf::from("/r/b/y"), 100, true, 2),
+            Node::new(PathBuf::from("/r/a/file.bin"), 100, false, 2),
+        ];
+
+        let levels: Vec<_> = result
+            .depth_summary()
+            .iter()
+            .map(|level| (level.depth, level.dir_count, level.size))
+            .collect();
+        assert_eq!(levels, [(1, 2, 1000), (2, 2, 700), (3, 1, 500)]);
+        assert!(ScanResult::new().depth_summary().is_empty());
+    }
+
+    #[test]
+    fn test_disk_info() {
+        let disk = DiskInfo {
+            total: 1000,
+            free: 400,
+            available: 300,
+        };
+        assert_eq!(disk.used(), 600);
+        assert_eq!(disk.percent_of_total(250), 25.0);
+        assert_eq!(DiskInfo { total: 0, ..disk }.percent_of_total(250), 0.0);
+
+        // Merging scans from different volumes drops the capacity
+        let mut a = ScanResult {
+            disk: Some(disk),
+            ..Default::default()
+        };
+        a.merge(ScanResult {
+            disk: Some(disk),
+            ..Default::default()
+        });
+        assert_eq!(a.disk, Some(disk));
+        a.merge(ScanResult::new());
+        assert_eq!(a.disk, None);
+    }
+
+    #[test]
+    fn test_filter_by_age() {
+        let day = Duration::from_secs(86400);
+        let now = SystemTime::now();
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("old"), 100, true, 1).with_modified(Some(now - 30 * day)),
+            Node::new(PathBuf::from("new"), 100, true, 1).with_modified(Some(now)),
+            Node::new(PathBuf::from("empty"), 0, true, 1),
+        ];
+
+        // No bounds keeps everything, including entries without a time
+        result.filter_by_age(None, None);
+        assert_eq!(result.nodes.len(), 3);
+
+        let mut older = ScanResult::new();
+        older.nodes = result.nodes.clone();
+        older.filter_by_age(Some(now - 7 * day), None);
+        assert_eq!(older.nodes.len(), 1);
+        assert_eq!(older.nodes[0].path, PathBuf::from("old"));
+
+        result.filter_by_age(None, Some(now - 7 * day));
+        assert_eq!(result.nodes.len(), 1);
+        assert_eq!(result.nodes[0].path, PathBuf::from("new"));
+    }
+
+    #[test]
+    fn test_filter_empty_dirs() {
+        let dir_node = |path: &str, file_count, entry_count| {
+            Node::new(PathBuf::from(path), 0, true, 1)
+                .with_file_count(file_count)
+                .with_entry_count(entry_count)
+        };
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            dir_node("full", 1, Some(2)),
+            dir_node("full/empty", 0, Some(0)),
+            dir_node("b", 0, Some(1)),
+            dir_node("b/nested", 0, Some(1)),
+            dir_node("b/nested/deeper", 0, Some(0)),
+            dir_node("a", 0, Some(0)),
+            // Its file was filtered out of the scan but is still on disk
+            dir_node("filtered", 0, Some(1)),
+            // Not listed, so its contents are unknown
+            dir_node("unread", 0, None),
+            dir_node("parent", 0, Some(1)),
+            dir_node("parent/unread", 0, None),
+            Node::new(PathBuf::from("full/file.txt"), 1, false, 2),
+        ];
+
+        result.filter_empty_dirs();
+
+        let paths: Vec<_> = result.nodes.iter().map(|n| n.path.clone()).collect();
+        assert_eq!(
+            paths,
+            ["a", "b", "b/nested", "b/nested/deeper", "full/empty"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn test_filter_files_only() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("dir"), 1000, true, 1),
+            Node::new(PathBuf::from("dir/file"), 600, false, 2),
+            Node::new(PathBuf::from("top.txt"), 50, false, 1),
+        ];
+
+        result.filter_files_only();
+
+        assert_eq!(result.nodes.len(), 2);
+        assert!(result.nodes.iter().all(|n| !n.is_dir));
+    }
+
+    #[test]
+    fn test_compare_with() {
+        let mut result = ScanResult::new();
+        result.nodes = vec![
+            Node::new(PathBuf::from("/r/grew"), 500, true, 1),
+            Node::new(PathBuf::from("/r/same"), 100, true, 1),
+            Node::new(PathBuf::from("/r/new"), 50, true, 1),
+        ];
+        let baseline = HashMap::from([
+            (PathBuf::from("/r/grew"), (100, true)),
+            (PathBuf::from("/r/same"), (100, true)),
+            (PathBuf::from("/r/gone"), (1000, true)),
+        ]);
+
+        result.compare_with(&baseline);
+
+        let changes: Vec<(&str, i128)> = result
+            .changes
+            .iter()
+            .map(|c| (c.path.to_str().unwrap(), c.change()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![("/r/gone", -1000), ("/r/grew", 400), ("/r/new", 50)]
+        );
+        assert_eq!(result.changes[0].new_size, None);
+        assert_eq!(result.changes[2].old_size, None);
+    }
+
+    #[test]
+    fn test_files_per_second() {
+        let mut result = ScanResult::new();
+        result.file_count = 500;
+        assert_eq!(result.files_per_second(), 0.0);
+
+        result.scan_duration_ms = 250;
+        assert_eq!(result.files_per_second(), 2000.0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut first = ScanResult::new();
+        first.total_size = 100;
+        first.file_count = 1;
+        first.largest_file = Some((PathBuf::from("/a/x/small.bin"), 100));
+        first
+            .nodes
+            .push(Node::new(PathBuf::from("/a/x"), 100, true, 1));
+
+        let mut second = ScanResult::new();
+        second.total_size = 50;
+        second.file_count = 2;
+        second.largest_file = Some((PathBuf::from("/b/y/big.bin"), 40));
+        second
+            .nodes
+            .push(Node::new(PathBuf::from("/b/y"), 50, true, 1));
+
+        first.merge(second);
+
+        assert_eq!(first.total_size, 150);
+        assert_eq!(first.file_count, 3);
+        assert_eq!(first.nodes.len(), 2);
+        assert_eq!(
+            first.largest_file,
+            Some((PathBuf::from("/a/x/small.bin"), 100))
+        );
+    }
+
+    #[test]
+    fn test_profile_merge() {
+        let ms = Duration::from_millis;
+        let mut profile = Profile::default();
+        profile.add("walk", ms(30));
+        profile.add("aggregate", ms(5));
+
+        let mut other = Profile::default();
+        other.add("walk", ms(10));
+        other.add("duplicates", ms(20));
+        profile.merge(&other);
+
+        let phases: Vec<_> = profile
+            .phases
+            .iter()
+            .map(|timing| (timing.phase, timing.duration))
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                ("walk", ms(40)),
+                ("aggregate", ms(5)),
+                ("duplicates", ms(20))
+            ]
+        );
+        assert_eq!(profile.total(), ms(65));
+    }
+
+    #[test]
+    fn test_common_ancestor() {
+        let paths = vec![
+            PathBuf::from("/home/me/projects/a"),
+            PathBuf::from("/home/me/projects/b/c"),
+        ];
+        assert_eq!(common_ancestor(&paths), PathBuf::from("/home/me/projects"));
+        assert_eq!(
+            common_ancestor(&[PathBuf::from("/only")]),
+            PathBuf::from("/only")
+        );
     }
 }
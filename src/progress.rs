@@ -0,0 +1,353 @@
+//! Terminal progress reporting for the scanner.
+
+use crate::cli::{NumberFormat, Units};
+use disk_scanner::{ProgressReporter, ScanResult};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle, TermLike};
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Spinner on stderr (so piped stdout stays clean), recreated for every scan.
+///
+/// Shows a bar with percent and ETA instead when the next scan's entry count is known.
+/// Streamed files are printed to stdout with the spinner cleared, so the two don't interleave.
+#[derive(Default)]
+pub struct SpinnerProgress {
+    bar: Mutex<Option<ProgressBar>>,
+    /// Entries the next scan will visit, from [`Scanner::count_entries`](disk_scanner::Scanner::count_entries)
+    total: Mutex<Option<u64>>,
+    units: Units,
+    numbers: NumberFormat,
+    hidden: bool,
+    /// Draw on stderr even when it isn't a terminal
+    forced: bool,
+    /// Redraw interval (None = indicatif's 20 Hz limit with a 100ms spinner tick)
+    interval: Option<Duration>,
+}
+
+impl SpinnerProgress {
+    /// Create a new spinner reporter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the units for sizes in progress messages
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Set the separators for counts and sizes in progress messages
+    pub fn with_number_format(mut self, numbers: NumberFormat) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Don't draw the spinner (streamed files are still printed)
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Draw even when stderr isn't a terminal (indicatif stays silent there otherwise)
+    pub fn with_forced(mut self, forced: bool) -> Self {
+        self.forced = forced;
+        self
+    }
+
+    /// Redraw at most once per `interval` instead of the default rate; zero redraws on
+    /// every update without a steady tick
+    pub fn with_interval(mut self, interval: Option<Duration>) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Show a bar against this many entries for the next scan instead of a spinner
+    pub fn set_total(&self, total: u64) {
+        *self.total.lock().unwrap() = Some(total);
+    }
+
+    /// Format a size for progress messages with the configured decimal separator
+    fn size(&self, bytes: u64) -> String {
+        self.numbers.localize(format_size_simple(
+            bytes,
+            self.units,
+            self.numbers.precision,
+        ))
+    }
+
+    /// Update the spinner message if a scan is in progress
+    fn set_message(&self, message: String) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.set_message(message);
+        }
+    }
+}
+
+impl ProgressReporter for SpinnerProgress {
+    fn start(&self) {
+        let target = match (self.hidden, self.forced, self.interval) {
+            (true, _, _) => ProgressDrawTarget::hidden(),
+            (false, true, None) => ProgressDrawTarget::term_like(Box::new(RawStderr)),
+            (false, true, Some(interval)) => {
+                ProgressDrawTarget::term_like_with_hz(Box::new(RawStderr), refresh_rate(interval))
+            }
+            (false, false, None) => ProgressDrawTarget::stderr(),
+            (false, false, Some(interval)) => {
+                ProgressDrawTarget::stderr_with_hz(refresh_rate(interval))
+            }
+        };
+        let total = self.total.lock().unwrap().take();
+        let pb = ProgressBar::with_draw_target(total, target);
+        let style = match total {
+            Some(_) => ProgressStyle::default_bar()
+                .template("{spinner:.green} [{bar:30.green/dim}] {percent:>3}% ETA {eta} {msg}")
+                .expect("Invalid progress template")
+                .progress_chars("=> "),
+            None => ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg}")
+                .expect("Invalid progress template"),
+        };
+        pb.set_style(style);
+        // A zero interval disables the steady tick
+        pb.enable_steady_tick(self.interval.unwrap_or(Duration::from_millis(100)));
+        pb.set_message("Starting scan...");
+        *self.bar.lock().unwrap() = Some(pb);
+    }
+
+    fn scanning(&self, current_dir: &str, files: u64, dirs: u64, bytes: u64) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            // The tree may have grown since it was counted
+            let position = files + dirs;
+            bar.set_position(bar.length().map_or(position, |len| position.min(len)));
+        }
+        self.set_message(format!(
+            "Scanning: {} | {} files, {} dirs | {}",
+            truncate_str(current_dir, 20),
+            format_number(files, self.numbers.separator),
+            format_number(dirs, self.numbers.separator),
+            self.size(bytes)
+        ));
+    }
+
+    fn file_found(&self, path: &Path, size: u64) {
+        let line = format!("{:>12}  {}", self.size(size), path.display());
+        // A closed stdout only loses the early feedback; the scan itself carries on
+        let print = || {
+            let _ = writeln!(std::io::stdout().lock(), "{line}");
+        };
+        match self.bar.lock().unwrap().as_ref() {
+            Some(bar) => bar.suspend(print),
+            None => print(),
+        }
+    }
+
+    fn phase(&self, message: &str) {
+        self.set_message(message.to_string());
+    }
+
+    fn finish(&self, result: &ScanResult, duration: Duration) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish_with_message(format!(
+                "{} {} files, {} dirs ({}) in {}",
+                if result.interrupted {
+                    "Interrupted!"
+                } else {
+                    "Done!"
+                },
+                format_number(result.file_count, self.numbers.separator),
+                format_number(result.dir_count, self.numbers.separator),
+                self.size(result.total_size),
+                format_duration(duration)
+            ));
+        }
+    }
+}
+
+/// Format a number with `separator` between groups of thousands (None = no grouping)
+pub fn format_number(n: u64, separator: Option<char>) -> String {
+    let s = n.to_string();
+    let Some(separator) = separator else {
+        return s;
+    };
+    let mut result = String::new();
+    for (i, c) in s.chars().rev().enumerate() {
+        if i > 0 && i % 3 == 0 {
+            result.push(separator);
+        }
+        result.push(c);
+    }
+    result.chars().rev().collect()
+}
+
+/// Truncate a string to `max_len` characters with an ellipsis, cutting only between
+/// characters; names that fit are borrowed as they are
+fn truncate_str(s: &str, max_len: usize) -> Cow<'_, str> {
+    if s.char_indices().nth(max_len).is_none() {
+        return Cow::Borrowed(s);
+    }
+    let cut = s
+        .char_indices()
+        .nth(max_len.saturating_sub(3))
+        .map_or(s.len(), |(idx, _)| idx);
+    Cow::Owned(format!("{}...", &s[..cut]))
+}
+
+/// Redraws per second for an interval, within what indicatif accepts (1-255 Hz)
+fn refresh_rate(interval: Duration) -> u8 {
+    let millis = interval.as_millis().max(1);
+    (1000 / millis).clamp(1, u8::MAX as u128) as u8
+}
+
+/// Stderr driven with ANSI escapes whether or not it is a terminal, for --force-progress
+#[derive(Debug)]
+struct RawStderr;
+
+impl TermLike for RawStderr {
+    fn width(&self) -> u16 {
+        terminal_size::terminal_size_of(io::stderr()).map_or(80, |(width, _)| width.0)
+    }
+
+    fn move_cursor_up(&self, n: usize) -> io::Result<()> {
+        self.write_str(&format!("\x1b[{n}A"))
+    }
+
+    fn move_cursor_down(&self, n: usize) -> io::Result<()> {
+        self.write_str(&format!("\x1b[{n}B"))
+    }
+
+    fn move_cursor_right(&self, n: usize) -> io::Result<()> {
+        self.write_str(&format!("\x1b[{n}C"))
+    }
+
+    fn move_cursor_left(&self, n: usize) -> io::Result<()> {
+        self.write_str(&format!("\x1b[{n}D"))
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        writeln!(io::stderr(), "{s}")
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        write!(io::stderr(), "{s}")
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        self.write_str("\r\x1b[2K")
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Format a duration in human-readable form
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let millis = duration.subsec_millis();
+
+    if secs >= 60 {
+        let mins = secs / 60;
+        let remaining_secs = secs % 60;
+        format!("{}m {}s", mins, remaining_secs)
+    } else if secs > 0 {
+        format!("{}.{:02}s", secs, millis / 10)
+    } else {
+        format!("{}ms", millis)
+    }
+}
+
+/// Simple size formatting for progress messages, with one decimal place below a gigabyte and
+/// two above unless `precision` is set
+fn format_size_simple(bytes: u64, units: Units, precision: Option<usize>) -> String {
+    let (base, suffixes) = match units {
+        Units::Binary => (1024u64, ["KiB", "MiB", "GiB", "TiB"]),
+        Units::Si => (1000u64, ["KB", "MB", "GB", "TB"]),
+        Units::Bytes => return format!("{} B", bytes),
+    };
+    let kb = base;
+    let mb = kb * base;
+    let gb = mb * base;
+    let tb = gb * base;
+
+    let large = precision.unwrap_or(2);
+    let small = precision.unwrap_or(1);
+    if bytes >= tb {
+        format!("{:.*} {}", large, bytes as f64 / tb as f64, suffixes[3])
+    } else if bytes >= gb {
+        format!("{:.*} {}", large, bytes as f64 / gb as f64, suffixes[2])
+    } else if bytes >= mb {
+        format!("{:.*} {}", small, bytes as f64 / mb as f64, suffixes[1])
+    } else if bytes >= kb {
+        format!("{:.*} {}", small, bytes as f64 / kb as f64, suffixes[0])
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size_simple() {
+        assert_eq!(format_size_simple(500, Units::Binary, None), "500 B");
+        assert_eq!(format_size_simple(1024, Units::Binary, None), "1.0 KiB");
+        assert_eq!(format_size_simple(1536, Units::Binary, None), "1.5 KiB");
+        assert_eq!(format_size_simple(1048576, Units::Binary, None), "1.0 MiB");
+        assert_eq!(
+            format_size_simple(1073741824, Units::Binary, None),
+            "1.00 GiB"
+        );
+        assert_eq!(
+            format_size_simple(1_000_000, Units::Binary, None),
+            "976.6 KiB"
+        );
+        assert_eq!(format_size_simple(1_000_000, Units::Si, None), "1.0 MB");
+        assert_eq!(format_size_simple(1536, Units::Si, None), "1.5 KB");
+    }
+
+    #[test]
+    fn test_format_size_simple_precision() {
+        assert_eq!(format_size_simple(1536, Units::Binary, Some(0)), "2 KiB");
+        assert_eq!(
+            format_size_simple(1536, Units::Binary, Some(3)),
+            "1.500 KiB"
+        );
+        assert_eq!(format_size_simple(500, Units::Binary, Some(3)), "500 B");
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(0, Some(',')), "0");
+        assert_eq!(format_number(999, Some(',')), "999");
+        assert_eq!(format_number(1000, Some(',')), "1,000");
+        assert_eq!(format_number(1234567, Some(',')), "1,234,567");
+        assert_eq!(format_number(1234567, Some('.')), "1.234.567");
+        assert_eq!(format_number(1234567, Some(' ')), "1 234 567");
+        assert_eq!(format_number(1234567, None), "1234567");
+    }
+
+    #[test]
+    fn test_refresh_rate() {
+        assert_eq!(refresh_rate(Duration::from_millis(50)), 20);
+        assert_eq!(refresh_rate(Duration::from_millis(500)), 2);
+        assert_eq!(refresh_rate(Duration::from_secs(5)), 1);
+        assert_eq!(refresh_rate(Duration::ZERO), 255);
+    }
+
+    #[test]
+    fn test_truncate_str() {
+        assert_eq!(truncate_str("short", 10), "short");
+        assert_eq!(truncate_str("this is a long string", 10), "this is...");
+        assert_eq!(truncate_str("exactly 10", 10), "exactly 10");
+
+        // Multi-byte characters are counted once and never split
+        assert_eq!(truncate_str("Música_compartida_🎵", 10), "Música_...");
+        assert_eq!(truncate_str("Música🎵", 7), "Música🎵");
+        assert_eq!(truncate_str("🎵🎵🎵🎵🎵🎵", 5), "🎵🎵...");
+        assert_eq!(truncate_str("写真フォルダ", 4), "写...");
+    }
+}
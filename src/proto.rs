@@ -0,0 +1,288 @@
+//! Compact length-delimited protobuf output for data pipelines (`protobuf` feature).
+//!
+//! A report is a stream of [`Record`]s, each prefixed with its length as a varint (the
+//! framing of protobuf's `writeDelimitedTo` / `parseDelimitedFrom`). Every scanned root
+//! contributes a `summary` record followed by one `entry` record per reported node. The
+//! schema is documented in `proto/disk_scanner.proto`.
+
+use crate::node::{Node, ScanResult};
+use anyhow::{Context, Result, bail};
+use prost::Message;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version of the message schema, bumped whenever fields change meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Totals for one scanned root
+#[derive(Clone, PartialEq, Message)]
+pub struct Summary {
+    /// Version of the message schema ([`SCHEMA_VERSION`])
+    #[prost(uint32, tag = "1")]
+    pub schema: u32,
+    /// Scanned directory (lossily converted to UTF-8)
+    #[prost(string, tag = "2")]
+    pub root: String,
+    #[prost(uint64, tag = "3")]
+    pub total_size: u64,
+    #[prost(uint64, tag = "4")]
+    pub file_count: u64,
+    #[prost(uint64, tag = "5")]
+    pub dir_count: u64,
+    #[prost(uint64, tag = "6")]
+    pub error_count: u64,
+    #[prost(uint64, tag = "7")]
+    pub scan_duration_ms: u64,
+    /// Whether the scan was stopped early, leaving the totals incomplete
+    #[prost(bool, tag = "8")]
+    pub interrupted: bool,
+}
+
+/// One reported file or directory
+#[derive(Clone, PartialEq, Message)]
+pub struct Entry {
+    /// Absolute path (lossily converted to UTF-8)
+    #[prost(string, tag = "1")]
+    pub path: String,
+    #[prost(uint64, tag = "2")]
+    pub size: u64,
+    #[prost(uint64, tag = "3")]
+    pub apparent_size: u64,
+    #[prost(uint64, tag = "4")]
+    pub file_count: u64,
+    #[prost(bool, tag = "5")]
+    pub is_dir: bool,
+    #[prost(uint32, tag = "6")]
+    pub depth: u32,
+    #[prost(bool, tag = "7")]
+    pub accessible: bool,
+    /// Last modification time in whole seconds since the Unix epoch, if known
+    #[prost(int64, optional, tag = "8")]
+    pub modified: Option<i64>,
+    /// Size of the files directly inside a directory (for files, `size`)
+    #[prost(uint64, tag = "9")]
+    pub self_size: u64,
+}
+
+/// A single framed message in the stream: a root's summary or one of its entries
+#[derive(Clone, PartialEq, Message)]
+pub struct Record {
+    #[prost(oneof = "Kind", tags = "1, 2")]
+    pub kind: Option<Kind>,
+}
+
+/// What a [`Record`] holds
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum Kind {
+    #[prost(message, tag = "1")]
+    Summary(Summary),
+    #[prost(message, tag = "2")]
+    Entry(Entry),
+}
+
+impl Summary {
+    /// Summarize `result` for `root`
+    pub fn new(result: &ScanResult, root: &Path) -> Self {
+        Self {
+            schema: SCHEMA_VERSION,
+            root: root.to_string_lossy().into_owned(),
+            total_size: result.total_size,
+            file_count: result.file_count,
+            dir_count: result.dir_count,
+            error_count: result.error_count,
+            scan_duration_ms: result.scan_duration_ms,
+            interrupted: result.interrupted,
+        }
+    }
+}
+
+impl From<&Node> for Entry {
+    fn from(node: &Node) -> Self {
+        Self {
+            path: node.path.to_string_lossy().into_owned(),
+            size: node.size,
+            apparent_size: node.apparent_size,
+            file_count: node.file_count,
+            is_dir: node.is_dir,
+            depth: u32::try_from(node.depth).unwrap_or(u32::MAX),
+            accessible: node.accessible,
+            modified: node.modified.map(unix_seconds),
+            self_size: node.self_size,
+        }
+    }
+}
+
+impl From<Entry> for Node {
+    fn from(entry: Entry) -> Self {
+        Node::new(
+            PathBuf::from(entry.path),
+            entry.size,
+            entry.is_dir,
+            entry.depth as usize,
+        )
+        .with_self_size(entry.self_size)
+        .with_apparent_size(entry.apparent_size)
+        .with_file_count(entry.file_count)
+        .with_accessible(entry.accessible)
+        .with_modified(entry.modified.map(from_unix_seconds))
+    }
+}
+
+/// Write the summary of `result` and then `nodes` as length-delimited records
+pub fn write_report(
+    out: &mut dyn Write,
+    result: &ScanResult,
+    root: &Path,
+    nodes: &[Node],
+) -> Result<()> {
+    let summary = Kind::Summary(Summary::new(result, root));
+    let entries = nodes.iter().map(|node| Kind::Entry(node.into()));
+    let mut buf = Vec::new();
+    for kind in std::iter::once(summary).chain(entries) {
+        buf.clear();
+        Record { kind: Some(kind) }
+            .encode_length_delimited(&mut buf)
+            .context("Failed to encode record")?;
+        out.write_all(&buf)
+            .context("Failed to write binary output")?;
+    }
+    Ok(())
+}
+
+/// Decode a stream written by [`write_report`] into one result per root.
+///
+/// Only what the stream carries is restored: the summary totals and the reported nodes.
+pub fn read_reports(mut bytes: &[u8]) -> Result<Vec<(PathBuf, ScanResult)>> {
+    let mut reports: Vec<(PathBuf, ScanResult)> = Vec::new();
+    while !bytes.is_empty() {
+        let record =
+            Record::decode_length_delimited(&mut bytes).context("Failed to decode record")?;
+        match record.kind {
+            Some(Kind::Summary(summary)) => {
+                if summary.schema != SCHEMA_VERSION {
+                    bail!(
+                        "Unsupported schema version {} (expected {})",
+                        summary.schema,
+                        SCHEMA_VERSION
+                    );
+                }
+                let mut result = ScanResult::new();
+                result.total_size = summary.total_size;
+                result.file_count = summary.file_count;
+                result.dir_count = summary.dir_count;
+                result.error_count = summary.error_count;
+                result.scan_duration_ms = summary.scan_duration_ms;
+                result.interrupted = summary.interrupted;
+                reports.push((PathBuf::from(summary.root), result));
+            }
+            Some(Kind::Entry(entry)) => match reports.last_mut() {
+                Some((_, result)) => result.nodes.push(entry.into()),
+                None => bail!("Entry record before any summary"),
+            },
+            None => bail!("Record without a summary or entry"),
+        }
+    }
+    Ok(reports)
+}
+
+/// Whole seconds between the Unix epoch and `time` (negative before it)
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    }
+}
+
+/// The time `secs` seconds after (or before, if negative) the Unix epoch
+fn from_unix_seconds(secs: i64) -> SystemTime {
+    let offset = Duration::from_secs(secs.unsigned_abs());
+    if secs < 0 {
+        UNIX_EPOCH - offset
+    } else {
+        UNIX_EPOCH + offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut result = ScanResult::new();
+        result.total_size = 4096;
+        result.file_count = 3;
+        result.dir_count = 1;
+        result.error_count = 2;
+        result.scan_duration_ms = 15;
+        result.interrupted = true;
+        result.nodes = vec![
+            Node::new(PathBuf::from("/data/photos"), 4000, true, 1)
+                .with_self_size(1000)
+                .with_apparent_size(3900)
+                .with_file_count(2)
+                .with_modified(Some(modified)),
+            Node::new(PathBuf::from("/data/notes.txt"), 96, false, 1).with_accessible(false),
+        ];
+
+        let mut out = Vec::new();
+        write_report(&mut out, &result, Path::new("/data"), &result.nodes).unwrap();
+        write_report(&mut out, &ScanResult::new(), Path::new("/other"), &[]).unwrap();
+
+        let reports = read_reports(&out).unwrap();
+        assert_eq!(reports.len(), 2);
+        let (root, decoded) = &reports[0];
+        assert_eq!(root, Path::new("/data"));
+        assert_eq!(
+            (decoded.total_size, decoded.file_count, decoded.dir_count),
+            (4096, 3, 1)
+        );
+        assert_eq!((decoded.error_count, decoded.scan_duration_ms), (2, 15));
+        assert!(decoded.interrupted);
+        assert_eq!(decoded.nodes.len(), 2);
+        for (decoded, original) in decoded.nodes.iter().zip(&result.nodes) {
+            assert_eq!(decoded.path, original.path);
+            assert_eq!(
+                (decoded.size, decoded.apparent_size, decoded.file_count),
+                (original.size, original.apparent_size, original.file_count)
+            );
+            assert_eq!(decoded.self_size, original.self_size);
+            assert_eq!(
+                (decoded.is_dir, decoded.depth, decoded.accessible),
+                (original.is_dir, original.depth, original.accessible)
+            );
+            assert_eq!(decoded.modified, original.modified);
+        }
+        assert_eq!(reports[1].0, Path::new("/other"));
+        assert!(reports[1].1.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_read_reports_errors() {
+        let mut entry_first = Vec::new();
+        Record {
+            kind: Some(Kind::Entry(Entry::default())),
+        }
+        .encode_length_delimited(&mut entry_first)
+        .unwrap();
+        assert!(
+            read_reports(&entry_first)
+                .unwrap_err()
+                .to_string()
+                .contains("before any summary")
+        );
+
+        let mut out = Vec::new();
+        write_report(&mut out, &ScanResult::new(), Path::new("/r"), &[]).unwrap();
+        assert!(read_reports(&out[..out.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_unix_seconds() {
+        for secs in [0, 1_700_000_000, -86_400] {
+            assert_eq!(unix_seconds(from_unix_seconds(secs)), secs);
+        }
+    }
+}
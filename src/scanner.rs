@@ -1,40 +1,201 @@
 //! Directory scanning logic using parallel traversal.
 
-use crate::node::{Node, ScanResult};
+use crate::cache::{CacheIndex, CachedDir, CachedFile, ScanCache};
+use crate::node::{
+    DiskInfo, DuplicateGroup, Node, Profile, ScanResult, ScanStats, SizeBucket, TypeSummary,
+};
 use anyhow::{Context, Result};
 use filesize::PathExt;
-use indicatif::{ProgressBar, ProgressStyle};
-use jwalk::WalkDir;
-use std::collections::HashMap;
+use glob::Pattern;
+use ignore::Match;
+use ignore::gitignore::Gitignore;
+use jwalk::WalkDirGeneric;
+use rayon::prelude::*;
+use regex::Regex;
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Maximum number of error paths kept on a [`ScanResult`]; further errors are only counted
+pub const MAX_RECORDED_ERRORS: usize = 1000;
+
+/// Minimum time between progress updates unless [`Scanner::progress_interval`] is set
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Receives progress updates from [`Scanner::scan`].
+///
+/// All methods default to no-ops so implementors only handle what they display.
+pub trait ProgressReporter: Send + Sync {
+    /// Called once when a scan starts
+    fn start(&self) {}
+
+    /// Called periodically during traversal with the running totals
+    fn scanning(&self, _current_dir: &str, _files: u64, _dirs: u64, _bytes: u64) {}
+
+    /// Called for each file of at least [`Scanner::stream_files`]'s size as soon as it is found
+    fn file_found(&self, _path: &Path, _size: u64) {}
+
+    /// Called when the scan moves on to a post-traversal phase
+    fn phase(&self, _message: &str) {}
+
+    /// Called once when a scan completes successfully
+    fn finish(&self, _result: &ScanResult, _duration: Duration) {}
+}
 
 /// Scanner configuration
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Scanner {
-    /// Number of threads to use (None = use all cores)
+    /// Number of threads to use (None or 0 = use all cores, 1 = walk serially in name order)
     pub num_threads: Option<usize>,
     /// Whether to include files in results (not just directories)
     pub include_files: bool,
+    /// Glob patterns for files to include in results even without `include_files`, matched
+    /// against the file name or full path
+    pub include_files_matching: Vec<Pattern>,
+    /// Whether to add a node for the scan root itself (depth 0)
+    pub include_root: bool,
+    /// Glob patterns for entries to skip (excluded directories are not descended into)
+    pub exclude: Vec<Pattern>,
+    /// Regexes a file's full path must match to be counted (empty = all files)
+    pub include_regex: Vec<Regex>,
+    /// Regexes on the full path for entries to skip (excluded directories are not descended into)
+    pub exclude_regex: Vec<Regex>,
+    /// Whether to skip entries ignored by `.gitignore` files in the scanned tree
+    pub respect_gitignore: bool,
+    /// Whether to report apparent sizes (logical length) instead of size on disk
+    pub apparent_size: bool,
+    /// Block size to round apparent file sizes up to, simulating another filesystem (None or 0 = off)
+    pub block_size: Option<u64>,
+    /// Lowercase file extensions to count (empty = all files)
+    pub extensions: Vec<String>,
+    /// Whether to aggregate totals per file extension
+    pub summarize_types: bool,
+    /// Increasing size boundaries to bucket files by (None = no histogram)
+    pub histogram_bounds: Option<Vec<u64>>,
+    /// Whether to hash same-sized files to find duplicates
+    pub find_duplicates: bool,
+    /// Whether to follow symbolic links during traversal
+    pub follow_symlinks: bool,
+    /// Whether to skip hidden files and directories
+    pub skip_hidden: bool,
+    /// Whether to look up the capacity of the scanned volume
+    pub disk_info: bool,
+    /// Whether to stay on the filesystem holding the scan root
+    pub one_file_system: bool,
+    /// Whether to record directories where the walk entered another filesystem
+    pub detect_mount_crossings: bool,
+    /// Whether to descend into pseudo-filesystems such as procfs, sysfs and devtmpfs
+    pub scan_special: bool,
+    /// Whether to count the size of a hard-linked file only at its first link
+    pub count_links_once: bool,
+    /// Deepest level to descend to below the root (None = unlimited)
+    pub max_scan_depth: Option<usize>,
+    /// Whether to list the contents of zip and tar archives as virtual subtrees
+    #[cfg(feature = "archives")]
+    pub into_archives: bool,
+    /// Minimum size of files reported to the progress receiver during the walk (None = none)
+    pub stream_min_size: Option<u64>,
+    /// Maximum number of nodes kept in the result, largest first (None or 0 = all)
+    pub max_results: Option<usize>,
+    /// Whether to record per-thread and per-phase diagnostics
+    pub collect_stats: bool,
+    /// Whether to time each phase of the scan
+    pub profile: bool,
+    /// Minimum time between progress updates (None = [`DEFAULT_PROGRESS_INTERVAL`])
+    pub progress_interval: Option<Duration>,
+    /// Receiver for progress updates (None = scan silently)
+    pub progress: Option<Arc<dyn ProgressReporter>>,
+    /// Flag that stops the walk early when set, leaving a partial result (None = never)
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+/// Sizes and identity of a file as read from its metadata
+#[derive(Default)]
+struct FileInfo {
+    disk_size: u64,
+    apparent_size: u64,
+    modified: Option<SystemTime>,
+    /// `(device, inode)`, only for files with several hard links
+    link_id: Option<(u64, u64)>,
+}
+
+/// Totals aggregated for a directory from the files beneath it
+#[derive(Default, Clone, Copy)]
+struct DirTotals {
+    size: u64,
+    /// Size of the files directly inside, which rolling up leaves alone
+    self_size: u64,
+    apparent_size: u64,
+    file_count: u64,
+    /// Modification time of the newest file
+    newest: Option<SystemTime>,
+}
+
+impl DirTotals {
+    /// Totals for a single file
+    fn file(size: u64, apparent_size: u64, modified: Option<SystemTime>) -> Self {
+        Self {
+            size,
+            self_size: size,
+            apparent_size,
+            file_count: 1,
+            newest: modified,
+        }
+    }
+
+    /// Accumulate a subdirectory's totals into these
+    fn add(&mut self, other: &DirTotals) {
+        self.size += other.size;
+        self.apparent_size += other.apparent_size;
+        self.file_count += other.file_count;
+        self.newest = self.newest.max(other.newest);
+    }
+
+    /// Accumulate a file directly inside the directory into these
+    fn add_file(&mut self, file: &DirTotals) {
+        self.add(file);
+        self.self_size += file.self_size;
+    }
 }
 
-/// Entry collected during scanning
+/// `.gitignore` matchers inherited from ancestor directories, outermost first
+type GitignoreStack = Vec<Arc<Gitignore>>;
+
+/// File collected during scanning
 struct ScannedEntry {
     path: PathBuf,
     size: u64,
-    is_dir: bool,
+    apparent_size: u64,
+    modified: Option<SystemTime>,
     depth: usize,
 }
 
+/// Directory collected during scanning, identified by its index in the scan's list
+struct ScannedDir {
+    path: PathBuf,
+    depth: usize,
+    /// Index of the parent directory, which always comes earlier in the list
+    parent: Option<usize>,
+    /// The files directly inside until the walk ends, then everything beneath
+    totals: DirTotals,
+    accessible: bool,
+    /// Entries listed directly inside, once the walk ends (None if it wasn't listed)
+    entry_count: Option<u64>,
+}
+
 impl Scanner {
     /// Create a new Scanner with default settings
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// Set the number of threads
+    /// Set the number of threads.
+    ///
+    /// With a single thread the walk runs on the calling thread and visits each directory's
+    /// entries in name order, so repeated scans of the same tree see entries in the same order.
     pub fn with_threads(mut self, threads: Option<usize>) -> Self {
         self.num_threads = threads;
         self
@@ -46,55 +207,530 @@ impl Scanner {
         self
     }
 
+    /// Include files matching any of the given glob patterns in the results, alongside the
+    /// directories
+    pub fn include_files_matching(mut self, patterns: Vec<Pattern>) -> Self {
+        self.include_files_matching = patterns;
+        self
+    }
+
+    /// Add a node for the scan root itself, at depth 0 and holding the total size
+    pub fn include_root(mut self, include: bool) -> Self {
+        self.include_root = include;
+        self
+    }
+
+    /// Skip entries matching any of the given glob patterns
+    pub fn with_excludes(mut self, patterns: Vec<Pattern>) -> Self {
+        self.exclude = patterns;
+        self
+    }
+
+    /// Only count files whose full path matches one of the given regexes
+    pub fn with_include_regexes(mut self, patterns: Vec<Regex>) -> Self {
+        self.include_regex = patterns;
+        self
+    }
+
+    /// Skip entries whose full path matches any of the given regexes; this takes
+    /// precedence over the include regexes
+    pub fn with_exclude_regexes(mut self, patterns: Vec<Regex>) -> Self {
+        self.exclude_regex = patterns;
+        self
+    }
+
+    /// Skip entries ignored by `.gitignore` files
+    pub fn respect_gitignore(mut self, respect: bool) -> Self {
+        self.respect_gitignore = respect;
+        self
+    }
+
+    /// Report apparent sizes instead of size on disk
+    pub fn apparent_size(mut self, apparent: bool) -> Self {
+        self.apparent_size = apparent;
+        self
+    }
+
+    /// Report each file's apparent size rounded up to a multiple of `block_size`, to estimate
+    /// usage on a filesystem with that allocation unit. `None` or 0 keeps the default sizes.
+    pub fn block_size(mut self, block_size: Option<u64>) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Only count files with one of the given extensions (case-insensitive, leading dot optional)
+    pub fn with_extensions(mut self, extensions: Vec<String>) -> Self {
+        self.extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Aggregate totals per file extension into `ScanResult::types`
+    pub fn summarize_types(mut self, summarize: bool) -> Self {
+        self.summarize_types = summarize;
+        self
+    }
+
+    /// Count files and their sizes into `ScanResult::histogram`, with a bucket below the
+    /// first of these increasing boundaries, one between each pair, and one from the last up
+    pub fn size_histogram(mut self, bounds: Option<Vec<u64>>) -> Self {
+        self.histogram_bounds = bounds;
+        self
+    }
+
+    /// Detect duplicate files by content into `ScanResult::duplicates`
+    pub fn find_duplicates(mut self, find: bool) -> Self {
+        self.find_duplicates = find;
+        self
+    }
+
+    /// Follow symbolic links.
+    ///
+    /// Symlinked directories whose target was already scanned (including cycles) are
+    /// skipped, but sizes may still be double-counted when a link is reached before the
+    /// directory it points to.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Skip hidden entries (dotfiles, and entries with the hidden attribute on Windows)
+    pub fn skip_hidden(mut self, skip: bool) -> Self {
+        self.skip_hidden = skip;
+        self
+    }
+
+    /// Look up the total, used, and free space of the volume holding the scan root
+    pub fn disk_info(mut self, disk_info: bool) -> Self {
+        self.disk_info = disk_info;
+        self
+    }
+
+    /// Don't descend into directories on other filesystems (mount points), like `du -x`.
+    ///
+    /// Only supported on Unix; elsewhere this has no effect.
+    pub fn one_file_system(mut self, one_file_system: bool) -> Self {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    /// Record the directories where the walk entered another filesystem in
+    /// [`ScanResult::mount_crossings`]; sizes are unaffected.
+    ///
+    /// Costs an extra `stat` per directory. Only supported on Unix; elsewhere nothing is recorded.
+    pub fn detect_mount_crossings(mut self, detect: bool) -> Self {
+        self.detect_mount_crossings = detect;
+        self
+    }
+
+    /// Descend into pseudo-filesystems (procfs, sysfs, devtmpfs, ...), which are skipped by default.
+    ///
+    /// Their mount points are read from `/proc/self/mounts`, so only Linux skips anything.
+    pub fn scan_special(mut self, scan_special: bool) -> Self {
+        self.scan_special = scan_special;
+        self
+    }
+
+    /// Count a file with several hard links only once, at the first link encountered, like
+    /// `du`; later links add nothing to the totals. Files are identified by `(device, inode)`.
+    ///
+    /// Cached sizes are not reused with this set, since each file needs a `stat`. Only
+    /// supported on Unix; elsewhere every link is counted.
+    pub fn count_links_once(mut self, count_links_once: bool) -> Self {
+        self.count_links_once = count_links_once;
+        self
+    }
+
+    /// Stop descending below `depth` levels under the root (1 = only the root's own entries).
+    ///
+    /// Directory sizes then only count files within that depth.
+    pub fn max_scan_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_scan_depth = depth;
+        self
+    }
+
+    /// List the files inside zip and tar archives as nodes under the archive's path.
+    ///
+    /// Their sizes are uncompressed and not added to any totals, which still count the
+    /// archive's own size. Unreadable archives are recorded as errors.
+    #[cfg(feature = "archives")]
+    pub fn into_archives(mut self, into_archives: bool) -> Self {
+        self.into_archives = into_archives;
+        self
+    }
+
+    /// Report files of at least `min_size` bytes to the progress receiver as they are found,
+    /// before the scan finishes
+    pub fn stream_files(mut self, min_size: Option<u64>) -> Self {
+        self.stream_min_size = min_size;
+        self
+    }
+
+    /// Keep only the `max` largest nodes, already sorted by size, instead of every
+    /// directory (and file) in the tree. `None` or 0 keeps all nodes.
+    pub fn max_results(mut self, max: Option<usize>) -> Self {
+        self.max_results = max;
+        self
+    }
+
+    /// Record how many entries each walk thread read, waits on the visited-directories lock
+    /// and phase timings
+    /// in [`ScanResult::stats`]
+    pub fn collect_stats(mut self, collect: bool) -> Self {
+        self.collect_stats = collect;
+        self
+    }
+
+    /// Time each phase of the scan (walk, aggregation, hashing, ...) into
+    /// [`ScanResult::profile`]
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Send progress updates at most once per `interval`; zero reports every entry
+    pub fn progress_interval(mut self, interval: Option<Duration>) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Report progress to the given receiver while scanning
+    pub fn with_progress(mut self, progress: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop the walk once `flag` is set, e.g. from a Ctrl-C handler.
+    ///
+    /// Directories are no longer descended into and the entries found so far are
+    /// aggregated as usual; the result is marked [`interrupted`](ScanResult::interrupted).
+    pub fn cancel_on(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
     /// Scan a directory and return results
     pub fn scan(&self, root: &Path) -> Result<ScanResult> {
+        self.scan_with_filter(root, |_, _| true)
+    }
+
+    /// Scan a directory, keeping only entries for which `filter(path, is_dir)` returns true.
+    ///
+    /// The filter runs after the built-in filters, on the walker's threads; a directory it
+    /// rejects is not descended into. The root itself is always scanned.
+    pub fn scan_with_filter<F>(&self, root: &Path, filter: F) -> Result<ScanResult>
+    where
+        F: Fn(&Path, bool) -> bool + Send + Sync + 'static,
+    {
+        self.walk(root, None, false, Arc::new(filter))
+            .map(|(result, _)| result)
+    }
+
+    /// Count the files and directories a scan of `root` visits (including the root), as
+    /// passed to [`ProgressReporter::scanning`], so progress can be shown against a total.
+    ///
+    /// This walks the whole tree with the same filters, so it takes about as long as a scan.
+    pub fn count_entries(&self, root: &Path) -> Result<u64> {
+        let counter = Scanner {
+            include_files: false,
+            include_files_matching: Vec::new(),
+            summarize_types: false,
+            histogram_bounds: None,
+            find_duplicates: false,
+            disk_info: false,
+            stream_min_size: None,
+            progress: None,
+            ..self.clone()
+        };
+        let result = counter.scan(root)?;
+        Ok(result.file_count + result.dir_count + 1)
+    }
+
+    /// Scan a directory, reusing file sizes from `previous` for directories whose
+    /// modification time is unchanged, and return a cache for the next scan.
+    ///
+    /// Files rewritten in place don't change their directory's modification time, so
+    /// their new sizes are only picked up once something is added to or removed from
+    /// the directory.
+    pub fn scan_incremental(
+        &self,
+        root: &Path,
+        previous: Option<&ScanCache>,
+    ) -> Result<(ScanResult, ScanCache)> {
+        // Listings recorded with different filters can't be reused
+        let previous = previous.filter(|cache| cache.options == self.cache_options());
+        self.walk(root, previous, true, Arc::new(|_, _| true))
+    }
+
+    /// Options that affect which files a cached directory listing contains
+    pub fn cache_options(&self) -> String {
+        let exclude: Vec<&str> = self.exclude.iter().map(|p| p.as_str()).collect();
+        let include_regex: Vec<&str> = self.include_regex.iter().map(|r| r.as_str()).collect();
+        let exclude_regex: Vec<&str> = self.exclude_regex.iter().map(|r| r.as_str()).collect();
+        format!(
+            "exclude={:?} include_regex={:?} exclude_regex={:?} ext={:?} gitignore={} follow_symlinks={} skip_hidden={} one_file_system={} scan_special={} max_scan_depth={:?}",
+            exclude,
+            include_regex,
+            exclude_regex,
+            self.extensions,
+            self.respect_gitignore,
+            self.follow_symlinks,
+            self.skip_hidden,
+            self.one_file_system,
+            self.scan_special,
+            self.max_scan_depth
+        )
+    }
+
+    /// Walk a directory, optionally reusing and recording directory listings
+    fn walk(
+        &self,
+        root: &Path,
+        previous: Option<&ScanCache>,
+        record_cache: bool,
+        filter: EntryFilter,
+    ) -> Result<(ScanResult, ScanCache)> {
         let root = root
             .canonicalize()
             .with_context(|| format!("Failed to resolve path: {}", root.display()))?;
 
-        // Setup progress indicator
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template("{spinner:.green} {msg}")
-                .expect("Invalid progress template"),
-        );
-        pb.enable_steady_tick(Duration::from_millis(100));
-        pb.set_message("Starting scan...");
+        let progress = self.progress.as_deref();
+        if let Some(progress) = progress {
+            progress.start();
+        }
 
         // Track total scan duration
         let start_time = Instant::now();
 
-        // Shared state for progress tracking
-        let files_scanned = Arc::new(AtomicU64::new(0));
-        let dirs_scanned = Arc::new(AtomicU64::new(0));
-        let total_size = Arc::new(AtomicU64::new(0));
-        let error_count = Arc::new(AtomicU64::new(0));
-        let last_update = Arc::new(Mutex::new(Instant::now()));
-        let current_dir = Arc::new(Mutex::new(String::from("...")));
+        // jwalk reads directories on its thread pool but yields entries to a single consumer,
+        // so everything below is owned by the loop and needs no locking
+        let mut files_scanned: u64 = 0;
+        let mut dirs_scanned: u64 = 0;
+        let mut scanned_size: u64 = 0;
+        let mut largest_file: Option<(PathBuf, u64)> = None;
+        let mut histogram = self
+            .histogram_bounds
+            .as_deref()
+            .map(empty_histogram)
+            .unwrap_or_default();
+        // Hard-linked files already counted, by (device, inode)
+        let mut counted_links: HashSet<(u64, u64)> = HashSet::new();
+        let mut error_count: u64 = 0;
+        let mut errors: Vec<(PathBuf, String)> = Vec::new();
+        let mut last_update = Instant::now();
+        let progress_interval = self.progress_interval.unwrap_or(DEFAULT_PROGRESS_INTERVAL);
+        let mut current_dir = String::from("...");
 
-        // Collected entries (always collect files for size calculation)
-        let entries: Arc<Mutex<Vec<ScannedEntry>>> = Arc::new(Mutex::new(Vec::new()));
+        // Files are only kept when something needs them individually
+        let mut entries: Vec<ScannedEntry> = Vec::new();
+        let retain_files = self.include_files || self.summarize_types || self.find_duplicates;
+
+        // Archives whose contents are listed once the walk is done
+        #[cfg(feature = "archives")]
+        let mut archives: Vec<(PathBuf, usize)> = Vec::new();
+
+        // Every directory walked, looked up by path only when it is found and for the first
+        // of each run of its files; sizes are then rolled up by index rather than by path
+        let mut dirs: Vec<ScannedDir> = Vec::new();
+        let mut dir_ids: HashMap<PathBuf, usize> = HashMap::new();
+        // Siblings share their parent's path, so consecutive files reuse the last lookup
+        let mut last_parent: Option<(Arc<Path>, usize)> = None;
+
+        // Directories whose file sizes are reused from the previous scan, and the
+        // listings recorded for the next one
+        let cache_index = previous.map(ScanCache::index).unwrap_or_default();
+        let mut unchanged_dirs: HashSet<PathBuf> = HashSet::new();
+        let mut recorded_dirs: HashMap<PathBuf, CachedDir> = HashMap::new();
 
         // Configure walker
-        let num_threads = self.num_threads.unwrap_or_else(num_cpus);
-        let walker = WalkDir::new(&root)
-            .parallelism(jwalk::Parallelism::RayonNewPool(num_threads))
+        let num_threads = self
+            .num_threads
+            .filter(|&threads| threads > 0)
+            .unwrap_or_else(num_cpus);
+        let parallelism = if num_threads == 1 {
+            jwalk::Parallelism::Serial
+        } else {
+            jwalk::Parallelism::RayonNewPool(num_threads)
+        };
+        let exclude = Arc::new(self.exclude.clone());
+        let extensions = Arc::new(self.extensions.clone());
+        let include_regex = Arc::new(self.include_regex.clone());
+        let exclude_regex = Arc::new(self.exclude_regex.clone());
+        let respect_gitignore = self.respect_gitignore;
+        let use_apparent_size = self.apparent_size;
+        let block_size = self.block_size.filter(|&size| size > 0);
+        let follow_symlinks = self.follow_symlinks;
+        let skip_hidden = self.skip_hidden;
+        let root_device = if self.one_file_system {
+            root.metadata().ok().and_then(|m| device_id(&m))
+        } else {
+            None
+        };
+        let special_mounts = if self.scan_special {
+            HashSet::new()
+        } else {
+            special_mounts()
+        };
+        let visited_dirs: Arc<Mutex<HashSet<PathBuf>>> = Arc::new(Mutex::new(HashSet::new()));
+        let cycles_skipped = Arc::new(AtomicU64::new(0));
+        let cycles_skipped_clone = Arc::clone(&cycles_skipped);
+        let visited_lock_waits = Arc::new(AtomicU64::new(0));
+        let visited_lock_waits_clone = Arc::clone(&visited_lock_waits);
+        let thread_entries: Option<Arc<Vec<AtomicU64>>> = self
+            .collect_stats
+            .then(|| Arc::new((0..num_threads).map(|_| AtomicU64::new(0)).collect()));
+        let thread_entries_clone = thread_entries.clone();
+        let mount_crossings: Option<Arc<Mutex<Vec<PathBuf>>>> =
+            self.detect_mount_crossings.then(Default::default);
+        let mount_crossings_clone = mount_crossings.clone();
+        let dropped_entries: Arc<Mutex<HashMap<PathBuf, u64>>> = Default::default();
+        let dropped_entries_clone = Arc::clone(&dropped_entries);
+        let cancel = self.cancel.clone();
+        let cancel_clone = cancel.clone();
+        let listings_dropped = Arc::new(AtomicBool::new(false));
+        let listings_dropped_clone = Arc::clone(&listings_dropped);
+        let walker = WalkDirGeneric::<(GitignoreStack, ())>::new(&root)
+            .parallelism(parallelism)
+            .sort(num_threads == 1)
             .skip_hidden(false)
-            .follow_links(false);
-
-        // Clone references for the closure
-        let files_scanned_clone = Arc::clone(&files_scanned);
-        let dirs_scanned_clone = Arc::clone(&dirs_scanned);
-        let total_size_clone = Arc::clone(&total_size);
-        let error_count_clone = Arc::clone(&error_count);
-        let last_update_clone = Arc::clone(&last_update);
-        let current_dir_clone = Arc::clone(&current_dir);
-        let entries_clone = Arc::clone(&entries);
-        let pb_clone = pb.clone();
-
-        // Process entries in parallel - calculate sizes during walk
-        walker.into_iter().for_each(|entry_result| {
+            .follow_links(follow_symlinks)
+            .max_depth(self.max_scan_depth.unwrap_or(usize::MAX))
+            .process_read_dir(move |depth, dir, gitignores, children| {
+                // The root entry itself is never filtered
+                if depth.is_none() {
+                    return;
+                }
+
+                // Once cancelled, nothing more is listed or descended into
+                if is_cancelled(&cancel_clone) {
+                    if !children.is_empty() {
+                        listings_dropped_clone.store(true, Ordering::Relaxed);
+                        children.clear();
+                    }
+                    return;
+                }
+                let listed = children.len();
+
+                // Attribute the listing to the pool thread that read it
+                if let Some(counts) = &thread_entries_clone {
+                    // A serial walk runs outside any pool, on what counts as thread 0
+                    let index = rayon::current_thread_index().unwrap_or(0);
+                    if let Some(count) = counts.get(index) {
+                        count.fetch_add(children.len() as u64, Ordering::Relaxed);
+                    }
+                }
+
+                // Skip symlinked directories whose target has already been read
+                if follow_symlinks {
+                    if let Ok(canonical) = dir.canonicalize() {
+                        lock_counting(&visited_dirs, &visited_lock_waits_clone).insert(canonical);
+                    }
+                    children.retain(|child| {
+                        let is_cycle = match child {
+                            Ok(entry) if entry.path_is_symlink() && entry.file_type().is_dir() => {
+                                entry.path().canonicalize().is_ok_and(|target| {
+                                    lock_counting(&visited_dirs, &visited_lock_waits_clone)
+                                        .contains(&target)
+                                })
+                            }
+                            Err(err) => err.loop_ancestor().is_some(),
+                            Ok(_) => false,
+                        };
+                        if is_cycle {
+                            cycles_skipped_clone.fetch_add(1, Ordering::Relaxed);
+                        }
+                        !is_cycle
+                    });
+                }
+
+                // Don't cross into directories mounted from another filesystem
+                if let Some(root_device) = root_device {
+                    children.retain(|child| match child {
+                        Ok(entry) if entry.file_type().is_dir() => entry
+                            .metadata()
+                            .ok()
+                            .and_then(|m| device_id(&m))
+                            .is_none_or(|device| device == root_device),
+                        _ => true,
+                    });
+                }
+
+                // Don't descend into procfs, sysfs and friends
+                if !special_mounts.is_empty() {
+                    children.retain(|child| match child {
+                        Ok(entry) if entry.file_type().is_dir() => {
+                            !special_mounts.contains(&entry.path())
+                        }
+                        _ => true,
+                    });
+                }
+
+                // Matchers pushed here are inherited by subdirectories
+                if respect_gitignore {
+                    let gitignore_path = dir.join(".gitignore");
+                    if gitignore_path.is_file() {
+                        let (gitignore, _) = Gitignore::new(gitignore_path);
+                        gitignores.push(Arc::new(gitignore));
+                    }
+                }
+
+                // Dropping an entry here also prevents descending into it
+                children.retain(|child| match child {
+                    Ok(entry) => {
+                        let path = entry.path();
+                        let is_dir = entry.file_type().is_dir();
+                        !(skip_hidden && is_hidden(&path))
+                            && (is_dir || has_extension(&path, &extensions))
+                            && (is_dir
+                                || include_regex.is_empty()
+                                || matches_regex(&path, &include_regex))
+                            && !matches_glob(&path, &exclude)
+                            && !matches_regex(&path, &exclude_regex)
+                            && !is_gitignored(&path, is_dir, gitignores)
+                            && filter(&path, is_dir)
+                    }
+                    Err(_) => true,
+                });
+
+                // Entries left out here, or that failed to read, still keep the directory
+                // from counting as empty
+                let dropped = listed - children.iter().flatten().count();
+                if dropped > 0 {
+                    dropped_entries_clone
+                        .lock()
+                        .unwrap()
+                        .insert(dir.to_path_buf(), dropped as u64);
+                }
+
+                // Note subdirectories that live on another filesystem than this one
+                if let Some(crossings) = &mount_crossings_clone {
+                    let device = dir.metadata().ok().and_then(|m| device_id(&m));
+                    if let Some(device) = device {
+                        for child in children.iter().flatten() {
+                            let crosses = child.file_type().is_dir()
+                                && child
+                                    .metadata()
+                                    .ok()
+                                    .and_then(|m| device_id(&m))
+                                    .is_some_and(|child_device| child_device != device);
+                            if crosses {
+                                crossings.lock().unwrap().push(child.path());
+                            }
+                        }
+                    }
+                }
+            });
+
+        // Calculate sizes as entries arrive from the walk; the scan counts as interrupted
+        // only if cancelling actually left entries out
+        let mut interrupted = false;
+        for entry_result in walker {
+            if is_cancelled(&cancel) {
+                interrupted = true;
+                break;
+            }
             match entry_result {
                 Ok(entry) => {
                     let path = entry.path();
@@ -102,286 +738,2126 @@ impl Scanner {
                     let depth = entry.depth();
 
                     if is_dir {
-                        dirs_scanned_clone.fetch_add(1, Ordering::Relaxed);
+                        dirs_scanned += 1;
 
-                        // Update current directory being scanned
-                        if let Ok(mut dir) = current_dir_clone.try_lock() {
-                            if let Some(name) = path.file_name() {
-                                *dir = name.to_string_lossy().to_string();
+                        // jwalk attaches a failure to list a directory to the directory itself
+                        if let Some(err) = &entry.read_children_error {
+                            error_count += 1;
+                            if errors.len() < MAX_RECORDED_ERRORS {
+                                errors.push((path.clone(), error_message(err)));
                             }
                         }
 
-                        // Add directory entry (size will be calculated later)
-                        if depth > 0 {
-                            if let Ok(mut entries) = entries_clone.try_lock() {
-                                entries.push(ScannedEntry {
-                                    path: path.clone(),
-                                    size: 0,
-                                    is_dir: true,
-                                    depth,
-                                });
-                            }
+                        // Update current directory being scanned
+                        if let Some(name) = path.file_name() {
+                            current_dir = name.to_string_lossy().to_string();
+                        }
+
+                        if record_cache {
+                            record_dir(
+                                &entry,
+                                &cache_index,
+                                &mut unchanged_dirs,
+                                &mut recorded_dirs,
+                            );
+                        }
+
+                        // Register the directory (its size is calculated later); the root is
+                        // kept for its totals even when it isn't reported
+                        let parent = match depth {
+                            0 => None,
+                            _ => dir_ids.get(entry.parent_path()).copied(),
+                        };
+                        if let Some(parent) = parent {
+                            dirs[parent].entry_count = dirs[parent].entry_count.map(|n| n + 1);
                         }
+                        // The walker doesn't read directories at the depth limit
+                        let listed = entry.read_children_error.is_none()
+                            && self.max_scan_depth.is_none_or(|max| depth < max);
+                        dir_ids.insert(path.clone(), dirs.len());
+                        dirs.push(ScannedDir {
+                            path: path.clone(),
+                            depth,
+                            parent,
+                            totals: DirTotals::default(),
+                            accessible: entry.read_children_error.is_none(),
+                            entry_count: listed.then_some(0),
+                        });
                     } else {
-                        files_scanned_clone.fetch_add(1, Ordering::Relaxed);
+                        files_scanned += 1;
+
+                        // Get file sizes immediately, from the cache if the directory is unchanged
+                        let parent = entry.parent_path();
+                        let name = entry.file_name().to_str();
+                        let cached = name
+                            .filter(|_| !self.count_links_once && unchanged_dirs.contains(parent))
+                            .and_then(|name| cache_index.file(parent, name));
+                        let info = match cached {
+                            Some(file) => FileInfo {
+                                disk_size: file.disk_size,
+                                apparent_size: file.apparent_size,
+                                modified: file.modified,
+                                link_id: None,
+                            },
+                            None => get_file_info(&path, follow_symlinks).unwrap_or_default(),
+                        };
+                        let FileInfo {
+                            mut disk_size,
+                            mut apparent_size,
+                            modified,
+                            link_id,
+                        } = info;
+                        if let (Some(dir), Some(name)) = (recorded_dirs.get_mut(parent), name) {
+                            dir.files.push(CachedFile {
+                                name: name.to_string(),
+                                disk_size,
+                                apparent_size,
+                                modified,
+                            });
+                        }
+
+                        // Further links to an inode already counted add nothing
+                        if self.count_links_once
+                            && link_id.is_some_and(|id| !counted_links.insert(id))
+                        {
+                            disk_size = 0;
+                            apparent_size = 0;
+                        }
+
+                        let size = if let Some(block_size) = block_size {
+                            apparent_size.div_ceil(block_size) * block_size
+                        } else if use_apparent_size {
+                            apparent_size
+                        } else {
+                            disk_size
+                        };
+                        scanned_size += size;
+                        let is_largest =
+                            largest_file.as_ref().is_none_or(|(largest, largest_size)| {
+                                size > *largest_size || (size == *largest_size && path < *largest)
+                            });
+                        if is_largest {
+                            largest_file = Some((path.clone(), size));
+                        }
+                        if let Some(bounds) = &self.histogram_bounds {
+                            let bucket = &mut histogram[bounds.partition_point(|&b| b <= size)];
+                            bucket.file_count += 1;
+                            bucket.size += size;
+                        }
+
+                        if let (Some(progress), Some(min_size)) = (progress, self.stream_min_size) {
+                            if size >= min_size {
+                                progress.file_found(&path, size);
+                            }
+                        }
+
+                        #[cfg(feature = "archives")]
+                        if self.into_archives && crate::archive::is_archive(&path) {
+                            archives.push((path.clone(), depth));
+                        }
 
-                        // Get file size immediately
-                        let size = get_file_size(&path).unwrap_or(0);
-                        total_size_clone.fetch_add(size, Ordering::Relaxed);
+                        let parent_id = match &last_parent {
+                            Some((last, id)) if Arc::ptr_eq(last, &entry.parent_path) => Some(*id),
+                            _ => dir_ids.get(parent).copied().inspect(|&id| {
+                                last_parent = Some((entry.parent_path.clone(), id));
+                            }),
+                        };
+                        if let Some(id) = parent_id {
+                            dirs[id].totals.add_file(&DirTotals::file(
+                                size,
+                                apparent_size,
+                                modified,
+                            ));
+                            dirs[id].entry_count = dirs[id].entry_count.map(|n| n + 1);
+                        }
 
-                        // Always add file entry (needed for directory size calculation)
-                        if let Ok(mut entries) = entries_clone.try_lock() {
+                        if retain_files || matches_glob(&path, &self.include_files_matching) {
                             entries.push(ScannedEntry {
                                 path: path.clone(),
                                 size,
-                                is_dir: false,
+                                apparent_size,
+                                modified,
                                 depth,
                             });
                         }
                     }
 
-                    // Update progress display (throttled to avoid flickering)
-                    if let Ok(mut last) = last_update_clone.try_lock() {
-                        if last.elapsed() >= Duration::from_millis(50) {
-                            *last = Instant::now();
-                            let files = files_scanned_clone.load(Ordering::Relaxed);
-                            let dirs = dirs_scanned_clone.load(Ordering::Relaxed);
-                            let size = total_size_clone.load(Ordering::Relaxed);
-                            let dir_name = current_dir_clone
-                                .lock()
-                                .map(|d| d.clone())
-                                .unwrap_or_default();
-
-                            pb_clone.set_message(format!(
-                                "Scanning: {} | {} files, {} dirs | {}",
-                                truncate_str(&dir_name, 20),
-                                format_number(files),
-                                format_number(dirs),
-                                format_size_simple(size)
-                            ));
+                    // Report progress (throttled to avoid flickering)
+                    if let Some(progress) = progress {
+                        if last_update.elapsed() >= progress_interval {
+                            last_update = Instant::now();
+                            progress.scanning(
+                                &current_dir,
+                                files_scanned,
+                                dirs_scanned,
+                                scanned_size,
+                            );
                         }
                     }
                 }
-                Err(_) => {
-                    error_count_clone.fetch_add(1, Ordering::Relaxed);
+                Err(err) => {
+                    error_count += 1;
+                    if errors.len() < MAX_RECORDED_ERRORS {
+                        let path = err.path().map(Path::to_path_buf).unwrap_or_default();
+                        errors.push((path, error_message(&err)));
+                    }
                 }
             }
-        });
-
-        let file_count = files_scanned.load(Ordering::Relaxed);
-        let dir_count = dirs_scanned.load(Ordering::Relaxed);
-        let scanned_size = total_size.load(Ordering::Relaxed);
-
-        pb.set_message(format!(
-            "Calculating directory sizes... ({} files, {})",
-            format_number(file_count),
-            format_size_simple(scanned_size)
-        ));
-
-        // Now calculate directory sizes by aggregating from entries
-        let mut dir_sizes: HashMap<PathBuf, u64> = HashMap::new();
-        let all_entries = entries.lock().unwrap();
-
-        // Initialize all directories
-        for entry in all_entries.iter() {
-            if entry.is_dir {
-                dir_sizes.insert(entry.path.clone(), 0);
+        }
+
+        // Entries the walker left out count towards their directory too
+        for (path, dropped) in std::mem::take(&mut *dropped_entries.lock().unwrap()) {
+            if let Some(&id) = dir_ids.get(&path) {
+                dirs[id].entry_count = dirs[id].entry_count.map(|n| n + dropped);
             }
         }
 
-        // Add file sizes to parent directories
-        let file_entries: Vec<_> = all_entries.iter().filter(|e| !e.is_dir).collect();
-        let total_files = file_entries.len();
+        let walk_duration = start_time.elapsed();
+        let mut timer = PhaseTimer::new(self.profile, start_time);
+        timer.lap("walk");
 
-        for (idx, entry) in file_entries.iter().enumerate() {
-            // Update progress for directory calculation
-            if idx % 50000 == 0 && total_files > 0 {
-                pb.set_message(format!(
-                    "Aggregating sizes... {:.0}%",
-                    (idx as f64 / total_files as f64) * 100.0
-                ));
+        // Read archive listings in parallel; a broken archive is an error on its own path
+        #[cfg(feature = "archives")]
+        let mut archive_nodes = Vec::new();
+        #[cfg(feature = "archives")]
+        if !archives.is_empty() {
+            if let Some(progress) = progress {
+                progress.phase("Listing archive contents...");
             }
-
-            // Propagate size up to all parent directories
-            let mut current = entry.path.parent();
-            while let Some(parent) = current {
-                if let Some(dir_size) = dir_sizes.get_mut(parent) {
-                    *dir_size += entry.size;
-                }
-                if parent == root {
-                    break;
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .context("Failed to create archive thread pool")?;
+            let listings: Vec<_> = pool.install(|| {
+                archives
+                    .par_iter()
+                    .map(|(path, depth)| {
+                        crate::archive::list_entries(path).map(|entries| {
+                            crate::archive::virtual_nodes(
+                                path,
+                                *depth,
+                                &entries,
+                                self.include_files,
+                            )
+                        })
+                    })
+                    .collect()
+            });
+            for ((path, _), listing) in archives.into_iter().zip(listings) {
+                match listing {
+                    Ok(listed) => archive_nodes.extend(listed),
+                    Err(err) => {
+                        error_count += 1;
+                        if errors.len() < MAX_RECORDED_ERRORS {
+                            errors.push((path, err.root_cause().to_string()));
+                        }
+                    }
                 }
-                current = parent.parent();
+            }
+            timer.lap("archives");
+        }
+        #[cfg(not(feature = "archives"))]
+        let archive_nodes: Vec<Node> = Vec::new();
+
+        if let Some(progress) = progress {
+            progress.phase("Calculating directory sizes...");
+        }
+
+        // Roll totals up bottom-up: children come after their parent, so walking the list
+        // backwards completes each directory before it is added to its parent
+        for id in (0..dirs.len()).rev() {
+            if let Some(parent) = dirs[id].parent {
+                let totals = dirs[id].totals;
+                dirs[parent].totals.add(&totals);
             }
         }
 
-        pb.set_message("Building results...");
+        timer.lap("aggregate");
+
+        if let Some(progress) = progress {
+            progress.phase("Building results...");
+        }
 
         // Build the result
         let mut result = ScanResult::new();
-        result.file_count = file_count;
-        result.dir_count = dir_count.saturating_sub(1); // Exclude root
+        result.file_count = files_scanned;
+        result.dir_count = dirs_scanned.saturating_sub(1); // Exclude root
         result.total_size = scanned_size;
-        result.error_count = error_count.load(Ordering::Relaxed);
-
-        // Add directories with their calculated sizes
-        for (path, size) in dir_sizes {
-            let depth = path
-                .strip_prefix(&root)
-                .map(|p| p.components().count())
-                .unwrap_or(0);
-            result.nodes.push(Node::new(path, size, true, depth));
-        }
-
-        // Add files if requested
-        if self.include_files {
-            for entry in all_entries.iter() {
-                if !entry.is_dir {
-                    result.nodes.push(Node::new(
-                        entry.path.clone(),
-                        entry.size,
-                        false,
-                        entry.depth,
-                    ));
-                }
+        result.largest_file = largest_file;
+        result.histogram = histogram;
+        result.error_count = error_count;
+        result.errors = errors;
+        result.cycles_skipped = cycles_skipped.load(Ordering::Relaxed);
+        result.cached_dirs = unchanged_dirs.len() as u64;
+        result.max_scan_depth = self.max_scan_depth;
+        result.interrupted = interrupted || listings_dropped.load(Ordering::Relaxed);
+        if let Some(crossings) = mount_crossings {
+            let mut crossings = std::mem::take(&mut *crossings.lock().unwrap());
+            crossings.sort();
+            result.mount_crossings = crossings;
+        }
+        if self.disk_info {
+            // Some filesystems can't report capacity; the summary just omits it
+            result.disk = fs4::statvfs(&root).ok().map(|stats| DiskInfo {
+                total: stats.total_space(),
+                free: stats.free_space(),
+                available: stats.available_space(),
+            });
+        }
+
+        // Directories with their calculated sizes, then files if requested
+        let dirs = dirs
+            .into_iter()
+            .filter(|dir| dir.depth > 0 || self.include_root)
+            .map(|dir| {
+                Node::new(dir.path, dir.totals.size, true, dir.depth)
+                    .with_self_size(dir.totals.self_size)
+                    .with_apparent_size(dir.totals.apparent_size)
+                    .with_file_count(dir.totals.file_count)
+                    .with_modified(dir.totals.newest)
+                    .with_accessible(dir.accessible)
+                    .with_entry_count(dir.entry_count)
+            });
+        let files = entries
+            .iter()
+            .filter(|entry| {
+                self.include_files || matches_glob(&entry.path, &self.include_files_matching)
+            })
+            .map(|entry| {
+                Node::new(entry.path.clone(), entry.size, false, entry.depth)
+                    .with_apparent_size(entry.apparent_size)
+                    .with_modified(entry.modified)
+            });
+        let nodes = dirs.chain(files).chain(archive_nodes);
+        result.nodes = match self.max_results.filter(|&max| max > 0) {
+            Some(max) => largest_nodes(nodes, max),
+            None => nodes.collect(),
+        };
+        timer.lap("build results");
+
+        // Group files by extension if requested
+        if self.summarize_types {
+            result.types = summarize_by_type(entries.iter());
+            timer.lap("types");
+        }
+
+        // Hash same-sized files to confirm duplicates if requested
+        if self.find_duplicates {
+            if let Some(progress) = progress {
+                progress.phase("Hashing duplicate candidates...");
             }
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .context("Failed to create hashing thread pool")?;
+            result.duplicates = pool.install(|| find_duplicates(entries.iter()));
+            timer.lap("duplicates");
         }
+        result.profile = timer.finish();
 
         let duration = start_time.elapsed();
-        pb.finish_with_message(format!(
-            "Done! {} files, {} dirs ({}) in {}",
-            format_number(result.file_count),
-            format_number(result.dir_count),
-            format_size_simple(result.total_size),
-            format_duration(duration)
-        ));
+        result.scan_duration_ms = duration.as_millis() as u64;
+        if let Some(counts) = thread_entries {
+            result.stats = Some(ScanStats {
+                threads: num_threads,
+                entries_per_thread: counts.iter().map(|c| c.load(Ordering::Relaxed)).collect(),
+                visited_lock_waits: follow_symlinks
+                    .then(|| visited_lock_waits.load(Ordering::Relaxed)),
+                walk_ms: walk_duration.as_millis() as u64,
+                aggregate_ms: duration.saturating_sub(walk_duration).as_millis() as u64,
+            });
+        }
+        if let Some(progress) = progress {
+            progress.finish(&result, duration);
+        }
 
-        Ok(result)
+        let mut cache = ScanCache::new(self.cache_options());
+        cache.dirs = recorded_dirs.into_values().collect();
+        Ok((result, cache))
     }
 }
 
-/// Get the size of a file on disk
-fn get_file_size(path: &Path) -> Option<u64> {
-    path.size_on_disk()
-        .ok()
-        .or_else(|| path.metadata().ok().map(|m| m.len()))
+/// Times consecutive phases of a scan when profiling is enabled
+struct PhaseTimer {
+    profile: Option<Profile>,
+    last: Instant,
 }
 
-/// Get the number of CPU cores
-fn num_cpus() -> usize {
-    std::thread::available_parallelism()
-        .map(|n| n.get())
-        .unwrap_or(4)
-}
+impl PhaseTimer {
+    /// Start timing from `start`, or do nothing if `enabled` is false
+    fn new(enabled: bool, start: Instant) -> Self {
+        Self {
+            profile: enabled.then(Profile::default),
+            last: start,
+        }
+    }
 
-/// Format a number with thousand separators
-fn format_number(n: u64) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    for (i, c) in s.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 {
-            result.push(',');
+    /// Attribute the time since the previous lap to `phase`
+    fn lap(&mut self, phase: &'static str) {
+        if let Some(profile) = &mut self.profile {
+            let now = Instant::now();
+            profile.add(phase, now - self.last);
+            self.last = now;
         }
-        result.push(c);
     }
-    result.chars().rev().collect()
-}
 
-/// Truncate a string to max length with ellipsis
-fn truncate_str(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+    /// The recorded phases, if profiling
+    fn finish(self) -> Option<Profile> {
+        self.profile
     }
 }
 
-/// Format a duration in human-readable form
-fn format_duration(duration: Duration) -> String {
-    let secs = duration.as_secs();
-    let millis = duration.subsec_millis();
+/// Caller-supplied predicate on an entry's path and whether it is a directory
+type EntryFilter = Arc<dyn Fn(&Path, bool) -> bool + Send + Sync>;
 
-    if secs >= 60 {
-        let mins = secs / 60;
-        let remaining_secs = secs % 60;
-        format!("{}m {}s", mins, remaining_secs)
-    } else if secs > 0 {
-        format!("{}.{:02}s", secs, millis / 10)
-    } else {
-        format!("{}ms", millis)
+/// Node ordered by size, with ties broken by path so the kept set is deterministic
+struct BySize(Node);
+
+impl Ord for BySize {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.0
+            .size
+            .cmp(&other.0.size)
+            .then_with(|| other.0.path.cmp(&self.0.path))
     }
 }
 
-/// Simple size formatting for progress messages
-fn format_size_simple(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-    const TB: u64 = GB * 1024;
+impl PartialOrd for BySize {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
 
-    if bytes >= TB {
-        format!("{:.2} TB", bytes as f64 / TB as f64)
-    } else if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.1} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.1} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} B", bytes)
+impl PartialEq for BySize {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::tempdir;
+impl Eq for BySize {}
 
-    #[test]
-    fn test_scan_empty_dir() {
-        let dir = tempdir().unwrap();
-        let scanner = Scanner::new();
-        let result = scanner.scan(dir.path()).unwrap();
-        assert_eq!(result.file_count, 0);
+/// The `max` largest nodes, largest first, keeping at most `max` in memory at once
+fn largest_nodes(nodes: impl Iterator<Item = Node>, max: usize) -> Vec<Node> {
+    // Min-heap of the largest nodes seen so far; the smallest is evicted when it overflows
+    let mut heap = BinaryHeap::with_capacity(max + 1);
+    for node in nodes {
+        heap.push(Reverse(BySize(node)));
+        if heap.len() > max {
+            heap.pop();
+        }
     }
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(BySize(node))| node)
+        .collect()
+}
 
-    #[test]
-    fn test_scan_with_files() {
-        let dir = tempdir().unwrap();
+/// Message for a walk error, without the path jwalk prefixes to I/O errors
+fn error_message(err: &jwalk::Error) -> String {
+    err.io_error()
+        .map_or_else(|| err.to_string(), |io| io.to_string())
+}
 
-        fs::write(dir.path().join("small.txt"), "hello").unwrap();
-        fs::write(dir.path().join("large.txt"), "x".repeat(1000)).unwrap();
-        fs::create_dir(dir.path().join("subdir")).unwrap();
-        fs::write(dir.path().join("subdir/nested.txt"), "nested content").unwrap();
+/// Record a directory's modification time, noting whether it matches the cached listing
+fn record_dir(
+    entry: &jwalk::DirEntry<(GitignoreStack, ())>,
+    cache_index: &CacheIndex,
+    unchanged_dirs: &mut HashSet<PathBuf>,
+    recorded_dirs: &mut HashMap<PathBuf, CachedDir>,
+) {
+    let Some(modified) = entry.metadata().ok().and_then(|m| m.modified().ok()) else {
+        return;
+    };
 
-        let scanner = Scanner::new().include_files(true);
-        let result = scanner.scan(dir.path()).unwrap();
+    let path = entry.path();
+    if cache_index.is_unchanged(&path, modified) {
+        unchanged_dirs.insert(path.clone());
+    }
+    recorded_dirs.insert(
+        path.clone(),
+        CachedDir {
+            path,
+            modified,
+            files: Vec::new(),
+        },
+    );
+}
 
-        assert_eq!(result.file_count, 3);
-        assert!(result.total_size > 0);
+/// Check whether a path matches any of the patterns, by file name or full path
+fn matches_glob(path: &Path, patterns: &[Pattern]) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy());
+    patterns.iter().any(|pattern| {
+        name.as_deref().is_some_and(|n| pattern.matches(n)) || pattern.matches_path(path)
+    })
+}
+
+/// Check whether a full path matches any of the regexes
+fn matches_regex(path: &Path, patterns: &[Regex]) -> bool {
+    if patterns.is_empty() {
+        return false;
     }
+    let path = path.to_string_lossy();
+    patterns.iter().any(|re| re.is_match(&path))
+}
 
-    #[test]
-    fn test_format_size_simple() {
-        assert_eq!(format_size_simple(500), "500 B");
-        assert_eq!(format_size_simple(1024), "1.0 KB");
-        assert_eq!(format_size_simple(1536), "1.5 KB");
-        assert_eq!(format_size_simple(1048576), "1.0 MB");
-        assert_eq!(format_size_simple(1073741824), "1.00 GB");
+/// Aggregate file sizes and counts per lowercase extension, largest total first
+fn summarize_by_type<'a>(files: impl Iterator<Item = &'a ScannedEntry>) -> Vec<TypeSummary> {
+    let mut by_type: HashMap<Option<String>, (u64, u64)> = HashMap::new();
+    for file in files {
+        let extension = file
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        let (size, count) = by_type.entry(extension).or_default();
+        *size += file.size;
+        *count += 1;
     }
 
-    #[test]
-    fn test_format_number() {
-        assert_eq!(format_number(0), "0");
-        assert_eq!(format_number(999), "999");
-        assert_eq!(format_number(1000), "1,000");
-        assert_eq!(format_number(1234567), "1,234,567");
+    let mut types: Vec<TypeSummary> = by_type
+        .into_iter()
+        .map(|(extension, (size, file_count))| TypeSummary {
+            extension,
+            size,
+            file_count,
+        })
+        .collect();
+    types.sort_by_key(|t| std::cmp::Reverse(t.size));
+    types
+}
+
+/// Empty buckets for the ranges below, between and above increasing `bounds`
+fn empty_histogram(bounds: &[u64]) -> Vec<SizeBucket> {
+    let mins = std::iter::once(0).chain(bounds.iter().copied());
+    let maxes = bounds
+        .iter()
+        .copied()
+        .map(Some)
+        .chain(std::iter::once(None));
+    mins.zip(maxes)
+        .map(|(min, max)| SizeBucket {
+            min,
+            max,
+            file_count: 0,
+            size: 0,
+        })
+        .collect()
+}
+
+/// Group files with identical content, hashing only files that share a size
+fn find_duplicates<'a>(files: impl Iterator<Item = &'a ScannedEntry>) -> Vec<DuplicateGroup> {
+    // Group by apparent size first; empty files are never worth reporting
+    let mut by_size: HashMap<u64, Vec<&ScannedEntry>> = HashMap::new();
+    for file in files.filter(|f| f.apparent_size > 0) {
+        by_size.entry(file.apparent_size).or_default().push(file);
+    }
+    let candidates: Vec<&ScannedEntry> = by_size
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .flatten()
+        .collect();
+
+    // Hash candidates in parallel; unreadable files are skipped
+    let hashed: Vec<(&ScannedEntry, blake3::Hash)> = candidates
+        .into_par_iter()
+        .filter_map(|file| hash_file(&file.path).map(|hash| (file, hash)))
+        .collect();
+
+    let mut by_content: HashMap<(u64, blake3::Hash), Vec<&ScannedEntry>> = HashMap::new();
+    for (file, hash) in hashed {
+        by_content
+            .entry((file.apparent_size, hash))
+            .or_default()
+            .push(file);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_content
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let mut paths: Vec<PathBuf> = group.iter().map(|f| f.path.clone()).collect();
+            paths.sort();
+            DuplicateGroup {
+                size: group[0].size,
+                paths,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.wasted_size()));
+    groups
+}
+
+/// Hash a file's contents
+fn hash_file(path: &Path) -> Option<blake3::Hash> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(file).ok()?;
+    Some(hasher.finalize())
+}
+
+/// Whether a cancellation flag has been set
+fn is_cancelled(flag: &Option<Arc<AtomicBool>>) -> bool {
+    flag.as_ref()
+        .is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
+
+/// Lock a mutex, counting the times it was already held by another thread
+fn lock_counting<'a, T>(mutex: &'a Mutex<T>, contention: &AtomicU64) -> MutexGuard<'a, T> {
+    match mutex.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::WouldBlock) => {
+            contention.fetch_add(1, Ordering::Relaxed);
+            mutex.lock().unwrap()
+        }
+        Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+    }
+}
+
+/// Identifier of the filesystem an entry lives on, where the platform exposes one
+#[cfg(unix)]
+fn device_id(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+/// Identifier of the filesystem an entry lives on, where the platform exposes one
+#[cfg(not(unix))]
+fn device_id(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Filesystem types that expose kernel state rather than stored files
+#[cfg(target_os = "linux")]
+const SPECIAL_FILESYSTEMS: &[&str] = &[
+    "proc",
+    "sysfs",
+    "devtmpfs",
+    "devpts",
+    "cgroup",
+    "cgroup2",
+    "securityfs",
+    "debugfs",
+    "tracefs",
+    "pstore",
+    "bpf",
+    "configfs",
+    "fusectl",
+    "mqueue",
+    "hugetlbfs",
+    "efivarfs",
+    "binfmt_misc",
+    "rpc_pipefs",
+    "nsfs",
+];
+
+/// Mount points of pseudo-filesystems, which scans skip unless `scan_special` is set
+#[cfg(target_os = "linux")]
+fn special_mounts() -> HashSet<PathBuf> {
+    std::fs::read_to_string("/proc/self/mounts")
+        .map(|mounts| parse_special_mounts(&mounts))
+        .unwrap_or_default()
+}
+
+/// Mount points of pseudo-filesystems; other platforms have none worth skipping
+#[cfg(not(target_os = "linux"))]
+fn special_mounts() -> HashSet<PathBuf> {
+    HashSet::new()
+}
+
+/// Mount points with a special filesystem type in the `/proc/mounts` format
+/// (`device mount_point type options dump pass`, with spaces escaped as `\040`)
+#[cfg(target_os = "linux")]
+fn parse_special_mounts(mounts: &str) -> HashSet<PathBuf> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+            SPECIAL_FILESYSTEMS
+                .contains(&fs_type)
+                .then(|| PathBuf::from(unescape_mount_path(mount_point)))
+        })
+        .collect()
+}
+
+/// Decode the octal escapes (`\040` for a space) used for mount points in `/proc/mounts`
+#[cfg(target_os = "linux")]
+fn unescape_mount_path(path: &str) -> String {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let code = rest
+            .get(index + 1..index + 4)
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Extended-length form of an absolute path (`\\?\C:\...` or `\\?\UNC\server\share\...`),
+/// which Windows APIs accept beyond the legacy 260-character `MAX_PATH` limit.
+///
+/// Walked paths are usually verbatim already, since the root is canonicalized; paths that
+/// are relative, already verbatim, or contain `.`/`..` (which verbatim paths don't resolve)
+/// are returned unchanged.
+#[cfg(windows)]
+fn extended_length_path(path: &Path) -> std::borrow::Cow<'_, Path> {
+    use std::borrow::Cow;
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    if !path.is_absolute() {
+        return Cow::Borrowed(path);
+    }
+    let mut components = path.components();
+    let mut extended = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(letter) => PathBuf::from(format!(r"\\?\{}:\", letter as char)),
+            Prefix::UNC(server, share) => {
+                let mut unc = OsString::from(r"\\?\UNC\");
+                unc.push(server);
+                unc.push(r"\");
+                unc.push(share);
+                unc.push(r"\");
+                PathBuf::from(unc)
+            }
+            _ => return Cow::Borrowed(path),
+        },
+        _ => return Cow::Borrowed(path),
+    };
+    for component in components {
+        match component {
+            Component::RootDir => {}
+            Component::Normal(name) => extended.push(name),
+            _ => return Cow::Borrowed(path),
+        }
+    }
+    Cow::Owned(extended)
+}
+
+/// Check whether an entry is hidden: a dotfile, or on Windows, flagged with the hidden attribute
+fn is_hidden(path: &Path) -> bool {
+    if path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = path.symlink_metadata() {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+
+    false
+}
+
+/// Check whether a file has one of the wanted extensions (always true when none are set)
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    extensions.is_empty()
+        || path
+            .extension()
+            .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase()))
+}
+
+/// Check whether a path is ignored, letting the innermost `.gitignore` with a match decide
+fn is_gitignored(path: &Path, is_dir: bool, gitignores: &[Arc<Gitignore>]) -> bool {
+    for gitignore in gitignores.iter().rev() {
+        match gitignore.matched(path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+    false
+}
+
+/// Get the size of a file on disk, its apparent (logical) size, its modification time and,
+/// if it has several hard links, its identity.
+///
+/// Symlinks are measured themselves unless `follow_symlinks` is set.
+fn get_file_info(path: &Path, follow_symlinks: bool) -> Option<FileInfo> {
+    let metadata = if follow_symlinks {
+        path.metadata()
+    } else {
+        path.symlink_metadata()
+    }
+    .ok()?;
+    let apparent_size = metadata.len();
+    // Unlike std, GetCompressedFileSizeW doesn't add the extended-length prefix itself
+    #[cfg(windows)]
+    let path = extended_length_path(path);
+    let disk_size = path.size_on_disk_fast(&metadata).unwrap_or(apparent_size);
+    Some(FileInfo {
+        disk_size,
+        apparent_size,
+        modified: metadata.modified().ok(),
+        link_id: link_id(&metadata),
+    })
+}
+
+/// `(device, inode)` of a file with more than one hard link
+#[cfg(unix)]
+fn link_id(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+/// `(device, inode)` of a file with more than one hard link
+#[cfg(not(unix))]
+fn link_id(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Get the number of CPU cores
+fn num_cpus() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scan_empty_dir() {
+        let dir = tempdir().unwrap();
+        let scanner = Scanner::new();
+        let result = scanner.scan(dir.path()).unwrap();
+        assert_eq!(result.file_count, 0);
+    }
+
+    #[test]
+    fn test_scan_with_files() {
+        let dir = tempdir().unwrap();
+
+        fs::write(dir.path().join("small.txt"), "hello").unwrap();
+        fs::write(dir.path().join("large.txt"), "x".repeat(1000)).unwrap();
+        fs::create_dir(dir.path().join("subdir")).unwrap();
+        fs::write(dir.path().join("subdir/nested.txt"), "nested content").unwrap();
+
+        let scanner = Scanner::new().include_files(true);
+        let result = scanner.scan(dir.path()).unwrap();
+
+        assert_eq!(result.file_count, 3);
+        assert!(result.total_size > 0);
+
+        let subdir = result.nodes.iter().find(|n| n.is_dir).unwrap();
+        assert_eq!(subdir.file_count, 1);
+    }
+
+    #[test]
+    fn test_largest_file() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("top.txt"), "x".repeat(2000)).unwrap();
+        fs::write(dir.path().join("a/mid.txt"), "x".repeat(500)).unwrap();
+        fs::write(dir.path().join("a/b/deep.bin"), "x".repeat(7000)).unwrap();
+        fs::create_dir(dir.path().join("empty")).unwrap();
+
+        // Tracked without keeping individual files
+        let result = Scanner::new().apparent_size(true).scan(dir.path()).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(result.largest_file, Some((root.join("a/b/deep.bin"), 7000)));
+
+        let empty = Scanner::new().scan(&dir.path().join("empty")).unwrap();
+        assert_eq!(empty.largest_file, None);
+    }
+
+    #[test]
+    fn test_nested_totals_roll_up() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        fs::write(dir.path().join("a/one.txt"), "1").unwrap();
+        fs::write(dir.path().join("a/b/two.txt"), "22").unwrap();
+        fs::write(dir.path().join("a/b/c/three.txt"), "333").unwrap();
+
+        let result = Scanner::new().apparent_size(true).scan(dir.path()).unwrap();
+        let totals = |name: &str| {
+            let node = result
+                .nodes
+                .iter()
+                .find(|n| n.path.ends_with(name))
+                .unwrap();
+            (node.size, node.file_count)
+        };
+
+        assert_eq!(totals("a"), (6, 3));
+        assert_eq!(totals("a/b"), (5, 2));
+        assert_eq!(totals("a/b/c"), (3, 1));
+    }
+
+    #[test]
+    fn test_parallel_scan_records_every_entry() {
+        let dir = tempdir().unwrap();
+        for d in 0..20 {
+            let sub = dir.path().join(format!("dir{d}"));
+            fs::create_dir(&sub).unwrap();
+            for f in 0..50 {
+                fs::write(sub.join(format!("file{f}.txt")), "x").unwrap();
+            }
+        }
+
+        let result = Scanner::new()
+            .with_threads(Some(8))
+            .include_files(true)
+            .apparent_size(true)
+            .scan(dir.path())
+            .unwrap();
+
+        assert_eq!(result.file_count, 1000);
+        assert_eq!(result.nodes.iter().filter(|n| !n.is_dir).count(), 1000);
+        assert_eq!(result.nodes.iter().filter(|n| n.is_dir).count(), 20);
+        assert!(
+            result
+                .nodes
+                .iter()
+                .filter(|n| n.is_dir)
+                .all(|n| n.size == 50)
+        );
+    }
+
+    #[test]
+    fn test_repeated_scans_are_deterministic() {
+        let dir = tempdir().unwrap();
+        for d in 0..10 {
+            let sub = dir.path().join(format!("dir{d}/nested"));
+            fs::create_dir_all(&sub).unwrap();
+            for f in 0..30 {
+                fs::write(sub.join(format!("file{f}.bin")), "x".repeat(f * 100)).unwrap();
+            }
+        }
+
+        let scan = || {
+            let result = Scanner::new()
+                .with_threads(Some(8))
+                .include_files(true)
+                .scan(dir.path())
+                .unwrap();
+            (
+                result.file_count,
+                result.dir_count,
+                result.total_size,
+                result.nodes.len(),
+            )
+        };
+
+        let first = scan();
+        assert_eq!(first.0, 300);
+        for _ in 0..5 {
+            assert_eq!(scan(), first);
+        }
+    }
+
+    #[test]
+    fn test_totals_without_retained_files() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("root.txt"), "x".repeat(10)).unwrap();
+        fs::write(dir.path().join("a/one.txt"), "x".repeat(100)).unwrap();
+        fs::write(dir.path().join("a/b/two.txt"), "x".repeat(1000)).unwrap();
+
+        let dirs_only = Scanner::new().apparent_size(true).scan(dir.path()).unwrap();
+        let with_files = Scanner::new()
+            .apparent_size(true)
+            .include_files(true)
+            .scan(dir.path())
+            .unwrap();
+
+        assert!(dirs_only.nodes.iter().all(|n| n.is_dir));
+        assert_eq!(dirs_only.total_size, 1110);
+        assert_eq!(dirs_only.file_count, 3);
+        for node in dirs_only.nodes {
+            let same = with_files
+                .nodes
+                .iter()
+                .find(|n| n.path == node.path)
+                .unwrap();
+            assert_eq!((node.size, node.file_count), (same.size, same.file_count));
+        }
+    }
+
+    #[test]
+    fn test_incremental_scan_reuses_unchanged_dirs() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("changed")).unwrap();
+        fs::create_dir(dir.path().join("same")).unwrap();
+        fs::write(dir.path().join("changed/old.txt"), "x".repeat(10)).unwrap();
+        fs::write(dir.path().join("same/file.txt"), "x".repeat(100)).unwrap();
+
+        let scanner = Scanner::new().apparent_size(true);
+        let (first, cache) = scanner.scan_incremental(dir.path(), None).unwrap();
+        assert_eq!(first.cached_dirs, 0);
+        assert_eq!(cache.dirs.len(), 3);
+
+        // Let the coarse filesystem clock tick so the directory mtime changes
+        std::thread::sleep(Duration::from_millis(50));
+        fs::write(dir.path().join("changed/new.txt"), "x".repeat(1000)).unwrap();
+        // Rewriting in place leaves the directory mtime alone, so the cached size is kept
+        fs::write(dir.path().join("same/file.txt"), "x".repeat(200)).unwrap();
+
+        let (second, _) = scanner.scan_incremental(dir.path(), Some(&cache)).unwrap();
+        assert_eq!(second.cached_dirs, 2);
+        assert_eq!(second.file_count, 3);
+        assert_eq!(second.total_size, 1110);
+
+        let changed = second
+            .nodes
+            .iter()
+            .find(|n| n.path.ends_with("changed"))
+            .unwrap();
+        assert_eq!(changed.size, 1010);
+
+        // Listings recorded with other filters are ignored
+        let (filtered, _) = Scanner::new()
+            .skip_hidden(true)
+            .scan_incremental(dir.path(), Some(&cache))
+            .unwrap();
+        assert_eq!(filtered.cached_dirs, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_count_links_once() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::write(dir.path().join("a/data.bin"), "x".repeat(1000)).unwrap();
+        fs::hard_link(dir.path().join("a/data.bin"), dir.path().join("b/link.bin")).unwrap();
+
+        let scanner = Scanner::new().apparent_size(true);
+        assert_eq!(scanner.scan(dir.path()).unwrap().total_size, 2000);
+
+        // Both links are still counted as files, but the size only once
+        let result = scanner.count_links_once(true).scan(dir.path()).unwrap();
+        assert_eq!(result.file_count, 2);
+        assert_eq!(result.total_size, 1000);
+        let dir_sizes: u64 = result.nodes.iter().map(|n| n.size).sum();
+        assert_eq!(dir_sizes, 1000);
+    }
+
+    #[test]
+    fn test_apparent_size_on_sparse_file() {
+        let dir = tempdir().unwrap();
+        let sparse = fs::File::create(dir.path().join("sparse.img")).unwrap();
+        sparse.set_len(10 * 1024 * 1024).unwrap();
+
+        let on_disk = Scanner::new().include_files(true).scan(dir.path()).unwrap();
+        let apparent = Scanner::new()
+            .include_files(true)
+            .apparent_size(true)
+            .scan(dir.path())
+            .unwrap();
+
+        assert!(on_disk.total_size < 10 * 1024 * 1024);
+        assert_eq!(apparent.total_size, 10 * 1024 * 1024);
+
+        let node = &on_disk.nodes[0];
+        assert_eq!(node.apparent_size, 10 * 1024 * 1024);
+        assert!(node.size < node.apparent_size);
+    }
+
+    #[test]
+    fn test_scan_with_excludes() {
+        let dir = tempdir().unwrap();
+
+        fs::write(dir.path().join("keep.txt"), "keep").unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::write(
+            dir.path().join("node_modules/pkg/index.js"),
+            "x".repeat(100_000),
+        )
+        .unwrap();
+
+        let scanner = Scanner::new()
+            .include_files(true)
+            .with_excludes(vec![Pattern::new("node_modules").unwrap()]);
+        let result = scanner.scan(dir.path()).unwrap();
+
+        assert_eq!(result.file_count, 1);
+        assert_eq!(result.dir_count, 0);
+        assert!(
+            result
+                .nodes
+                .iter()
+                .all(|n| !n.path.to_string_lossy().contains("node_modules"))
+        );
+        assert!(result.total_size < 100_000);
+    }
+
+    #[test]
+    fn test_entry_count_includes_filtered_entries() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/empty")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/debug.log"), "x").unwrap();
+
+        let result = Scanner::new()
+            .max_scan_depth(Some(2))
+            .with_excludes(vec![Pattern::new("*.log").unwrap()])
+            .scan(dir.path())
+            .unwrap();
+        let entry_count = |path: &str| {
+            result
+                .nodes
+                .iter()
+                .find(|n| n.path == dir.path().join(path))
+                .unwrap()
+                .entry_count
+        };
+
+        assert_eq!(entry_count("src"), Some(3));
+        // At the depth limit the directory is reported without being listed
+        assert_eq!(entry_count("src/empty"), None);
+    }
+
+    #[test]
+    fn test_scan_with_filter() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("build/out")).unwrap();
+        fs::write(dir.path().join("build/out/app.bin"), "x".repeat(1000)).unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "x".repeat(10)).unwrap();
+
+        // Rejecting a directory drops its whole subtree
+        let result = Scanner::new()
+            .include_files(true)
+            .apparent_size(true)
+            .scan_with_filter(dir.path(), |path, is_dir| {
+                !(is_dir && path.ends_with("build"))
+            })
+            .unwrap();
+        assert_eq!(result.file_count, 1);
+        assert_eq!(result.dir_count, 1);
+        assert_eq!(result.total_size, 10);
+        assert!(result.nodes.iter().all(|n| !n.path.ends_with("app.bin")));
+
+        // Files are offered too, with is_dir false
+        let result = Scanner::new()
+            .scan_with_filter(dir.path(), |path, is_dir| {
+                is_dir || path.extension().is_some_and(|ext| ext == "rs")
+            })
+            .unwrap();
+        assert_eq!(result.file_count, 1);
+        assert_eq!(result.dir_count, 3);
+    }
+
+    #[test]
+    fn test_scan_respects_gitignore() {
+        let dir = tempdir().unwrap();
+
+        fs::write(dir.path().join(".gitignore"), "build/\n").unwrap();
+        fs::create_dir_all(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("build/out.bin"), "x".repeat(100_000)).unwrap();
+        fs::create_dir_all(dir.path().join("src/gen")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/.gitignore"), "gen/\n").unwrap();
+        fs::write(dir.path().join("src/gen/code.rs"), "x".repeat(100_000)).unwrap();
+
+        let default_result = Scanner::new().scan(dir.path()).unwrap();
+        assert_eq!(default_result.file_count, 5);
+
+        let result = Scanner::new()
+            .respect_gitignore(true)
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(result.file_count, 3);
+        assert!(result.nodes.iter().all(|n| !n.path.ends_with("build")));
+        assert!(result.nodes.iter().all(|n| !n.path.ends_with("gen")));
+        assert!(result.total_size < 100_000);
+    }
+
+    #[test]
+    fn test_include_root() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("top.txt"), vec![0u8; 3000]).unwrap();
+        fs::write(dir.path().join("sub/inner.txt"), vec![0u8; 7000]).unwrap();
+
+        let without = Scanner::new().apparent_size(true).scan(dir.path()).unwrap();
+        assert!(without.nodes.iter().all(|n| n.depth > 0));
+
+        let result = Scanner::new()
+            .apparent_size(true)
+            .include_root(true)
+            .scan(dir.path())
+            .unwrap();
+        let root = result.nodes.iter().find(|n| n.depth == 0).unwrap();
+        assert_eq!(root.path, dir.path().canonicalize().unwrap());
+        assert_eq!(root.size, result.total_size);
+        assert_eq!(root.file_count, 2);
+        // The root node doesn't change the totals
+        assert_eq!(result.total_size, 10_000);
+        assert_eq!(result.total_size, without.total_size);
+        assert_eq!(result.dir_count, 1);
+        assert_eq!(result.nodes.len(), without.nodes.len() + 1);
+
+        // As the largest directory it leads the sorted, directory-only list
+        let mut result = result;
+        result.filter_dirs_only();
+        result.sort_by_size_desc();
+        assert_eq!(result.nodes[0].depth, 0);
+        assert_eq!(result.nodes[0].size, 10_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_dir_marked_inaccessible() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("secret.bin"), vec![0u8; 1000]).unwrap();
+        fs::create_dir(dir.path().join("open")).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Permissions don't apply to root, so there is nothing to test
+        if fs::read_dir(&locked).is_ok() {
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+            return;
+        }
+
+        let result = Scanner::new().scan(dir.path()).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let node = |name: &str| {
+            result
+                .nodes
+                .iter()
+                .find(|n| n.path.ends_with(name))
+                .unwrap()
+        };
+        assert!(!node("locked").accessible);
+        assert_eq!(node("locked").size, 0);
+        assert!(node("open").accessible);
+        assert_eq!(result.error_count, 1);
+        assert!(result.errors[0].0.ends_with("locked"));
+    }
+
+    #[test]
+    fn test_max_scan_depth() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        fs::write(dir.path().join("top.bin"), vec![0u8; 1]).unwrap();
+        fs::write(dir.path().join("a/one.bin"), vec![0u8; 10]).unwrap();
+        fs::write(dir.path().join("a/b/two.bin"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("a/b/c/three.bin"), vec![0u8; 1000]).unwrap();
+
+        let scanner = Scanner::new().apparent_size(true);
+        let full = scanner.scan(dir.path()).unwrap();
+        assert_eq!(full.total_size, 1111);
+        assert_eq!(full.max_scan_depth, None);
+
+        let limited = scanner.max_scan_depth(Some(2)).scan(dir.path()).unwrap();
+        assert_eq!(limited.max_scan_depth, Some(2));
+        assert_eq!(limited.total_size, 11);
+        assert_eq!(limited.file_count, 2);
+        // a/b is listed but not read, so it looks empty
+        let size_of = |name: &str| {
+            limited
+                .nodes
+                .iter()
+                .find(|n| n.path.ends_with(name))
+                .map(|n| n.size)
+        };
+        assert_eq!(size_of("a"), Some(10));
+        assert_eq!(size_of("a/b"), Some(0));
+        assert_eq!(size_of("a/b/c"), None);
+    }
+
+    #[test]
+    fn test_self_size() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        fs::create_dir_all(dir.path().join("a/empty")).unwrap();
+        fs::write(dir.path().join("a/one.bin"), vec![0u8; 10]).unwrap();
+        fs::write(dir.path().join("a/two.bin"), vec![0u8; 20]).unwrap();
+        fs::write(dir.path().join("a/b/c/deep.bin"), vec![0u8; 1000]).unwrap();
+
+        let result = Scanner::new()
+            .apparent_size(true)
+            .include_files(true)
+            .include_root(true)
+            .scan(dir.path())
+            .unwrap();
+        let sizes = |name: &str| {
+            let node = result
+                .nodes
+                .iter()
+                .find(|n| n.path == dir.path().join(name))
+                .unwrap();
+            (node.size, node.self_size)
+        };
+        // Only files directly inside count towards a directory's own size
+        assert_eq!(sizes(""), (1030, 0));
+        assert_eq!(sizes("a"), (1030, 30));
+        assert_eq!(sizes("a/b"), (1000, 0));
+        assert_eq!(sizes("a/b/c"), (1000, 1000));
+        assert_eq!(sizes("a/empty"), (0, 0));
+        assert_eq!(sizes("a/one.bin"), (10, 10));
+    }
+
+    #[test]
+    fn test_include_files_matching() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("isos")).unwrap();
+        fs::write(dir.path().join("isos/debian.iso"), vec![0u8; 3000]).unwrap();
+        fs::write(dir.path().join("isos/notes.txt"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("backup.ISO"), vec![0u8; 50]).unwrap();
+        fs::write(dir.path().join("top.iso"), vec![0u8; 200]).unwrap();
+
+        let result = Scanner::new()
+            .apparent_size(true)
+            .include_files_matching(vec![Pattern::new("*.iso").unwrap()])
+            .scan(dir.path())
+            .unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let mut files: Vec<_> = result
+            .nodes
+            .iter()
+            .filter(|n| !n.is_dir)
+            .map(|n| n.path.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [PathBuf::from("isos/debian.iso"), PathBuf::from("top.iso")]
+        );
+
+        // Directories and totals still cover every file
+        let isos = result.nodes.iter().find(|n| n.is_dir).unwrap();
+        assert_eq!((isos.size, isos.file_count), (3100, 2));
+        assert_eq!(result.total_size, 3350);
+    }
+
+    #[test]
+    fn test_aggregation_matches_files() {
+        // Files alongside subdirectories at every level, so a directory's files arrive
+        // interleaved with its subdirectories' contents
+        let dir = tempdir().unwrap();
+        for (idx, sub) in ["", "x", "x/y", "x/y/z", "x/w", "v", "v/u/t"]
+            .iter()
+            .enumerate()
+        {
+            let sub = dir.path().join(sub);
+            fs::create_dir_all(&sub).unwrap();
+            for file in 0..3 {
+                let size = 100 * idx + 10 * file + 1;
+                fs::write(sub.join(format!("f{}.bin", file)), vec![0u8; size]).unwrap();
+            }
+        }
+
+        let result = Scanner::new()
+            .apparent_size(true)
+            .include_files(true)
+            .include_root(true)
+            .with_threads(Some(4))
+            .scan(dir.path())
+            .unwrap();
+        let files: Vec<&Node> = result.nodes.iter().filter(|n| !n.is_dir).collect();
+        assert_eq!(files.len(), 21);
+        for node in result.nodes.iter().filter(|n| n.is_dir) {
+            let beneath: Vec<&&Node> = files
+                .iter()
+                .filter(|f| f.path.starts_with(&node.path))
+                .collect();
+            let size: u64 = beneath.iter().map(|f| f.size).sum();
+            let own: u64 = beneath
+                .iter()
+                .filter(|f| f.path.parent() == Some(node.path.as_path()))
+                .map(|f| f.size)
+                .sum();
+            assert_eq!(node.size, size, "{}", node.path.display());
+            assert_eq!(node.apparent_size, size, "{}", node.path.display());
+            assert_eq!(node.self_size, own, "{}", node.path.display());
+            assert_eq!(node.file_count, beneath.len() as u64);
+        }
+        assert_eq!(
+            result.nodes.iter().find(|n| n.depth == 0).unwrap().size,
+            result.total_size
+        );
+    }
+
+    #[test]
+    fn test_cancel_on() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/b/file.bin"), vec![0u8; 100]).unwrap();
+
+        let flag = Arc::new(AtomicBool::new(false));
+        let scanner = Scanner::new().cancel_on(Arc::clone(&flag));
+        let full = scanner.scan(dir.path()).unwrap();
+        assert!(!full.interrupted);
+        assert_eq!(full.file_count, 1);
+
+        // A flag set before the walk starts stops it straight away
+        flag.store(true, Ordering::Relaxed);
+        let partial = scanner.scan(dir.path()).unwrap();
+        assert!(partial.interrupted);
+        assert_eq!(partial.file_count, 0);
+        assert_eq!(partial.total_size, 0);
+    }
+
+    #[test]
+    fn test_cancel_after_walk_is_not_interrupted() {
+        // Sets the flag once the walk is over, as a late Ctrl-C would
+        struct CancelAfterWalk(Arc<AtomicBool>);
+        impl ProgressReporter for CancelAfterWalk {
+            fn phase(&self, _message: &str) {
+                self.0.store(true, Ordering::Relaxed);
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/b/file.bin"), vec![0u8; 100]).unwrap();
+
+        let flag = Arc::new(AtomicBool::new(false));
+        let result = Scanner::new()
+            .cancel_on(Arc::clone(&flag))
+            .with_progress(Arc::new(CancelAfterWalk(Arc::clone(&flag))))
+            .scan(dir.path())
+            .unwrap();
+        assert!(flag.load(Ordering::Relaxed));
+        assert!(!result.interrupted);
+        assert_eq!(result.file_count, 1);
+    }
+
+    #[cfg(feature = "archives")]
+    #[test]
+    fn test_into_archives() {
+        use std::io::Write;
+
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("backups")).unwrap();
+        let zip_path = dir.path().join("backups/site.zip");
+        let mut writer = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        for (name, size) in [("www/index.html", 300), ("www/img/logo.png", 5000)] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(&vec![0u8; size]).unwrap();
+        }
+        writer.finish().unwrap();
+        fs::write(dir.path().join("backups/broken.zip"), "not a zip").unwrap();
+
+        let plain = Scanner::new().scan(dir.path()).unwrap();
+        let result = Scanner::new().into_archives(true).scan(dir.path()).unwrap();
+
+        let node = |path: &str| result.nodes.iter().find(|n| n.path.ends_with(path));
+        let www = node("site.zip/www").unwrap();
+        assert_eq!((www.size, www.file_count, www.depth), (5300, 2, 3));
+        assert_eq!(node("site.zip/www/img").unwrap().size, 5000);
+        assert!(node("site.zip/www/index.html").is_none());
+
+        // Archive contents don't change the totals, and the broken one is an error
+        assert_eq!(result.total_size, plain.total_size);
+        assert_eq!(result.file_count, plain.file_count);
+        assert_eq!(result.error_count, 1);
+        assert!(result.errors[0].0.ends_with("broken.zip"));
+
+        let with_files = Scanner::new()
+            .into_archives(true)
+            .include_files(true)
+            .scan(dir.path())
+            .unwrap();
+        let index = with_files
+            .nodes
+            .iter()
+            .find(|n| n.path.ends_with("site.zip/www/index.html"))
+            .unwrap();
+        assert_eq!((index.size, index.is_dir, index.depth), (300, false, 4));
+    }
+
+    #[test]
+    fn test_paths_beyond_legacy_max_path() {
+        let dir = tempdir().unwrap();
+        let mut deep = dir.path().to_path_buf();
+        for level in 0..8 {
+            deep.push(format!("{level}-{}", "n".repeat(48)));
+        }
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("file.bin"), vec![0u8; 1000]).unwrap();
+        assert!(deep.as_os_str().len() > 260);
+
+        let result = Scanner::new().apparent_size(true).scan(dir.path()).unwrap();
+        assert_eq!(result.error_count, 0);
+        assert_eq!(result.file_count, 1);
+        assert_eq!(result.dir_count, 8);
+        assert_eq!(result.total_size, 1000);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_extended_length_path() {
+        assert_eq!(
+            extended_length_path(Path::new(r"C:\Users\me\file.txt")),
+            Path::new(r"\\?\C:\Users\me\file.txt")
+        );
+        assert_eq!(
+            extended_length_path(Path::new(r"\\server\share\dir")),
+            Path::new(r"\\?\UNC\server\share\dir")
+        );
+        for unchanged in [r"\\?\C:\already", r"relative\path", r"C:\a\..\b"] {
+            assert_eq!(
+                extended_length_path(Path::new(unchanged)),
+                Path::new(unchanged)
+            );
+        }
+    }
+
+    #[test]
+    fn test_exclude_does_not_filter_root() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("target");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("file.txt"), "content").unwrap();
+
+        let result = Scanner::new()
+            .with_excludes(vec![Pattern::new("target").unwrap()])
+            .scan(&root)
+            .unwrap();
+        assert_eq!(result.file_count, 1);
+    }
+
+    #[test]
+    fn test_regex_filters() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("build/cache")).unwrap();
+        fs::write(dir.path().join("keep.log"), "x".repeat(10)).unwrap();
+        fs::write(dir.path().join("scratch.tmp"), "x".repeat(100)).unwrap();
+        fs::write(dir.path().join("build/out.log"), "x".repeat(1000)).unwrap();
+        fs::write(dir.path().join("build/cache/blob.log"), "x".repeat(10_000)).unwrap();
+        let re = |p: &str| Regex::new(p).unwrap();
+
+        let result = Scanner::new()
+            .apparent_size(true)
+            .with_exclude_regexes(vec![re(r".*\.tmp$")])
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(result.file_count, 3);
+        assert_eq!(result.total_size, 11_010);
+
+        // Excluded directories are pruned, and exclusion wins over inclusion
+        let result = Scanner::new()
+            .apparent_size(true)
+            .with_include_regexes(vec![re(r"\.log$"), re(r"\.tmp$")])
+            .with_exclude_regexes(vec![re(r"cache$"), re(r"scratch")])
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(result.file_count, 2);
+        assert_eq!(result.total_size, 1010);
+        assert!(result.nodes.iter().all(|n| !n.path.ends_with("cache")));
+    }
+
+    #[test]
+    fn test_scan_with_extensions() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("photos")).unwrap();
+        fs::write(dir.path().join("photos/notes.txt"), "x".repeat(100_000)).unwrap();
+        fs::write(dir.path().join("photos/IMG_001.JPG"), "x".repeat(10)).unwrap();
+
+        let result = Scanner::new()
+            .with_extensions(vec![".jpg".to_string()])
+            .scan(dir.path())
+            .unwrap();
+
+        assert_eq!(result.file_count, 1);
+        let photos = result.nodes.iter().find(|n| n.is_dir).unwrap();
+        assert_eq!(photos.file_count, 1);
+        assert!(photos.size < 100_000);
+        assert_eq!(photos.size, result.total_size);
+    }
+
+    #[test]
+    fn test_summarize_by_type() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.mp4"), "x".repeat(100_000)).unwrap();
+        fs::write(dir.path().join("b.MP4"), "x".repeat(100_000)).unwrap();
+        fs::write(dir.path().join("notes.txt"), "x").unwrap();
+        fs::write(dir.path().join("Makefile"), "x").unwrap();
+
+        let result = Scanner::new()
+            .summarize_types(true)
+            .scan(dir.path())
+            .unwrap();
+
+        assert_eq!(result.types.len(), 3);
+        assert_eq!(result.types[0].extension.as_deref(), Some("mp4"));
+        assert_eq!(result.types[0].file_count, 2);
+        assert!(result.types.iter().any(|t| t.extension.is_none()));
+    }
+
+    #[test]
+    fn test_size_histogram() {
+        let dir = tempdir().unwrap();
+        // Boundaries are inclusive below: 1000 falls in the 1000-10000 bucket
+        for (name, size) in [
+            ("a", 10),
+            ("b", 999),
+            ("c", 1000),
+            ("d", 5000),
+            ("e", 20_000),
+        ] {
+            fs::write(dir.path().join(name), "x".repeat(size)).unwrap();
+        }
+
+        let result = Scanner::new()
+            .apparent_size(true)
+            .size_histogram(Some(vec![1000, 10_000]))
+            .scan(dir.path())
+            .unwrap();
+        let buckets: Vec<_> = result
+            .histogram
+            .iter()
+            .map(|b| (b.min, b.max, b.file_count, b.size))
+            .collect();
+        assert_eq!(
+            buckets,
+            [
+                (0, Some(1000), 2, 1009),
+                (1000, Some(10_000), 2, 6000),
+                (10_000, None, 1, 20_000),
+            ]
+        );
+
+        assert!(
+            Scanner::new()
+                .scan(dir.path())
+                .unwrap()
+                .histogram
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("backup")).unwrap();
+        fs::write(dir.path().join("photo.jpg"), "same content").unwrap();
+        fs::write(dir.path().join("backup/photo.jpg"), "same content").unwrap();
+        fs::write(dir.path().join("other.jpg"), "diff content").unwrap();
+
+        let result = Scanner::new()
+            .find_duplicates(true)
+            .scan(dir.path())
+            .unwrap();
+
+        assert_eq!(result.duplicates.len(), 1);
+        let group = &result.duplicates[0];
+        assert_eq!(group.paths.len(), 2);
+        assert!(group.paths.iter().all(|p| p.ends_with("photo.jpg")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        let data = dir.path().join("data");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir(&data).unwrap();
+        fs::write(data.join("big.bin"), "x".repeat(100_000)).unwrap();
+        std::os::unix::fs::symlink(&data, root.join("link")).unwrap();
+
+        let without = Scanner::new().scan(&root).unwrap();
+        assert!(without.total_size < 100_000);
+
+        let with = Scanner::new().follow_symlinks(true).scan(&root).unwrap();
+        assert!(with.total_size >= 100_000);
+        assert_eq!(with.file_count, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_skips_cycles() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/file.txt"), "content").unwrap();
+        std::os::unix::fs::symlink("..", dir.path().join("sub/up")).unwrap();
+
+        let result = Scanner::new()
+            .follow_symlinks(true)
+            .scan(dir.path())
+            .unwrap();
+
+        assert_eq!(result.file_count, 1);
+        assert_eq!(result.cycles_skipped, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_errors_record_paths() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::os::unix::fs::symlink("/nonexistent", root.join("broken")).unwrap();
+
+        let result = Scanner::new().follow_symlinks(true).scan(&root).unwrap();
+
+        assert_eq!(result.error_count, 1);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].0, root.join("broken"));
+        assert!(!result.errors[0].1.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_one_file_system() {
+        // Needs a second filesystem; /dev/shm is a tmpfs on most Linux systems
+        let Ok(other) = tempfile::tempdir_in("/dev/shm") else {
+            return;
+        };
+        let dir = tempdir().unwrap();
+        if device_id(&dir.path().metadata().unwrap())
+            == device_id(&other.path().metadata().unwrap())
+        {
+            return;
+        }
+        fs::write(other.path().join("big.bin"), "x".repeat(100_000)).unwrap();
+        fs::write(dir.path().join("local.txt"), "x").unwrap();
+        std::os::unix::fs::symlink(other.path(), dir.path().join("mount")).unwrap();
+
+        let crossing = Scanner::new()
+            .follow_symlinks(true)
+            .apparent_size(true)
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(crossing.total_size, 100_001);
+
+        let result = Scanner::new()
+            .follow_symlinks(true)
+            .one_file_system(true)
+            .apparent_size(true)
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(result.total_size, 1);
+        assert_eq!(result.file_count, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_mount_crossings() {
+        // Same setup as test_one_file_system: a symlink into /dev/shm's tmpfs
+        let Ok(other) = tempfile::tempdir_in("/dev/shm") else {
+            return;
+        };
+        let dir = tempdir().unwrap();
+        if device_id(&dir.path().metadata().unwrap())
+            == device_id(&other.path().metadata().unwrap())
+        {
+            return;
+        }
+        fs::create_dir(other.path().join("inner")).unwrap();
+        fs::write(other.path().join("inner/big.bin"), "x".repeat(1000)).unwrap();
+        fs::create_dir(dir.path().join("local")).unwrap();
+        std::os::unix::fs::symlink(other.path(), dir.path().join("mount")).unwrap();
+
+        let scanner = Scanner::new().follow_symlinks(true).apparent_size(true);
+        assert!(scanner.scan(dir.path()).unwrap().mount_crossings.is_empty());
+
+        // Only the boundary is noted, not the directories below it, and sizes are unchanged
+        let result = scanner
+            .detect_mount_crossings(true)
+            .scan(dir.path())
+            .unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(result.mount_crossings, [root.join("mount")]);
+        assert_eq!(result.total_size, 1000);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_special_mounts() {
+        let mounts = "/dev/sda1 / ext4 rw,relatime 0 0\n\
+                      proc /proc proc rw,nosuid 0 0\n\
+                      sysfs /sys sysfs rw 0 0\n\
+                      tmpfs /run tmpfs rw 0 0\n\
+                      proc /mnt/my\\040root/proc proc rw 0 0\n\
+                      malformed\n";
+        let special = parse_special_mounts(mounts);
+        assert_eq!(special.len(), 3);
+        assert!(special.contains(Path::new("/proc")));
+        assert!(special.contains(Path::new("/sys")));
+        assert!(special.contains(Path::new("/mnt/my root/proc")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_special_mounts_skipped() {
+        if !special_mounts().contains(Path::new("/proc")) {
+            return;
+        }
+        // Only list the top level of `/` so the test stays fast
+        let scan = |scanner: Scanner| {
+            let result = scanner
+                .max_scan_depth(Some(1))
+                .scan(Path::new("/"))
+                .unwrap();
+            result.nodes.iter().any(|n| n.path == Path::new("/proc"))
+        };
+        assert!(!scan(Scanner::new()));
+        assert!(scan(Scanner::new().scan_special(true)));
+    }
+
+    #[test]
+    fn test_skip_hidden() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".cache")).unwrap();
+        fs::write(dir.path().join(".cache/blob"), "x".repeat(100_000)).unwrap();
+        fs::write(dir.path().join(".env"), "SECRET=1").unwrap();
+        fs::write(dir.path().join("visible.txt"), "hello").unwrap();
+
+        let default_result = Scanner::new().scan(dir.path()).unwrap();
+        assert_eq!(default_result.file_count, 3);
+
+        let result = Scanner::new().skip_hidden(true).scan(dir.path()).unwrap();
+        assert_eq!(result.file_count, 1);
+        assert_eq!(result.dir_count, 0);
+        assert!(result.nodes.iter().all(|n| !n.path.ends_with(".cache")));
+        assert!(result.total_size < 100_000);
+    }
+
+    #[test]
+    fn test_progress_reporter_called() {
+        #[derive(Default)]
+        struct Recorder {
+            events: Mutex<Vec<String>>,
+        }
+        impl ProgressReporter for Recorder {
+            fn start(&self) {
+                self.events.lock().unwrap().push("start".into());
+            }
+            fn finish(&self, result: &ScanResult, _duration: Duration) {
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push(format!("finish {}", result.file_count));
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("file.txt"), "hello").unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        Scanner::new()
+            .with_progress(recorder.clone())
+            .scan(dir.path())
+            .unwrap();
+
+        assert_eq!(*recorder.events.lock().unwrap(), ["start", "finish 1"]);
+    }
+
+    #[test]
+    fn test_stream_files() {
+        #[derive(Default)]
+        struct Recorder {
+            found: Mutex<Vec<(PathBuf, u64)>>,
+        }
+        impl ProgressReporter for Recorder {
+            fn file_found(&self, path: &Path, size: u64) {
+                self.found.lock().unwrap().push((path.to_path_buf(), size));
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/big.bin"), vec![0u8; 5000]).unwrap();
+        fs::write(dir.path().join("small.txt"), "hello").unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        let scanner = Scanner::new()
+            .apparent_size(true)
+            .with_progress(recorder.clone());
+        scanner.scan(dir.path()).unwrap();
+        assert!(recorder.found.lock().unwrap().is_empty());
+
+        scanner.stream_files(Some(1000)).scan(dir.path()).unwrap();
+        let found = recorder.found.lock().unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].0.ends_with("sub/big.bin"));
+        assert_eq!(found[0].1, 5000);
+    }
+
+    #[test]
+    fn test_single_thread_order() {
+        #[derive(Default)]
+        struct Recorder {
+            found: Mutex<Vec<PathBuf>>,
+        }
+        impl ProgressReporter for Recorder {
+            fn file_found(&self, path: &Path, _size: u64) {
+                self.found.lock().unwrap().push(path.to_path_buf());
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        for name in ["b/2.txt", "b/1.txt", "a/z.txt", "c.txt", "a/y/x.txt"] {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+
+        // Files are found depth first in name order, the same way every time
+        for _ in 0..3 {
+            let recorder = Arc::new(Recorder::default());
+            let result = Scanner::new()
+                .with_threads(Some(1))
+                .stream_files(Some(0))
+                .collect_stats(true)
+                .with_progress(recorder.clone())
+                .scan(dir.path())
+                .unwrap();
+            let found: Vec<PathBuf> = recorder
+                .found
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|path| path.strip_prefix(dir.path()).unwrap().to_path_buf())
+                .collect();
+            assert_eq!(
+                found,
+                ["a/y/x.txt", "a/z.txt", "b/1.txt", "b/2.txt", "c.txt"].map(PathBuf::from)
+            );
+
+            // Every entry is read on the calling thread
+            let stats = result.stats.unwrap();
+            assert_eq!(stats.entries_per_thread, [8]);
+        }
+    }
+
+    #[test]
+    fn test_block_size() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/one.txt"), "x").unwrap();
+        fs::write(dir.path().join("sub/exact.bin"), vec![0u8; 4096]).unwrap();
+        fs::write(dir.path().join("sub/over.bin"), vec![0u8; 4097]).unwrap();
+        fs::write(dir.path().join("empty.txt"), "").unwrap();
+
+        let result = Scanner::new()
+            .block_size(Some(4096))
+            .include_files(true)
+            .scan(dir.path())
+            .unwrap();
+        let size_of = |name: &str| {
+            result
+                .nodes
+                .iter()
+                .find(|n| n.path.ends_with(name))
+                .unwrap()
+                .size
+        };
+        assert_eq!(size_of("one.txt"), 4096);
+        assert_eq!(size_of("exact.bin"), 4096);
+        assert_eq!(size_of("over.bin"), 8192);
+        assert_eq!(size_of("empty.txt"), 0);
+        assert_eq!(size_of("sub"), 16384);
+        assert_eq!(result.total_size, 16384);
+        // The logical length is still reported alongside
+        assert_eq!(
+            result
+                .nodes
+                .iter()
+                .find(|n| n.path.ends_with("sub"))
+                .unwrap()
+                .apparent_size,
+            8194
+        );
+
+        // 0 behaves like unset
+        let default = Scanner::new().apparent_size(true).block_size(Some(0));
+        assert_eq!(default.scan(dir.path()).unwrap().total_size, 8194);
+    }
+
+    #[test]
+    fn test_largest_nodes_matches_full_sort() {
+        // Plenty of equal sizes so tie-breaking is exercised
+        let nodes: Vec<Node> = (0..200u64)
+            .map(|i| Node::new(PathBuf::from(format!("/n/{i}")), (i * 37) % 23, true, 1))
+            .collect();
+
+        let mut sorted = nodes.clone();
+        sorted.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+
+        for max in [1, 5, 23, 200, 500] {
+            let top = largest_nodes(nodes.iter().cloned(), max);
+            let expected = &sorted[..max.min(sorted.len())];
+            let paths = |nodes: &[Node]| nodes.iter().map(|n| n.path.clone()).collect::<Vec<_>>();
+            assert_eq!(paths(&top), paths(expected));
+        }
+    }
+
+    #[test]
+    fn test_max_results() {
+        let dir = tempdir().unwrap();
+        for (i, size) in [3000, 1000, 5000, 2000, 4000].iter().enumerate() {
+            let sub = dir.path().join(format!("d{i}"));
+            fs::create_dir(&sub).unwrap();
+            fs::write(sub.join("f.bin"), vec![0u8; *size]).unwrap();
+        }
+
+        let scanner = Scanner::new().apparent_size(true).include_files(true);
+        let mut full = scanner.scan(dir.path()).unwrap();
+        full.sort_by_size_desc();
+        let capped = scanner.max_results(Some(4)).scan(dir.path()).unwrap();
+
+        assert_eq!(capped.nodes.len(), 4);
+        let sizes = |nodes: &[Node]| nodes.iter().map(|n| n.size).collect::<Vec<_>>();
+        assert_eq!(sizes(&capped.nodes), sizes(full.top_n(4)));
+        assert_eq!(capped.nodes[0].size, 5000);
+        // Totals still cover the whole tree
+        assert_eq!(capped.total_size, full.total_size);
+        assert_eq!(capped.dir_count, 5);
+    }
+
+    #[test]
+    fn test_count_entries_matches_progress() {
+        #[derive(Default)]
+        struct Recorder {
+            last: Mutex<u64>,
+        }
+        impl ProgressReporter for Recorder {
+            fn scanning(&self, _current_dir: &str, files: u64, dirs: u64, _bytes: u64) {
+                *self.last.lock().unwrap() = files + dirs;
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/one.txt"), "1").unwrap();
+        fs::write(dir.path().join("a/b/two.txt"), "2").unwrap();
+        fs::write(dir.path().join("skip.log"), "3").unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        let scanner = Scanner::new()
+            .with_excludes(vec![Pattern::new("*.log").unwrap()])
+            .with_progress(recorder.clone());
+        // Root, a, a/b and the two text files
+        assert_eq!(scanner.count_entries(dir.path()).unwrap(), 5);
+        assert_eq!(*recorder.last.lock().unwrap(), 0);
+
+        scanner.scan(dir.path()).unwrap();
+        assert!(*recorder.last.lock().unwrap() <= 5);
+    }
+
+    #[test]
+    fn test_profile() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        fs::write(dir.path().join("b.txt"), "a").unwrap();
+
+        assert!(Scanner::new().scan(dir.path()).unwrap().profile.is_none());
+
+        // Optional phases only appear when they run
+        let result = Scanner::new()
+            .find_duplicates(true)
+            .profile(true)
+            .scan(dir.path())
+            .unwrap();
+        let phases: Vec<_> = result
+            .profile
+            .unwrap()
+            .phases
+            .iter()
+            .map(|timing| timing.phase)
+            .collect();
+        assert_eq!(phases, ["walk", "aggregate", "build results", "duplicates"]);
+    }
+
+    #[test]
+    fn test_collect_stats() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/one.txt"), "1").unwrap();
+        fs::write(dir.path().join("a/b/two.txt"), "2").unwrap();
+
+        assert!(Scanner::new().scan(dir.path()).unwrap().stats.is_none());
+
+        let result = Scanner::new()
+            .with_threads(Some(2))
+            .collect_stats(true)
+            .scan(dir.path())
+            .unwrap();
+        let stats = result.stats.unwrap();
+        assert_eq!(stats.threads, 2);
+        assert_eq!(stats.entries_per_thread.len(), 2);
+        // a, a/one.txt, a/b and a/b/two.txt, wherever they were read
+        assert_eq!(stats.entries_per_thread.iter().sum::<u64>(), 4);
+        assert_eq!(stats.visited_lock_waits, None);
+
+        // The visited-directories lock is only taken when following symlinks
+        let result = Scanner::new()
+            .with_threads(Some(2))
+            .follow_symlinks(true)
+            .collect_stats(true)
+            .scan(dir.path())
+            .unwrap();
+        assert!(result.stats.unwrap().visited_lock_waits.is_some());
+    }
+
+    #[test]
+    fn test_progress_interval() {
+        #[derive(Default)]
+        struct Recorder {
+            updates: Mutex<u64>,
+        }
+        impl ProgressReporter for Recorder {
+            fn scanning(&self, _current_dir: &str, _files: u64, _dirs: u64, _bytes: u64) {
+                *self.updates.lock().unwrap() += 1;
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        for i in 0..10 {
+            fs::write(dir.path().join(format!("{i}.txt")), "x").unwrap();
+        }
+
+        // A zero interval reports every entry, including the root
+        let recorder = Arc::new(Recorder::default());
+        Scanner::new()
+            .progress_interval(Some(Duration::ZERO))
+            .with_progress(recorder.clone())
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(*recorder.updates.lock().unwrap(), 11);
+
+        let recorder = Arc::new(Recorder::default());
+        Scanner::new()
+            .progress_interval(Some(Duration::from_secs(60)))
+            .with_progress(recorder.clone())
+            .scan(dir.path())
+            .unwrap();
+        assert_eq!(*recorder.updates.lock().unwrap(), 0);
     }
 
     #[test]
-    fn test_truncate_str() {
-        assert_eq!(truncate_str("short", 10), "short");
-        assert_eq!(truncate_str("this is a long string", 10), "this is...");
+    fn test_matches_glob() {
+        let patterns = vec![
+            Pattern::new("*.log").unwrap(),
+            Pattern::new("target").unwrap(),
+        ];
+        assert!(matches_glob(Path::new("/a/b/debug.log"), &patterns));
+        assert!(matches_glob(Path::new("/a/target"), &patterns));
+        assert!(!matches_glob(Path::new("/a/target.txt"), &patterns));
+        assert!(!matches_glob(Path::new("/a/target"), &[]));
     }
 }
@@ -0,0 +1,265 @@
+//! Interactive terminal browser for scan results.
+
+use crate::cli::{NumberFormat, Units};
+use crate::display::format_bytes;
+use anyhow::{Context, Result};
+use disk_scanner::node::{Node, ScanResult};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Navigation state for browsing a scan result directory by directory
+pub struct Browser<'a> {
+    /// Root of the scan
+    root: &'a Path,
+    /// Total size of the root directory
+    root_size: u64,
+    /// Children of each directory, in the order the nodes were sorted in
+    children: HashMap<&'a Path, Vec<&'a Node>>,
+    /// Directories entered from the root, with the selection to restore on the way back
+    stack: Vec<(&'a Node, usize)>,
+    /// Selected entry in the current directory
+    selected: usize,
+    /// Units for human-readable sizes
+    units: Units,
+    /// Separators for sizes
+    numbers: NumberFormat,
+    /// Whether to use colors
+    color: bool,
+}
+
+impl<'a> Browser<'a> {
+    /// Create a browser positioned at the scan root
+    pub fn new(result: &'a ScanResult, root: &'a Path) -> Self {
+        // Rebuild parent/child relationships from the flat node list
+        let mut children: HashMap<&Path, Vec<&Node>> = HashMap::new();
+        for node in &result.nodes {
+            if let Some(parent) = node.path.parent() {
+                children.entry(parent).or_default().push(node);
+            }
+        }
+
+        Self {
+            root,
+            root_size: result.total_size,
+            children,
+            stack: Vec::new(),
+            selected: 0,
+            units: Units::default(),
+            numbers: NumberFormat::default(),
+            color: true,
+        }
+    }
+
+    /// Enable or disable colors
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the units for human-readable sizes
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Set the separators for sizes
+    pub fn with_number_format(mut self, numbers: NumberFormat) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Run the browser until the user quits
+    pub fn run(mut self) -> Result<()> {
+        let mut terminal = ratatui::try_init().context("Failed to initialize terminal")?;
+        let result = self.event_loop(&mut terminal);
+        ratatui::try_restore().context("Failed to restore terminal")?;
+        result
+    }
+
+    /// Draw and handle key presses until `q` is pressed
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.render(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Apply a key press, returning false when the browser should quit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                self.selected = (self.selected + 1).min(self.entries().len().saturating_sub(1))
+            }
+            KeyCode::Enter => self.descend(),
+            KeyCode::Backspace => self.ascend(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Enter the selected directory
+    fn descend(&mut self) {
+        if let Some(&node) = self.entries().get(self.selected) {
+            if node.is_dir {
+                self.stack.push((node, self.selected));
+                self.selected = 0;
+            }
+        }
+    }
+
+    /// Go back to the parent directory, restoring its selection
+    fn ascend(&mut self) {
+        if let Some((_, selected)) = self.stack.pop() {
+            self.selected = selected;
+        }
+    }
+
+    /// Path and size of the directory being viewed
+    fn current(&self) -> (&'a Path, u64) {
+        match self.stack.last() {
+            Some((node, _)) => (&node.path, node.size),
+            None => (self.root, self.root_size),
+        }
+    }
+
+    /// Entries of the directory being viewed
+    fn entries(&self) -> &[&'a Node] {
+        self.children
+            .get(self.current().0)
+            .map_or(&[], |entries| entries.as_slice())
+    }
+
+    /// Render the header, entry list and key help
+    fn render(&self, frame: &mut Frame) {
+        let [header_area, list_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let (path, size) = self.current();
+        let header = Line::from(vec![
+            Span::styled(
+                format!(" {} ", self.size(size)),
+                self.style(Style::new().fg(Color::Green)),
+            ),
+            Span::styled(
+                path.display().to_string(),
+                self.style(Style::new().fg(Color::Blue))
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]);
+        frame.render_widget(Paragraph::new(header), header_area);
+
+        let items: Vec<ListItem> = self
+            .entries()
+            .iter()
+            .map(|node| {
+                let name = node
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| node.path.display().to_string());
+                let (name, style) = if !node.accessible {
+                    (
+                        format!("{name}/ ⚠ unreadable"),
+                        self.style(Style::new().fg(Color::Yellow)),
+                    )
+                } else if node.is_dir {
+                    (format!("{name}/"), self.style(Style::new().fg(Color::Blue)))
+                } else {
+                    (name, Style::new())
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:>12}  ", self.size(node.size)),
+                        self.style(Style::new().fg(Color::Green)),
+                    ),
+                    Span::styled(name, style),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let footer = "↑/↓ move  enter open  backspace back  q quit";
+        frame.render_widget(
+            Paragraph::new(footer).style(Style::new().add_modifier(Modifier::DIM)),
+            footer_area,
+        );
+    }
+
+    /// Format a size in the configured units and decimal separator
+    fn size(&self, bytes: u64) -> String {
+        self.numbers
+            .localize(format_bytes(bytes, self.units, self.numbers.precision))
+    }
+
+    /// Drop colors from a style when they are disabled
+    fn style(&self, style: Style) -> Style {
+        if self.color { style } else { Style::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample_result() -> ScanResult {
+        let mut result = ScanResult::new();
+        result.total_size = 300;
+        result.nodes = vec![
+            Node::new(PathBuf::from("/root/big"), 200, true, 1),
+            Node::new(PathBuf::from("/root/big/inner"), 150, true, 2),
+            Node::new(PathBuf::from("/root/small"), 100, true, 1),
+            Node::new(PathBuf::from("/root/big/file.bin"), 50, false, 2),
+        ];
+        result
+    }
+
+    #[test]
+    fn test_browser_navigation() {
+        let result = sample_result();
+        let mut browser = Browser::new(&result, Path::new("/root"));
+        assert_eq!(browser.current(), (Path::new("/root"), 300));
+        assert_eq!(browser.entries().len(), 2);
+
+        // Moving is clamped to the list bounds
+        browser.handle_key(KeyCode::Down);
+        browser.handle_key(KeyCode::Down);
+        assert_eq!(browser.selected, 1);
+        browser.handle_key(KeyCode::Up);
+
+        browser.handle_key(KeyCode::Enter);
+        assert_eq!(browser.current(), (Path::new("/root/big"), 200));
+        assert_eq!(browser.entries().len(), 2);
+
+        // Files cannot be entered
+        browser.handle_key(KeyCode::Down);
+        browser.handle_key(KeyCode::Enter);
+        assert_eq!(browser.current().0, Path::new("/root/big"));
+
+        browser.handle_key(KeyCode::Backspace);
+        assert_eq!(browser.current().0, Path::new("/root"));
+        assert_eq!(browser.selected, 0);
+
+        assert!(!browser.handle_key(KeyCode::Char('q')));
+    }
+}
@@ -0,0 +1,129 @@
+//! Parsing human-readable sizes shared by the size options (`--min-size`, `--collapse-under`, ...).
+
+/// Parse a human-friendly size such as `500`, `10K`, `2.5GB`, or `1.5 GiB` into bytes.
+///
+/// Suffixes ending in `iB` and single-letter suffixes (`K`, `M`, ...) are binary
+/// (powers of 1024); two-letter suffixes (`KB`, `MB`, ...) are decimal (powers of 1000).
+/// Units are case-insensitive and may be separated from the number by spaces. Sizes that
+/// don't fit in 64 bits are rejected rather than wrapped or saturated.
+pub fn parse_human_size(s: &str) -> Result<u64, String> {
+    let input = s.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, suffix) = input.split_at(split);
+
+    let value: f64 = number.parse().map_err(|_| {
+        format!(
+            "invalid size '{}': expected a number with an optional unit, e.g. 10K or 1.5GB",
+            s
+        )
+    })?;
+    let multiplier: u64 = match suffix.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "T" | "TIB" => 1 << 40,
+        "P" | "PIB" => 1 << 50,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        "PB" => 1_000_000_000_000_000,
+        _ => {
+            return Err(format!(
+                "invalid size '{}': unknown unit '{}' (expected B, K, KB, KiB, M, MB, MiB, ...)",
+                s,
+                suffix.trim()
+            ));
+        }
+    };
+    let too_large = || format!("invalid size '{}': larger than {} bytes", s, u64::MAX);
+
+    // Whole numbers are multiplied exactly; fractions go through f64
+    if let Ok(whole) = number.parse::<u64>() {
+        return whole.checked_mul(multiplier).ok_or_else(too_large);
+    }
+    let bytes = (value * multiplier as f64).round();
+    // u64::MAX as f64 rounds up to 2^64, the first value that doesn't fit
+    if bytes >= u64::MAX as f64 {
+        return Err(too_large());
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_human_size() {
+        assert_eq!(parse_human_size("500"), Ok(500));
+        assert_eq!(parse_human_size("0"), Ok(0));
+        assert_eq!(parse_human_size("10K"), Ok(10 * 1024));
+        assert_eq!(parse_human_size("10k"), Ok(10 * 1024));
+        assert_eq!(parse_human_size("3 MiB"), Ok(3 << 20));
+        assert_eq!(parse_human_size("2.5GB"), Ok(2_500_000_000));
+        assert_eq!(parse_human_size("1.5GiB"), Ok(1_610_612_736));
+        assert_eq!(parse_human_size("100 mb"), Ok(100_000_000));
+        assert_eq!(parse_human_size("  7 b  "), Ok(7));
+        assert_eq!(parse_human_size(".5K"), Ok(512));
+        assert_eq!(parse_human_size("1.5"), Ok(2));
+        assert_eq!(parse_human_size("2PB"), Ok(2_000_000_000_000_000));
+    }
+
+    #[test]
+    fn test_parse_human_size_limits() {
+        assert_eq!(
+            parse_human_size("18446744073709551615"),
+            Ok(u64::MAX),
+            "whole numbers are exact up to u64::MAX"
+        );
+        for input in [
+            "18446744073709551616",
+            "99999999999GB",
+            "16384PiB",
+            "99999999999999999999.5",
+        ] {
+            let err = parse_human_size(input).unwrap_err();
+            assert!(err.contains(input), "{}: {}", input, err);
+        }
+        assert!(
+            parse_human_size("99999999999GB")
+                .unwrap_err()
+                .contains("larger than")
+        );
+        // About 100 PB still fits
+        assert_eq!(parse_human_size("99999999GB"), Ok(99_999_999_000_000_000));
+        assert_eq!(parse_human_size("16383.99PiB").map(|b| b > 0), Ok(true));
+    }
+
+    #[test]
+    fn test_parse_human_size_errors() {
+        for input in [
+            "", " ", "abc", "K", "10XB", "-5K", "1.2.3M", "1,5K", "nan", "inf", "1e30", "10 K B",
+            "5MBB",
+        ] {
+            assert!(
+                parse_human_size(input).is_err(),
+                "{:?} should be rejected",
+                input
+            );
+        }
+        assert!(
+            parse_human_size("10XB")
+                .unwrap_err()
+                .contains("unknown unit 'XB'")
+        );
+        assert!(
+            parse_human_size("abc")
+                .unwrap_err()
+                .contains("expected a number")
+        );
+
+        // Multi-byte characters don't break the number/unit split
+        assert!(parse_human_size("10ＭＢ").is_err());
+        assert!(parse_human_size("５K").is_err());
+    }
+}
@@ -2,11 +2,25 @@ use assert_cmd::Command;
 use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 use std::fs;
+use std::time::{Duration, SystemTime};
 use tempfile::tempdir;
 
-/// Get a command for running the disk-scanner binary
+/// Version of the JSON report schema (`JSON_SCHEMA_VERSION`)
+const JSON_SCHEMA: u64 = 5;
+
+/// Get a command for running the disk-scanner binary, with numbers formatted the same in
+/// every environment
 fn cmd() -> Command {
-    cargo_bin_cmd!("disk-scanner")
+    let mut cmd = cargo_bin_cmd!("disk-scanner");
+    cmd.env_remove("LC_ALL")
+        .env_remove("LC_NUMERIC")
+        .env_remove("LANG")
+        // Keep a config file in the developer's home out of the tests
+        .env(
+            "XDG_CONFIG_HOME",
+            std::env::temp_dir().join("disk-scanner-no-config"),
+        );
+    cmd
 }
 
 #[test]
@@ -46,7 +60,9 @@ fn test_scan_specific_directory() {
         .arg(dir.path())
         .assert()
         .success()
-        .stdout(predicate::str::contains("Disk Usage Report"));
+        .stdout(predicate::str::contains("Disk Usage Report"))
+        .stdout(predicate::str::contains("Scan time:"))
+        .stdout(predicate::str::contains("Largest file: file1.txt"));
 }
 
 #[test]
@@ -82,38 +98,1997 @@ fn test_all_flag_includes_files() {
 }
 
 #[test]
-fn test_nonexistent_path() {
+fn test_top_files_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("nested/deeper")).unwrap();
+    fs::write(
+        dir.path().join("nested/deeper/big.bin"),
+        "x".repeat(100_000),
+    )
+    .unwrap();
+    fs::write(dir.path().join("small.txt"), "x").unwrap();
+
     cmd()
-        .arg("/nonexistent/path/that/does/not/exist")
+        .arg(dir.path())
+        .args(["--top-files", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r"nested/deeper/big\.bin,\d+,file,3\nsmall\.txt,").unwrap(),
+        )
+        .stdout(predicate::str::contains(",dir,").not());
+}
+
+#[test]
+fn test_age_filters() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("stale")).unwrap();
+    fs::create_dir_all(dir.path().join("fresh")).unwrap();
+    fs::write(dir.path().join("stale/old.bin"), "x").unwrap();
+    fs::write(dir.path().join("fresh/new.bin"), "x").unwrap();
+    let year_ago = SystemTime::now() - Duration::from_secs(365 * 86400);
+    fs::File::options()
+        .write(true)
+        .open(dir.path().join("stale/old.bin"))
+        .unwrap()
+        .set_modified(year_ago)
+        .unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--older-than", "30d", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("stale"))
+        .stdout(predicate::str::contains("fresh").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--newer-than", "1d", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fresh"))
+        .stdout(predicate::str::contains("stale").not());
+
+    // Ages beyond what SystemTime can hold still filter instead of being ignored
+    cmd()
+        .arg(dir.path())
+        .args(["--older-than", "500000000000y", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("stale").not())
+        .stdout(predicate::str::contains("fresh").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--newer-than", "500000000000y", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("stale"))
+        .stdout(predicate::str::contains("fresh"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--older-than", "soon"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Cannot access path"));
+        .stderr(predicate::str::contains("invalid age"));
 }
 
+#[cfg(unix)]
 #[test]
-fn test_file_instead_of_directory() {
+fn test_show_errors_flag() {
     let dir = tempdir().unwrap();
-    let file_path = dir.path().join("file.txt");
-    fs::write(&file_path, "content").unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    std::os::unix::fs::symlink("/nonexistent", dir.path().join("data/broken")).unwrap();
 
     cmd()
-        .arg(&file_path)
+        .arg(dir.path())
+        .args(["--follow-symlinks", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Errors:"))
+        .stdout(predicate::str::contains("Inaccessible entries:").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--follow-symlinks", "--no-color", "--show-errors"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Inaccessible entries:"))
+        .stdout(predicate::str::contains("data/broken"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--follow-symlinks", "--show-errors", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""errors": ["#));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fail_on_error_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/file.txt"), "content").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--fail-on-error", "-L"])
+        .assert()
+        .success();
+
+    // A dangling symlink fails to resolve even when running as root,
+    // unlike a permission-denied directory
+    std::os::unix::fs::symlink("/nonexistent", dir.path().join("data/broken")).unwrap();
+
+    cmd().arg(dir.path()).arg("-L").assert().success();
+
+    cmd().arg(dir.path()).arg("--no-such-flag").assert().code(1);
+
+    cmd()
+        .arg(dir.path())
+        .args(["--fail-on-error", "-L", "--format", "json"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(r#""error_count": 1"#))
+        .stderr(predicate::str::contains("1 entries could not be read"));
+}
+
+#[test]
+fn test_units_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/file.bin"), "x".repeat(1_000_000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--no-color", "--units", "si"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 MB"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("976.56 KiB"));
+}
+
+#[test]
+fn test_locale_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/file.bin"), "x".repeat(1_000_000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--no-color", "--locale", "period"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("976,56 KiB"));
+
+    // The environment's locale applies unless --locale is given
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--no-color"])
+        .env("LC_NUMERIC", "fr_FR.UTF-8")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("976,56 KiB"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--no-color", "--locale", "none"])
+        .env("LC_ALL", "de_DE.UTF-8")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("976.56 KiB"));
+}
+
+#[test]
+fn test_progress_hidden_in_ci() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("file.txt"), "x").unwrap();
+    let spinner = predicate::str::contains("Done!")
+        .or(predicate::str::contains("\x1b["))
+        .or(predicate::str::is_match("[⠁⠂⠄⡀⢀⠠⠐⠈]").unwrap());
+
+    cmd()
+        .arg(dir.path())
+        .env("CI", "true")
+        .assert()
+        .success()
+        .stderr(spinner.not());
+
+    // --force-progress draws it anyway, even though stderr is captured
+    cmd()
+        .arg(dir.path())
+        .arg("--force-progress")
+        .env("CI", "true")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Done!"));
+}
+
+#[test]
+fn test_precision_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/file.bin"), "x".repeat(1_000_000)).unwrap();
+
+    for (precision, size) in [
+        ("0", "977 KiB"),
+        ("4", "976.5625 KiB"),
+        ("9", "976.562500 KiB"),
+    ] {
+        cmd()
+            .arg(dir.path())
+            .args(["--apparent-size", "--no-color", "--precision", precision])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(size));
+    }
+
+    // Combines with the locale's decimal separator
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--no-color", "--precision", "1"])
+        .args(["--locale", "period"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("976,6 KiB"));
+}
+
+#[test]
+fn test_bytes_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/file.bin"), "x".repeat(123_457)).unwrap();
+    fs::write(dir.path().join("small.txt"), "x".repeat(10)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args([
+            "--bytes",
+            "--apparent-size",
+            "--no-color",
+            "--all",
+            "-n",
+            "2",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total size: 123467"))
+        .stdout(predicate::str::is_match(r"(?m)^\s+123457\s+📄 data/file\.bin$").unwrap())
+        .stdout(predicate::str::contains("small.txt").not())
+        .stdout(predicate::str::contains("KiB").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--bytes", "--units", "si"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_count_zero_shows_all() {
+    let dir = tempdir().unwrap();
+    for i in 0..15 {
+        fs::create_dir(dir.path().join(format!("dir{i:02}"))).unwrap();
+    }
+
+    cmd()
+        .arg(dir.path())
+        .args(["-n", "0", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("All entries by size:"))
+        .stdout(predicate::str::contains("📁").count(15));
+
+    cmd()
+        .arg(dir.path())
+        .args(["-n", "0", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(",dir,").count(15));
+}
+
+#[test]
+fn test_summary_only_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/file.txt"), "content").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--summary-only", "--quiet", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total size"))
+        .stdout(predicate::str::contains("Scanned: 1 files, 1 directories"))
+        .stdout(predicate::str::contains("by size").not())
+        .stdout(predicate::str::contains("📁 data").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--summary-only", "--apparent-size", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "path,total_size_bytes,file_count,dir_count,error_count\n",
+        ))
+        .stdout(predicate::str::contains(",7,1,1,0\n"));
+}
+
+#[test]
+fn test_disk_info() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("file.txt"), "content").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r"Disk: .+ used of .+ \(scan is \d+\.\d% of disk\)").unwrap(),
+        );
+
+    cmd()
+        .arg(dir.path())
+        .args(["--no-color", "--no-disk-info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Disk:").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--no-disk-info", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""disk""#).not());
+}
+
+#[test]
+fn test_regex_flags() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/keep.txt"), "x".repeat(10)).unwrap();
+    fs::write(dir.path().join("data/drop.tmp"), "x".repeat(1000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--all", "--format", "csv"])
+        .args(["--exclude-regex", r".*\.tmp$"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("data,10,dir"))
+        .stdout(predicate::str::contains("drop.tmp").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--include-regex", "("])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("is not a directory"));
+        .stderr(predicate::str::contains("invalid regex '('"));
 }
 
 #[test]
-fn test_depth_flag() {
+fn test_empty_dirs_flag() {
     let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("old/logs/2020")).unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
 
-    // Create nested structure
-    let level1 = dir.path().join("level1");
-    let level2 = level1.join("level2");
-    let level3 = level2.join("level3");
+    cmd()
+        .arg(dir.path())
+        .args(["--empty-dirs", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3 empty directories:"))
+        .stdout(predicate::str::is_match(r"📁 old\n\s+📁 old/logs\n").unwrap())
+        .stdout(predicate::str::contains("📁 src").not());
 
-    fs::create_dir_all(&level3).unwrap();
-    fs::write(level3.join("deep.txt"), "deep content").unwrap();
+    cmd()
+        .arg(dir.path())
+        .args(["--empty-dirs", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "old,0,dir,1\nold/logs,0,dir,2\nold/logs/2020,0,dir,3\n",
+        ));
 
-    cmd().arg(dir.path()).args(["-d", "1"]).assert().success();
+    // A directory whose files are all excluded still has them on disk
+    fs::create_dir_all(dir.path().join("tmp")).unwrap();
+    fs::write(dir.path().join("tmp/debug.log"), "x").unwrap();
+    cmd()
+        .arg(dir.path())
+        .args(["--empty-dirs", "--exclude", "*.log", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old/logs/2020,"))
+        .stdout(predicate::str::contains("tmp").not());
+}
+
+#[test]
+fn test_progress_bar_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub/file.txt"), "hello").unwrap();
+
+    // The counting pass doesn't change the report
+    cmd()
+        .arg(dir.path())
+        .args(["--progress-bar", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("sub"))
+        .stdout(predicate::str::contains("1 files, 1 directories"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--progress-bar", "--quiet"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_check_flag() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("file.txt");
+    fs::write(&file, "not a directory").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--check")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("ok: "))
+        .stdout(predicate::str::contains("Disk Usage Report").not());
+
+    // Every path is reported, and any failure makes the check fail
+    cmd()
+        .arg(dir.path())
+        .arg(&file)
+        .arg(dir.path().join("missing"))
+        .arg("--check")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("ok: "))
+        .stderr(predicate::str::contains("is not a directory"))
+        .stderr(predicate::str::contains("No such file or directory"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--check", "--tree"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_block_size_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("notes")).unwrap();
+    for i in 0..3 {
+        fs::write(dir.path().join(format!("notes/{i}.txt")), "tiny").unwrap();
+    }
+
+    cmd()
+        .arg(dir.path())
+        .args(["--block-size", "4K", "--bytes", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total size: 12288"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--block-size", "4096", "--apparent-size"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_max_results_flag() {
+    let dir = tempdir().unwrap();
+    for (name, size) in [("small", 10_000), ("medium", 50_000), ("large", 100_000)] {
+        fs::create_dir_all(dir.path().join(name)).unwrap();
+        fs::write(dir.path().join(name).join("data.bin"), vec![0u8; size]).unwrap();
+    }
+
+    cmd()
+        .arg(dir.path())
+        .args(["--max-results", "2", "--apparent-size", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("large"))
+        .stdout(predicate::str::contains("medium"))
+        .stdout(predicate::str::contains("small").not())
+        .stdout(predicate::str::contains("3 directories"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--max-results", "2", "--tree"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_config_file() {
+    let dir = tempdir().unwrap();
+    let data = dir.path().join("data");
+    for i in 1..=4 {
+        fs::create_dir_all(data.join(format!("dir{i}"))).unwrap();
+        fs::write(data.join(format!("dir{i}/file")), "x".repeat(i * 1000)).unwrap();
+    }
+    let config_dir = dir.path().join("config");
+    fs::create_dir_all(config_dir.join("disk-scanner")).unwrap();
+    fs::write(
+        config_dir.join("disk-scanner/config.toml"),
+        "count = 2\nexclude = [\"dir4\"]\n",
+    )
+    .unwrap();
+
+    // The configured count applies when -n is omitted
+    cmd()
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .arg(&data)
+        .args(["--apparent-size", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📁 dir3"))
+        .stdout(predicate::str::contains("📁 dir2"))
+        .stdout(predicate::str::contains("📁 dir1").not())
+        .stdout(predicate::str::contains("📁 dir4").not());
+
+    // -n wins over the config, whose exclude still applies
+    cmd()
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .arg(&data)
+        .args(["-n", "10", "--apparent-size", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📁 dir1"))
+        .stdout(predicate::str::contains("📁 dir4").not());
+
+    // --no-config ignores the file
+    cmd()
+        .env("XDG_CONFIG_HOME", &config_dir)
+        .arg(&data)
+        .args(["--no-config", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📁 dir4"));
+
+    let bad = dir.path().join("bad.toml");
+    fs::write(&bad, "cuont = 3\n").unwrap();
+    cmd()
+        .arg(&data)
+        .arg("--config")
+        .arg(&bad)
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Unknown option 'cuont'"));
+}
+
+#[test]
+fn test_exclude_hidden_files_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join(".config")).unwrap();
+    fs::write(dir.path().join(".config/settings.json"), "x".repeat(3000)).unwrap();
+    fs::write(dir.path().join(".config/.secret"), "x".repeat(2000)).unwrap();
+    fs::write(dir.path().join(".bashrc"), "x".repeat(1000)).unwrap();
+    fs::write(dir.path().join("notes.txt"), "x".repeat(1000)).unwrap();
+
+    // The hidden directory is listed with its full size; hidden files are not
+    cmd()
+        .arg(dir.path())
+        .args([
+            "--all",
+            "--exclude-hidden-files",
+            "--bytes",
+            "--apparent-size",
+        ])
+        .args(["--no-color", "--no-disk-info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total size: 7000"))
+        .stdout(predicate::str::contains("5000  📁 .config"))
+        .stdout(predicate::str::contains("settings.json"))
+        .stdout(predicate::str::contains("notes.txt"))
+        .stdout(predicate::str::contains(".secret").not())
+        .stdout(predicate::str::contains(".bashrc").not());
+}
+
+#[test]
+fn test_exclude_empty_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("empty")).unwrap();
+    fs::create_dir(dir.path().join("logs")).unwrap();
+    fs::create_dir(dir.path().join("photos")).unwrap();
+    fs::write(dir.path().join("placeholder.jpg"), "").unwrap();
+    fs::write(dir.path().join("logs/app.log"), "x".repeat(5000)).unwrap();
+    fs::write(dir.path().join("photos/cat.jpg"), "x".repeat(5000)).unwrap();
+
+    // "logs" only holds files skipped by --ext, so its total is zero too
+    cmd()
+        .arg(dir.path())
+        .args(["--all", "--exclude-empty", "--ext", "jpg", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📁 photos"))
+        .stdout(predicate::str::contains("cat.jpg"))
+        .stdout(predicate::str::contains("📁 empty").not())
+        .stdout(predicate::str::contains("📁 logs").not())
+        .stdout(predicate::str::contains("placeholder.jpg").not());
+}
+
+#[test]
+fn test_min_files_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("dense")).unwrap();
+    for i in 0..100 {
+        fs::write(dir.path().join(format!("dense/{i}.txt")), "x").unwrap();
+    }
+    fs::create_dir(dir.path().join("sparse")).unwrap();
+    fs::write(dir.path().join("sparse/big.bin"), vec![0u8; 200_000]).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--min-files", "50", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📁 dense"))
+        .stdout(predicate::str::contains("📁 sparse").not());
+
+    // Both thresholds must be met
+    cmd()
+        .arg(dir.path())
+        .args([
+            "--min-files",
+            "50",
+            "--min-size",
+            "1MB",
+            "--apparent-size",
+            "--no-color",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📁 dense").not());
+}
+
+#[test]
+fn test_stream_flag() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("huge.bin"), vec![0u8; 100_000]).unwrap();
+    fs::write(dir.path().join("tiny.txt"), "hi").unwrap();
+
+    // Streamed lines come before the final report on stdout
+    let output = cmd()
+        .arg(dir.path())
+        .args([
+            "--stream",
+            "--min-size",
+            "50K",
+            "--apparent-size",
+            "--no-color",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let streamed = stdout.find("huge.bin").unwrap();
+    assert!(streamed < stdout.find("Disk Usage Report").unwrap());
+    assert!(!stdout.contains("tiny.txt"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--stream", "--format", "json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("requires --output"));
+}
+
+#[test]
+fn test_show_avg_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("tiny")).unwrap();
+    fs::create_dir_all(dir.path().join("empty")).unwrap();
+    for i in 0..4 {
+        fs::write(dir.path().join(format!("tiny/{i}.txt")), "x".repeat(256)).unwrap();
+    }
+
+    cmd()
+        .arg(dir.path())
+        .args(["--show-avg", "--apparent-size", "--bytes", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("AVG FILE"))
+        .stdout(predicate::str::is_match(r"1024\s+256\s+📁 tiny").unwrap())
+        .stdout(predicate::str::is_match(r"0\s+-\s+📁 empty").unwrap());
+
+    cmd()
+        .arg(dir.path())
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("AVG FILE").not());
+}
+
+#[test]
+fn test_single_threaded_scan() {
+    let dir = tempdir().unwrap();
+    for name in ["b/2.txt", "b/1.txt", "a/z.txt", "c.txt", "a/y/x.txt"] {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "same size").unwrap();
+    }
+
+    let run = || {
+        cmd()
+            .arg(dir.path())
+            .args(["--threads", "1", "--all", "--format", "csv"])
+            .output()
+            .unwrap()
+    };
+    let first = run();
+    assert!(first.status.success());
+    for _ in 0..3 {
+        assert_eq!(run().stdout, first.stdout);
+    }
+
+    cmd()
+        .arg(dir.path())
+        .args(["--threads", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("use 1 for a single-threaded scan"));
+}
+
+#[test]
+fn test_markdown_format() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs/guide.md"), "x".repeat(2048)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--format", "markdown", "--apparent-size", "--bytes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "| Size | % | Path |\n|---:|---:|---|\n",
+        ))
+        .stdout(predicate::str::contains("| 2048 | 100.0% | `docs` |"))
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_html_format() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("media")).unwrap();
+    fs::write(dir.path().join("media/movie.mkv"), "x".repeat(5000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--format", "html", "--all", "--apparent-size"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("<!DOCTYPE html>"))
+        .stdout(predicate::str::contains("<table>"))
+        .stdout(predicate::str::contains(
+            "<td class=\"num\" data-value=\"5000\">4.88 KiB</td>",
+        ))
+        .stdout(predicate::str::contains(
+            "<td><code>media/movie.mkv</code></td></tr>",
+        ))
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+// Newlines in file names are Unix-only
+#[cfg(unix)]
+#[test]
+fn test_print0_flag() {
+    let dir = tempdir().unwrap();
+    for (name, size) in [("big dir", 3000), ("new\nline", 2000), ("small", 10)] {
+        fs::create_dir_all(dir.path().join(name)).unwrap();
+        fs::write(dir.path().join(name).join("data.bin"), vec![0u8; size]).unwrap();
+    }
+    let root = dir.path().canonicalize().unwrap();
+
+    let output = cmd()
+        .arg(dir.path())
+        .args(["-0", "--apparent-size", "--count", "2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let paths: Vec<&[u8]> = output.stdout.split(|&b| b == 0).collect();
+    let expected = [root.join("big dir"), root.join("new\nline")];
+    assert_eq!(paths.len(), 3, "two NUL-terminated paths");
+    for (path, expected) in paths.iter().zip(&expected) {
+        assert_eq!(*path, expected.as_os_str().as_encoded_bytes());
+    }
+    assert!(paths[2].is_empty());
+
+    // Filters apply as in the report
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--print0", "--apparent-size", "--all", "--min-size", "1K"])
+        .output()
+        .unwrap();
+    let count = output.stdout.iter().filter(|&&b| b == 0).count();
+    assert_eq!(count, 4, "two directories and their files");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("small"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["-0", "--format", "json"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_show_files_matching_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("downloads")).unwrap();
+    fs::write(dir.path().join("downloads/ubuntu.iso"), vec![0u8; 5000]).unwrap();
+    fs::write(dir.path().join("downloads/readme.txt"), vec![0u8; 4000]).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--show-files-matching", "*.iso", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("📁 downloads"))
+        .stdout(predicate::str::contains("📄 downloads/ubuntu.iso"))
+        .stdout(predicate::str::contains("readme.txt").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--show-files-matching", "*.iso", "--all"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_self_size_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("nested/inner")).unwrap();
+    fs::create_dir_all(dir.path().join("flat")).unwrap();
+    fs::write(dir.path().join("nested/inner/big.bin"), vec![0u8; 4000]).unwrap();
+    fs::write(dir.path().join("nested/small.bin"), vec![0u8; 100]).unwrap();
+    fs::write(dir.path().join("flat/mid.bin"), vec![0u8; 3000]).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--self-size", "--apparent-size", "--bytes", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("SELF"))
+        .stdout(predicate::str::is_match(r"4100\s+100\s+📁 nested\n").unwrap());
+
+    // Sorting by self size puts the directory holding its own files first
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--sort", "self-size", "--apparent-size", "--format", "csv"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let paths: Vec<&str> = stdout
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap())
+        .collect();
+    assert_eq!(paths, ["nested/inner", "flat", "nested"]);
+}
+
+#[test]
+fn test_show_mtime_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("old")).unwrap();
+    fs::create_dir_all(dir.path().join("empty")).unwrap();
+    let file = fs::File::create(dir.path().join("old/data.bin")).unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(3 * 86_400 + 60))
+        .unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--show-mtime", "--all", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MODIFIED"))
+        .stdout(predicate::str::is_match(r"3 days ago\s+📁 old").unwrap())
+        .stdout(predicate::str::is_match(r"3 days ago\s+📄 old/data.bin").unwrap())
+        .stdout(predicate::str::is_match(r"-\s+📁 empty").unwrap());
+}
+
+#[test]
+fn test_relative_to_flag() {
+    let dir = tempdir().unwrap();
+    let project = dir.path().join("project");
+    fs::create_dir_all(project.join("src/parser")).unwrap();
+    fs::write(project.join("src/parser/mod.rs"), "content").unwrap();
+
+    let output = cmd()
+        .arg(project.join("src"))
+        .arg("--relative-to")
+        .arg(&project)
+        .args(["--format", "csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nsrc/parser,"), "{}", stdout);
+
+    // A base the scan root isn't under leaves paths absolute
+    let other = dir.path().join("other");
+    fs::create_dir_all(&other).unwrap();
+    let scanned = project.canonicalize().unwrap().join("src").join("parser");
+    cmd()
+        .arg(project.join("src"))
+        .arg("--relative-to")
+        .arg(&other)
+        .args(["--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("{},", scanned.display())));
+
+    cmd()
+        .arg(&project)
+        .args(["--relative-to", "/nonexistent/base"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("/nonexistent/base"));
+}
+
+#[test]
+fn test_absolute_paths_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("logs")).unwrap();
+    fs::write(dir.path().join("logs/app.log"), "content").unwrap();
+    let logs = dir.path().canonicalize().unwrap().join("logs");
+
+    for format in ["table", "csv", "markdown"] {
+        let output = cmd()
+            .arg(dir.path())
+            .args([
+                "--absolute-paths",
+                "--no-color",
+                "--width",
+                "200",
+                "--format",
+                format,
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains(&logs.display().to_string()),
+            "{}: {}",
+            format,
+            stdout
+        );
+    }
+
+    cmd()
+        .arg(dir.path())
+        .args(["--absolute-paths", "--relative-to", "/"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cache_flag() {
+    let dir = tempdir().unwrap();
+    let scan_root = dir.path().join("data");
+    fs::create_dir_all(scan_root.join("subdir")).unwrap();
+    fs::write(scan_root.join("subdir/file.txt"), "content").unwrap();
+    let cache = dir.path().join("cache.json");
+
+    cmd()
+        .arg(&scan_root)
+        .arg("--cache")
+        .arg(&cache)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("reused from cache").not());
+    assert!(cache.exists());
+
+    cmd()
+        .arg(&scan_root)
+        .arg("--cache")
+        .arg(&cache)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 directories reused from cache"));
+
+    // A corrupt cache falls back to a full scan
+    fs::write(&cache, "garbage").unwrap();
+    cmd()
+        .arg(&scan_root)
+        .arg("--cache")
+        .arg(&cache)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("subdir"))
+        .stderr(predicate::str::contains("ignoring cache"));
+}
+
+#[test]
+fn test_nonexistent_path() {
+    cmd()
+        .arg("/nonexistent/path/that/does/not/exist")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot access path"));
+}
+
+#[test]
+fn test_file_instead_of_directory() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("file.txt");
+    fs::write(&file_path, "content").unwrap();
+
+    cmd()
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not a directory"));
+}
+
+#[test]
+fn test_depth_flag() {
+    let dir = tempdir().unwrap();
+
+    // Create nested structure
+    let level1 = dir.path().join("level1");
+    let level2 = level1.join("level2");
+    let level3 = level2.join("level3");
+
+    fs::create_dir_all(&level3).unwrap();
+    fs::write(level3.join("deep.txt"), "deep content").unwrap();
+
+    cmd().arg(dir.path()).args(["-d", "1"]).assert().success();
+}
+
+#[test]
+fn test_json_format() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("subdir")).unwrap();
+    fs::write(dir.path().join("subdir/file.txt"), "content").unwrap();
+
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["schema"], JSON_SCHEMA);
+    assert_eq!(json["file_count"], 1);
+    assert_eq!(json["dir_count"], 1);
+    assert_eq!(json["nodes"][0]["is_dir"], true);
+    assert_eq!(json["nodes"][0]["depth"], 1);
+    assert!(json["scan_duration_ms"].is_u64());
+    assert!(json["files_per_second"].is_number());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Disk Usage Report"));
+}
+
+#[test]
+fn test_jsonl_format() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("a/b")).unwrap();
+    fs::write(dir.path().join("a/b/file.txt"), "content").unwrap();
+
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--format", "jsonl", "--all"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 4);
+    for node in &lines[..3] {
+        assert_eq!(node["type"], "node");
+        assert!(node["size"].is_u64());
+    }
+    let summary = &lines[3];
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["file_count"], 1);
+    assert_eq!(summary["dir_count"], 2);
+}
+
+#[test]
+fn test_csv_format() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("a,b")).unwrap();
+    fs::write(dir.path().join("a,b/file.txt"), "content").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--format", "csv", "--all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("path,size_bytes,type,depth\n"))
+        .stdout(predicate::str::contains("\"a,b\","))
+        .stdout(predicate::str::contains(",dir,1"))
+        .stdout(predicate::str::contains(",file,2"))
+        .stdout(predicate::str::contains("Disk Usage Report").not());
+}
+
+#[test]
+fn test_tree_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("big/inner")).unwrap();
+    fs::create_dir(dir.path().join("small")).unwrap();
+    fs::write(dir.path().join("big/inner/data.bin"), "x".repeat(10000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--tree")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Directory tree"))
+        .stdout(
+            predicate::str::is_match(r"├──[\s\S]*big[\s\S]*└──[\s\S]*inner[\s\S]*└──[\s\S]*small")
+                .unwrap(),
+        );
+
+    cmd()
+        .arg(dir.path())
+        .args(["--tree", "--collapse-under", "1K", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("└── (1 small dir: total 0 B)"))
+        .stdout(predicate::str::contains("📁 small").not());
+}
+
+#[test]
+fn test_reverse_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("big")).unwrap();
+    fs::create_dir(dir.path().join("empty")).unwrap();
+    fs::write(dir.path().join("big/data.bin"), "x".repeat(100_000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--reverse", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"empty,0,dir,1\nbig,").unwrap());
+}
+
+#[cfg(feature = "archives")]
+#[test]
+fn test_into_archives_flag() {
+    let dir = tempdir().unwrap();
+    let mut builder = tar::Builder::new(fs::File::create(dir.path().join("backup.tar")).unwrap());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(100_000);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "photos/2024/img.raw", &vec![0u8; 100_000][..])
+        .unwrap();
+    builder.finish().unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--into-archives", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("backup.tar/photos,100000,dir,2"))
+        .stdout(predicate::str::contains(
+            "backup.tar/photos/2024,100000,dir,3",
+        ));
+}
+
+#[test]
+fn test_max_scan_depth_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("shallow/deep")).unwrap();
+    fs::write(dir.path().join("shallow/near.bin"), "x".repeat(1000)).unwrap();
+    fs::write(dir.path().join("shallow/deep/far.bin"), "x".repeat(100_000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args([
+            "--max-scan-depth",
+            "2",
+            "--apparent-size",
+            "--bytes",
+            "--no-color",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total size: 1000"))
+        .stdout(predicate::str::contains("scan stopped at depth 2"));
+}
+
+#[test]
+fn test_include_root_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub/data.bin"), "x".repeat(100_000)).unwrap();
+    fs::write(dir.path().join("top.bin"), "x".repeat(50_000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--include-root", "--apparent-size", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\n.,150000,dir,0\nsub,100000,dir,1\n",
+        ));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--include-root", "--summary-only", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(",2,1,0"));
+}
+
+#[test]
+fn test_sort_by_name_and_depth() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("alpha/deep")).unwrap();
+    fs::create_dir(dir.path().join("beta")).unwrap();
+    fs::write(dir.path().join("beta/data.bin"), "x".repeat(100_000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--sort", "name", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\nalpha,.*\nalpha/deep,.*\nbeta,").unwrap());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--sort", "name", "--reverse", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\nbeta,.*\nalpha/deep,.*\nalpha,").unwrap());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--sort", "depth", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\nbeta,.*\nalpha,.*\nalpha/deep,").unwrap());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--sort", "name", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("First 10 by name"));
+}
+
+#[test]
+fn test_sort_by_count() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("big")).unwrap();
+    fs::create_dir(dir.path().join("many")).unwrap();
+    fs::write(dir.path().join("big/data.bin"), "x".repeat(100_000)).unwrap();
+    for i in 0..5 {
+        fs::write(dir.path().join(format!("many/{}.txt", i)), "x").unwrap();
+    }
+
+    cmd()
+        .arg(dir.path())
+        .args(["--sort", "count", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"many,\d+,dir,1\nbig,").unwrap());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--sort", "count"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("by file count"))
+        .stdout(predicate::str::contains("FILES"));
+}
+
+#[test]
+fn test_no_color_output() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("subdir")).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+
+    cmd()
+        .arg(dir.path())
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_quiet_flag() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("file.txt"), "content").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--quiet")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Disk Usage Report"))
+        .stderr(predicate::str::is_match("[⠁⠂⠄⡀⢀⠠⠐⠈]").unwrap().not())
+        .stderr(predicate::str::contains("Done!").not());
+}
+
+#[test]
+fn test_output_file() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("subdir")).unwrap();
+    let report = dir.path().join("report.txt");
+
+    cmd()
+        .arg(dir.path())
+        .arg("--output")
+        .arg(&report)
+        .env_remove("NO_COLOR")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    let contents = fs::read_to_string(&report).unwrap();
+    assert!(contents.contains("Disk Usage Report"));
+    assert!(contents.contains("subdir"));
+    assert!(!contents.contains("\x1b["));
+}
+
+#[test]
+fn test_output_file_missing_parent() {
+    let dir = tempdir().unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--output")
+        .arg(dir.path().join("missing/report.json"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to create output file"));
+}
+
+#[test]
+fn test_interactive_requires_terminal() {
+    let dir = tempdir().unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--interactive")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("requires stdout to be a terminal"));
+}
+
+#[test]
+fn test_by_type_flag() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("movie.mp4"), "x".repeat(100_000)).unwrap();
+    fs::write(dir.path().join("notes.txt"), "x").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--by-type")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("file types by size"))
+        .stdout(predicate::str::is_match(r"1 files  \.mp4[\s\S]*1 files  \.txt").unwrap());
+}
+
+#[test]
+fn test_histogram_flag() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("small.txt"), "x".repeat(10)).unwrap();
+    fs::write(dir.path().join("large.bin"), "x".repeat(50_000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--histogram", "--apparent-size", "--bytes"])
+        .args(["--histogram-buckets", "1K,10K"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("File size histogram"))
+        .stdout(predicate::str::is_match(r"< 1024 +1 files +10 ").unwrap())
+        .stdout(predicate::str::is_match(r"1024 - 10240 +0 files").unwrap())
+        .stdout(predicate::str::is_match(r">= 10240 +1 files +50000 ").unwrap())
+        .stdout(predicate::str::contains("small.txt").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--histogram-buckets", "10K,1K"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("must be positive and increasing"));
+}
+
+#[test]
+fn test_by_depth_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+    fs::create_dir(dir.path().join("d")).unwrap();
+    fs::write(dir.path().join("a/b/c/deep.bin"), "x".repeat(6000)).unwrap();
+    fs::write(dir.path().join("d/top.bin"), "x".repeat(2000)).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--by-depth", "--apparent-size", "--bytes", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Directories by depth"))
+        .stdout(predicate::str::is_match(r"depth   1 +2 dirs +8000 +100\.0%").unwrap())
+        .stdout(predicate::str::is_match(r"depth   2 +1 dirs +6000 +75\.0%").unwrap())
+        .stdout(predicate::str::is_match(r"depth   3 +1 dirs +6000 +75\.0%").unwrap());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--by-depth", "--apparent-size", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout("depth,dir_count,size_bytes\n1,2,8000\n2,1,6000\n3,1,6000\n");
+}
+
+#[test]
+fn test_duplicates_flag() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.txt"), "duplicate").unwrap();
+    fs::write(dir.path().join("b.txt"), "duplicate").unwrap();
+    fs::write(dir.path().join("c.txt"), "different").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("--duplicates")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 copies of"))
+        .stdout(predicate::str::contains("a.txt"))
+        .stdout(predicate::str::contains("b.txt"))
+        .stdout(predicate::str::contains("c.txt").not());
+}
+
+#[test]
+fn test_compare_with_baseline() {
+    let dir = tempdir().unwrap();
+    let scan_root = dir.path().join("data");
+    fs::create_dir_all(scan_root.join("growing")).unwrap();
+    fs::create_dir(scan_root.join("old")).unwrap();
+    fs::write(scan_root.join("growing/a.bin"), "x".repeat(1000)).unwrap();
+    fs::write(scan_root.join("old/b.bin"), "x".repeat(1000)).unwrap();
+    let baseline = dir.path().join("baseline.json");
+
+    cmd()
+        .arg(&scan_root)
+        .args([
+            "--apparent-size",
+            "--format",
+            "json",
+            "-n",
+            "1000",
+            "--output",
+        ])
+        .arg(&baseline)
+        .assert()
+        .success();
+
+    fs::write(scan_root.join("growing/c.bin"), "x".repeat(100_000)).unwrap();
+    fs::remove_dir_all(scan_root.join("old")).unwrap();
+    fs::create_dir(scan_root.join("fresh")).unwrap();
+    fs::write(scan_root.join("fresh/d.bin"), "x".repeat(10)).unwrap();
+
+    cmd()
+        .arg(&scan_root)
+        .args(["--apparent-size", "--format", "csv", "--compare"])
+        .arg(&baseline)
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "path,old_size_bytes,new_size_bytes,change_bytes\n\
+             growing,1000,101000,100000\n\
+             old,1000,,-1000\n\
+             fresh,,10,10\n",
+        ));
+
+    cmd()
+        .arg(&scan_root)
+        .args(["--apparent-size", "--compare"])
+        .arg(&baseline)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("changes since baseline"))
+        .stdout(
+            predicate::str::is_match(
+                r"\+97\.66 KiB  grew[\s\S]*-1000 B  -gone[\s\S]*\+10 B  \+new",
+            )
+            .unwrap(),
+        );
+
+    // Baselines from a newer schema version are rejected; unversioned ones still load
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&baseline).unwrap()).unwrap();
+    let mut newer = json.clone();
+    newer["schema"] = 99.into();
+    fs::write(&baseline, newer.to_string()).unwrap();
+    cmd()
+        .arg(&scan_root)
+        .arg("--compare")
+        .arg(&baseline)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("has JSON schema 99, newer than"));
+
+    let mut unversioned = json;
+    unversioned.as_object_mut().unwrap().remove("schema");
+    fs::write(&baseline, unversioned.to_string()).unwrap();
+    cmd()
+        .arg(&scan_root)
+        .arg("--compare")
+        .arg(&baseline)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_compare_with_schema_1_baseline() {
+    // A report as saved by the first versioned format still works as a baseline
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("grew")).unwrap();
+    fs::write(dir.path().join("grew/data.bin"), vec![0u8; 5000]).unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let baseline = dir.path().join("baseline.json");
+    let report = serde_json::json!({
+        "schema": 1,
+        "root": root,
+        "total_size": 1000,
+        "file_count": 1,
+        "dir_count": 1,
+        "error_count": 0,
+        "cycles_skipped": 0,
+        "scan_duration_ms": 3,
+        "files_per_second": 333.3,
+        "nodes": [{
+            "path": root.join("grew"),
+            "size": 1000,
+            "apparent_size": 1000,
+            "file_count": 1,
+            "is_dir": true,
+            "depth": 1,
+            "accessible": true
+        }]
+    });
+    fs::write(&baseline, report.to_string()).unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--apparent-size", "--bytes", "--no-color", "--compare"])
+        .arg(&baseline)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\+4000\s+grew").unwrap());
+}
+
+#[test]
+fn test_multiple_paths() {
+    let first = tempdir().unwrap();
+    let second = tempdir().unwrap();
+    fs::create_dir(first.path().join("alpha")).unwrap();
+    fs::create_dir(second.path().join("beta")).unwrap();
+
+    cmd()
+        .arg(first.path())
+        .arg(second.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alpha"))
+        .stdout(predicate::str::contains("beta"))
+        .stdout(predicate::str::contains("Disk Usage Report").count(2));
+
+    cmd()
+        .arg(first.path())
+        .arg(second.path())
+        .arg("--merge")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alpha"))
+        .stdout(predicate::str::contains("beta"))
+        .stdout(predicate::str::contains("Disk Usage Report").count(1));
+}
+
+#[test]
+fn test_multiple_paths_validated_before_scan() {
+    let dir = tempdir().unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .arg("/nonexistent/path/that/does/not/exist")
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("Cannot access path"));
+}
+
+#[test]
+fn test_stdin_paths() {
+    let first = tempdir().unwrap();
+    let second = tempdir().unwrap();
+    fs::create_dir(first.path().join("alpha")).unwrap();
+    fs::create_dir(second.path().join("beta")).unwrap();
+
+    let input = format!(
+        "  {}  \n\n/nonexistent/stdin/path\n{}\n",
+        first.path().display(),
+        second.path().display()
+    );
+
+    cmd()
+        .arg("--stdin")
+        .arg("--merge")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alpha"))
+        .stdout(predicate::str::contains("beta"))
+        .stderr(predicate::str::contains("stdin line 3"));
+}
+
+#[test]
+fn test_stdin_without_valid_paths() {
+    cmd()
+        .arg("--stdin")
+        .write_stdin("\n/nonexistent/stdin/path\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No valid directories"));
+}
+
+#[test]
+fn test_min_depth_window() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("l1/l2/l3/l4")).unwrap();
+    fs::write(dir.path().join("l1/l2/l3/l4/deep.txt"), "deep").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--min-depth", "2", "--depth", "3", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("l1/l2,"))
+        .stdout(predicate::str::contains("l1/l2/l3,"))
+        .stdout(predicate::str::contains("l1,").not())
+        .stdout(predicate::str::contains("l4").not());
+
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--min-depth", "2", "--depth", "3", "--format", "json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["dir_count"], 4);
+    assert_eq!(json["file_count"], 1);
+}
+
+#[test]
+fn test_interactive_delete_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("junk")).unwrap();
+    fs::write(dir.path().join("junk/old.log"), "x".repeat(5000)).unwrap();
+    fs::create_dir(dir.path().join("keep")).unwrap();
+    fs::write(dir.path().join("keep/a.txt"), "a").unwrap();
+
+    // Without confirmation nothing is deleted
+    cmd()
+        .arg(dir.path())
+        .args([
+            "--interactive-delete",
+            "--apparent-size",
+            "--bytes",
+            "--quiet",
+        ])
+        .write_stdin("1\nn\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1.         5000  junk/"))
+        .stdout(predicate::str::contains(
+            "Dry run: nothing deleted (would free 5000)",
+        ));
+    assert!(dir.path().join("junk").exists());
+
+    cmd()
+        .arg(dir.path())
+        .args([
+            "--interactive-delete",
+            "--yes",
+            "--apparent-size",
+            "--bytes",
+            "--quiet",
+        ])
+        .write_stdin("1\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Freed 5000 (1 of 1 entries deleted)",
+        ));
+    assert!(!dir.path().join("junk").exists());
+    assert!(dir.path().join("keep/a.txt").exists());
+
+    cmd().arg(dir.path()).args(["--yes"]).assert().failure();
+}
+
+#[test]
+fn test_profile_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/file.txt"), "content").unwrap();
+
+    let output = cmd().arg(dir.path()).arg("--profile").output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for phase in [
+        "Time profile",
+        "walk",
+        "aggregate",
+        "filter and sort",
+        "display",
+        "total",
+    ] {
+        assert!(stderr.contains(phase), "missing {phase} in {stderr}");
+    }
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Time profile"));
+}
+
+#[test]
+fn test_stats_flag() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub/file.txt"), "hello").unwrap();
+
+    cmd()
+        .arg(dir.path())
+        .args(["--stats", "--threads", "2", "--quiet", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Scan statistics").not())
+        .stderr(predicate::str::contains("Threads: 2"))
+        .stderr(predicate::str::contains("thread  1:"))
+        .stderr(predicate::str::contains("walking"))
+        .stderr(predicate::str::contains("lock waits").not());
+
+    cmd()
+        .arg(dir.path())
+        .args(["--stats", "--follow-symlinks", "--quiet", "--no-color"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(r"Visited-directory lock waits: \d+").unwrap());
+}
+
+#[test]
+fn test_width_flag() {
+    let dir = tempdir().unwrap();
+
+    // Not a terminal here, so the default width applies
+    cmd()
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\n{}\n", "═".repeat(70))));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--width", "100"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("\n{}\n", "═".repeat(100))));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--width", "10"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_path_expansion() {
+    let home = tempdir().unwrap();
+    fs::create_dir(home.path().join("Downloads")).unwrap();
+    let expected = home.path().canonicalize().unwrap().join("Downloads");
+
+    for path in ["~/Downloads", "$HOME/Downloads", "${HOME}/Downloads"] {
+        cmd()
+            .env("HOME", home.path())
+            .arg(path)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "Disk Usage Report: {}",
+                expected.display()
+            )));
+    }
+
+    cmd()
+        .env("HOME", home.path())
+        .arg("~/Missing")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("(expanded from '~/Missing')"));
+
+    cmd()
+        .env_remove("DISK_SCANNER_UNSET")
+        .arg("$DISK_SCANNER_UNSET/x")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot expand path"));
+
+    // An existing directory with a literal `$` keeps working
+    fs::create_dir(home.path().join("$cash")).unwrap();
+    cmd().arg(home.path().join("$cash")).assert().success();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_user_home_expansion() {
+    // root exists everywhere; its home is /root on Linux and /var/root on macOS
+    let root_home =
+        fs::canonicalize(nix::unistd::User::from_name("root").unwrap().unwrap().dir).unwrap();
+    cmd()
+        .args(["~root", "--check"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "ok: {}",
+            root_home.display()
+        )));
+
+    cmd()
+        .arg("~disk-scanner-no-such-user/data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "unknown user 'disk-scanner-no-such-user'",
+        ));
+
+    // A relative path that really starts with `~` is used as given
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("~nobody-here")).unwrap();
+    cmd()
+        .current_dir(dir.path())
+        .arg("~nobody-here")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_ncdu_format() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("a/b")).unwrap();
+    fs::write(dir.path().join("a/b/deep.bin"), "x".repeat(3000)).unwrap();
+    fs::write(dir.path().join("top.txt"), "hello").unwrap();
+
+    // Files are exported without --all, and display filters like --depth are ignored
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--format", "ncdu", "--depth", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0], 1);
+    assert_eq!(json[1], 2);
+    let root = json[3].as_array().unwrap();
+    let root_path = dir.path().canonicalize().unwrap();
+    assert_eq!(root[0]["name"], root_path.to_str().unwrap());
+    let a = root.iter().find(|e| e[0]["name"] == "a").unwrap();
+    assert_eq!(a[1][0]["name"], "b");
+    assert_eq!(a[1][1]["name"], "deep.bin");
+    assert_eq!(a[1][1]["asize"], 3000);
+    assert!(root.iter().any(|e| e["name"] == "top.txt"));
+
+    cmd()
+        .arg(dir.path())
+        .arg(dir.path().join("a"))
+        .args(["--format", "ncdu"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("single directory tree"));
+}
+
+#[cfg(feature = "emit")]
+#[test]
+fn test_emit_flag() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("subdir")).unwrap();
+    fs::write(dir.path().join("subdir/file.txt"), "content").unwrap();
+
+    // The report goes to the listener and nothing to stdout
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("tcp:{}", listener.local_addr().unwrap());
+    let reader = std::thread::spawn(move || {
+        let mut received = Vec::new();
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.read_to_end(&mut received).unwrap();
+        received
+    });
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--emit", &endpoint])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let json: serde_json::Value = serde_json::from_slice(&reader.join().unwrap()).unwrap();
+    assert_eq!(json["schema"], JSON_SCHEMA);
+    assert_eq!(json["file_count"], 1);
+
+    // Nothing listening is a clear error
+    cmd()
+        .arg(dir.path())
+        .args(["--emit", &endpoint, "--emit-timeout", "1"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Failed to connect to tcp:"));
+
+    cmd()
+        .arg(dir.path())
+        .args(["--emit", "ftp:host"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "expected unix:PATH or tcp:HOST:PORT",
+        ));
+}
+
+#[cfg(feature = "protobuf")]
+#[test]
+fn test_bin_format() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("subdir")).unwrap();
+    fs::write(dir.path().join("subdir/file.bin"), vec![0u8; 3000]).unwrap();
+    fs::write(dir.path().join("top.txt"), "content").unwrap();
+
+    let output = cmd()
+        .arg(dir.path())
+        .args(["--format", "bin", "--all", "--apparent-size"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // The stream decodes back into the same totals and entries
+    let reports = disk_scanner::proto::read_reports(&output.stdout).unwrap();
+    assert_eq!(reports.len(), 1);
+    let (root, result) = &reports[0];
+    assert_eq!(root, &dir.path().canonicalize().unwrap());
+    assert_eq!((result.total_size, result.file_count), (3007, 2));
+    let sizes: Vec<(&std::path::Path, u64)> = result
+        .nodes
+        .iter()
+        .map(|node| (node.path.strip_prefix(root).unwrap(), node.size))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            (std::path::Path::new("subdir"), 3000),
+            (std::path::Path::new("subdir/file.bin"), 3000),
+            (std::path::Path::new("top.txt"), 7),
+        ]
+    );
 }